@@ -9,6 +9,16 @@ pub struct Config {
     pub postgres_database: PostgresDatabaseConfig,
     pub endpoint_rate_limiters: EndpointRateLimitersConfig,
     pub cambrinary_session_tracker: CambrinarySessionTrackerConfig,
+    #[serde(default)]
+    pub batch_lookup: BatchLookupConfig,
+    /// Words are only cached in Redis when this is set
+    pub redis_cache: Option<RedisCacheConfig>,
+    #[serde(default)]
+    pub access_counts: AccessCountsConfig,
+    #[serde(default)]
+    pub negative_cache: NegativeCacheConfig,
+    /// Stored definitions are only fetched again when this is set
+    pub refresh: Option<RefreshConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -28,6 +38,13 @@ pub struct MetricsServerConfig {
     pub enabled: bool,
     pub host: String,
     pub port: u16,
+    /// Serves the `/admin` routes next to the metrics, the port must not
+    /// be reachable from outside
+    #[serde(default)]
+    pub enable_admin_routes: bool,
+    /// The `Authorization: Bearer` token the `/admin` routes require, they
+    /// can't be enabled without one
+    pub admin_token: Option<String>,
 }
 
 
@@ -61,6 +78,92 @@ pub struct CambrinarySessionTrackerConfig {
     pub session_acquire_cooldown_jitter_ms: u64,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct BatchLookupConfig {
+    pub max_words: usize,
+    /// A batch costs the rate limiters one request per this many words
+    pub words_per_request: usize,
+}
+
+impl Default for BatchLookupConfig {
+    fn default() -> Self {
+        Self {
+            max_words: 1000,
+            words_per_request: 25,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RedisCacheConfig {
+    /// e.g. `redis://127.0.0.1:6379`
+    pub url: String,
+    /// How long a word stays cached after it was read from or written to Postgres
+    #[serde(default = "default_word_ttl_s")]
+    pub word_ttl_s: u64,
+}
+
+fn default_word_ttl_s() -> u64 {
+    60 * 60 * 24
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct AccessCountsConfig {
+    /// Accesses are counted in memory and written to Postgres this often
+    pub flush_interval_ms: u64,
+}
+
+impl Default for AccessCountsConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval_ms: 10_000,
+        }
+    }
+}
+
+/// How long words without a meaning are answered from the database before
+/// they are fetched again.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct NegativeCacheConfig {
+    /// Cambridge has no entry for the word, it may get one
+    pub not_found_ttl_s: u64,
+    /// The entry page couldn't be parsed, usually fixed by a new release
+    pub parse_failure_ttl_s: u64,
+}
+
+impl Default for NegativeCacheConfig {
+    fn default() -> Self {
+        Self {
+            not_found_ttl_s: 60 * 60 * 24 * 30,
+            parse_failure_ttl_s: 60 * 60 * 24,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RefreshConfig {
+    /// Definitions fetched longer ago than this are fetched again
+    pub max_age_s: u64,
+    /// How long the job sleeps between two rounds
+    pub interval_s: u64,
+    /// How many words a round fetches at most, the most read first
+    pub batch_size: i64,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        Self {
+            max_age_s: 60 * 60 * 24 * 90,
+            interval_s: 60 * 60,
+            batch_size: 100,
+        }
+    }
+}
+
 impl Config {
     pub fn from_file(
         path: impl Into<std::path::PathBuf>,
@@ -4,5 +4,10 @@ pub use config::{
     Config,
     PostgresDatabaseConfig,
     RateLimiterConfig,
-    CambrinarySessionTrackerConfig
+    CambrinarySessionTrackerConfig,
+    BatchLookupConfig,
+    RedisCacheConfig,
+    AccessCountsConfig,
+    NegativeCacheConfig,
+    RefreshConfig
 };
\ No newline at end of file
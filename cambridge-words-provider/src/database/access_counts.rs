@@ -0,0 +1,73 @@
+use std::{collections::HashMap, sync::Mutex};
+
+/// How often a word was read since the last flush, and when it was read
+/// last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessCount {
+    pub count: i32,
+    pub last_access_at: i64,
+}
+
+/// Accesses of stored words counted in memory, so that they are written to
+/// Postgres in one `UPDATE` every flush instead of one per lookup. Counts
+/// that weren't flushed yet are lost when the server stops.
+#[derive(Default)]
+pub struct AccessCounts {
+    counts: Mutex<HashMap<String, AccessCount>>,
+}
+
+impl AccessCounts {
+    pub fn record(&self, words: &[String], accessed_at: i64) {
+        let mut counts = self.counts.lock().unwrap();
+        for word in words {
+            let access_count = counts.entry(word.to_lowercase()).or_insert(AccessCount {
+                count: 0,
+                last_access_at: accessed_at,
+            });
+            access_count.count += 1;
+            access_count.last_access_at = access_count.last_access_at.max(accessed_at);
+        }
+    }
+
+    pub fn take(&self) -> HashMap<String, AccessCount> {
+        std::mem::take(&mut *self.counts.lock().unwrap())
+    }
+
+    /// Puts back counts that couldn't be flushed, adding them to the ones
+    /// recorded since.
+    pub fn restore(&self, taken_counts: HashMap<String, AccessCount>) {
+        let mut counts = self.counts.lock().unwrap();
+        for (word, taken_count) in taken_counts {
+            let access_count = counts.entry(word).or_insert(AccessCount {
+                count: 0,
+                last_access_at: taken_count.last_access_at,
+            });
+            access_count.count += taken_count.count;
+            access_count.last_access_at = access_count.last_access_at.max(taken_count.last_access_at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_are_added_up_until_taken() {
+        let access_counts = AccessCounts::default();
+        access_counts.record(&["happy".to_string(), "sad".to_string()], 10);
+        access_counts.record(&["Happy".to_string()], 20);
+
+        let counts = access_counts.take();
+        assert_eq!(counts["happy"], AccessCount { count: 2, last_access_at: 20 });
+        assert_eq!(counts["sad"], AccessCount { count: 1, last_access_at: 10 });
+        assert!(access_counts.take().is_empty());
+
+        // A failed flush puts its counts back next to the new ones
+        access_counts.record(&["happy".to_string()], 30);
+        access_counts.restore(counts);
+        let counts = access_counts.take();
+        assert_eq!(counts["happy"], AccessCount { count: 3, last_access_at: 30 });
+        assert_eq!(counts["sad"], AccessCount { count: 1, last_access_at: 10 });
+    }
+}
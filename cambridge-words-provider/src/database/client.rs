@@ -1,19 +1,27 @@
-use crate::configuration::PostgresDatabaseConfig;
+use std::{sync::Arc, time::Duration};
 
-use super::prepare_postgres_con;
+use crate::configuration::{AccessCountsConfig, NegativeCacheConfig, PostgresDatabaseConfig, RedisCacheConfig};
+
+use super::{prepare_postgres_con, AccessCounts, WordCache};
 
 use anyhow::Result;
-use tracing::error;
+use tracing::{error, info};
 
 #[derive(Clone)]
 pub struct DatabaseClient {
     pub postgres_con: sqlx::postgres::PgPool,
+    pub word_cache: Option<WordCache>,
+    pub access_counts: Arc<AccessCounts>,
+    pub negative_cache: NegativeCacheConfig,
 }
 
 
 impl DatabaseClient {
     pub async fn new(
         postgres_config: &PostgresDatabaseConfig,
+        redis_cache_config: Option<&RedisCacheConfig>,
+        access_counts_config: &AccessCountsConfig,
+        negative_cache_config: &NegativeCacheConfig,
     ) -> Result<Self> {
 
         let postgres_con = prepare_postgres_con(
@@ -28,8 +36,40 @@ impl DatabaseClient {
             }
         }
 
-        Ok(Self {
+        let word_cache = match redis_cache_config {
+            Some(redis_cache_config) => {
+                info!("Connecting to the Redis word cache");
+                match WordCache::new(redis_cache_config).await {
+                    Ok(word_cache) => Some(word_cache),
+                    Err(e) => {
+                        error!("Failed to connect to Redis: {}", e);
+                        return Err(e);
+                    }
+                }
+            },
+            None => None,
+        };
+
+        let db_client = Self {
             postgres_con: postgres_con.unwrap(),
-        })
+            word_cache,
+            access_counts: Arc::new(AccessCounts::default()),
+            negative_cache: negative_cache_config.clone(),
+        };
+        db_client.spawn_access_counts_flush(Duration::from_millis(access_counts_config.flush_interval_ms));
+
+        Ok(db_client)
     }
-}
\ No newline at end of file
+
+    fn spawn_access_counts_flush(&self, flush_interval: Duration) {
+        let db_client = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                db_client.flush_access_counts().await;
+            }
+        });
+    }
+}
@@ -1 +1,3 @@
-mod words_db;
\ No newline at end of file
+mod words_db;
+
+pub use words_db::{DefinitionField, NotFoundReason};
\ No newline at end of file
@@ -1,64 +1,458 @@
+use serde::Deserialize;
+use sqlx::types::Json;
+
 use super::super::DatabaseClient;
 
-use crate::routes::{MeaningWithExamples, Word};
+use crate::routes::Word;
+
+/// A stored word with its definitions as they are in the database, which
+/// may not match the structs of this version.
+struct WordRow {
+    word: String,
+    meanings_with_examples: serde_json::Value,
+    has_meaning: bool,
+    entries: serde_json::Value,
+}
+
+impl TryFrom<WordRow> for Word {
+    type Error = serde_json::Error;
+
+    fn try_from(row: WordRow) -> Result<Self, Self::Error> {
+        Ok(Word {
+            meanings_with_examples: serde_json::from_value(row.meanings_with_examples)?,
+            entries: serde_json::from_value(row.entries)?,
+            word: row.word,
+            has_meaning: row.has_meaning,
+        })
+    }
+}
+
+/// The words of the rows, rows that can't be read are left out with a
+/// warning so that they are fetched again.
+fn readable_words(rows: Vec<WordRow>) -> Vec<Word> {
+    rows
+        .into_iter()
+        .filter_map(|row| {
+            let word = row.word.clone();
+            Word::try_from(row)
+                .map_err(|e| tracing::warn!("Stored definitions of {} can't be read: {}", word, e))
+                .ok()
+        })
+        .collect()
+}
+
+/// What a definition search looks in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefinitionField {
+    Meanings,
+    Examples,
+    Both,
+}
+
+/// Why a word is stored without a meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotFoundReason {
+    /// Cambridge has no entry for the word
+    NotFound,
+    /// Cambridge has a page the parser couldn't read
+    ParseFailure,
+}
+
+impl NotFoundReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotFoundReason::NotFound => "not_found",
+            NotFoundReason::ParseFailure => "parse_failure",
+        }
+    }
+}
 
 impl DatabaseClient {
+    /// Inserts the word, or replaces the definition of the stored one while
+    /// keeping its access count. Words are unique by their lower case form.
     pub async fn insert_word(&self, word: Word) -> Result<(), sqlx::Error> {
-        let meanings_with_examples = serde_json::to_string(&word.meanings_with_examples).unwrap();
+        self.upsert_word(&word, None).await?;
+        if let Some(word_cache) = &self.word_cache {
+            word_cache.set_word(&word).await;
+        }
+
+        Ok(())
+    }
+
+    /// Stores the word without a meaning, it is answered from the database
+    /// until the TTL of the reason runs out and fetched again after that.
+    /// Words without a meaning are not put in the Redis word cache, which
+    /// doesn't know about their TTL.
+    pub async fn insert_not_found_word(&self, word: &str, reason: NotFoundReason) -> Result<Word, sqlx::Error> {
+        let word = Word {
+            word: word.to_string(),
+            ..Default::default()
+        };
+        self.upsert_word(&word, Some(reason)).await?;
+
+        Ok(word)
+    }
+
+    async fn upsert_word(&self, word: &Word, not_found_reason: Option<NotFoundReason>) -> Result<(), sqlx::Error> {
+        let now = chrono::Utc::now().timestamp();
         let res = sqlx::query!(
             r#"
-            INSERT INTO words (word, meanings_with_examples, created_at, last_acsess_at, acsess_count, has_meaning)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO words (word, meanings_with_examples, created_at, last_access_at, access_count, has_meaning, entries, not_found_reason, fetched_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT ((lower(word))) DO UPDATE SET
+                meanings_with_examples = EXCLUDED.meanings_with_examples,
+                has_meaning = EXCLUDED.has_meaning,
+                entries = EXCLUDED.entries,
+                last_access_at = EXCLUDED.last_access_at,
+                not_found_reason = EXCLUDED.not_found_reason,
+                fetched_at = EXCLUDED.fetched_at
             "#,
             word.word,
-            meanings_with_examples,
-            chrono::Utc::now().timestamp(),
-            chrono::Utc::now().timestamp(),
+            Json(&word.meanings_with_examples) as _,
+            now,
+            now,
             0,
-            word.has_meaning
-
+            word.has_meaning,
+            Json(&word.entries) as _,
+            not_found_reason.map(|reason| reason.as_str()),
+            now,
         );
         res.execute(&self.postgres_con).await?;
-        
+
         Ok(())
     }
 
-    pub async fn update_word_metadata(&self, word: &str) -> Result<(), sqlx::Error> {
-        let query = sqlx::query!(
+    /// Words without a meaning fetched before these times have expired, by
+    /// reason.
+    fn not_found_fetched_after(&self) -> (i64, i64) {
+        let now = chrono::Utc::now().timestamp();
+        (
+            now - self.negative_cache.not_found_ttl_s as i64,
+            now - self.negative_cache.parse_failure_ttl_s as i64,
+        )
+    }
+
+    /// Deletes the stored words without a meaning, only the ones of
+    /// `reason` when it is set, so that they are fetched again on their next
+    /// lookup. Returns how many were deleted.
+    pub async fn purge_not_found_words(&self, reason: Option<NotFoundReason>) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query!(
             r#"
-            UPDATE words SET last_acsess_at = $1, acsess_count = acsess_count + 1 WHERE word = $2
+            DELETE FROM words WHERE NOT has_meaning AND ($1::TEXT IS NULL OR not_found_reason = $1)
             "#,
-            chrono::Utc::now().timestamp(),
-            word
-        );
-        query.execute(&self.postgres_con).await?;
+            reason.map(|reason| reason.as_str())
+        ).execute(&self.postgres_con).await?;
 
-        Ok(())
+        Ok(res.rows_affected())
     }
 
+    /// How many stored words have no meaning, by reason.
+    pub async fn count_not_found_words(&self) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT not_found_reason AS "reason!", COUNT(*) AS "count!"
+            FROM words WHERE NOT has_meaning AND not_found_reason IS NOT NULL
+            GROUP BY not_found_reason
+            ORDER BY not_found_reason
+            "#
+        ).fetch_all(&self.postgres_con).await?;
+
+        Ok(rows.into_iter().map(|row| (row.reason, row.count)).collect())
+    }
+
+    /// The stored word, from the Redis word cache when there is one. Words
+    /// without a meaning that expired are left out.
     pub async fn try_to_get_word(&self, word: &str) -> Result<Option<Word>, sqlx::Error> {
-        let word = sqlx::query!(
+        if let Some(word_cache) = &self.word_cache {
+            if let Some(cached_word) = word_cache.get_word(word).await {
+                return Ok(Some(cached_word));
+            }
+        }
+        let (not_found_after, parse_failure_after) = self.not_found_fetched_after();
+        let row = sqlx::query_as!(
+            WordRow,
             r#"
-            SELECT * FROM words WHERE word = $1
+            SELECT
+                word,
+                meanings_with_examples,
+                has_meaning,
+                entries
+            FROM words WHERE lower(word) = lower($1)
+            AND (has_meaning OR fetched_at >= CASE not_found_reason WHEN 'parse_failure' THEN $3::BIGINT ELSE $2::BIGINT END)
             "#,
-            word
+            word,
+            not_found_after,
+            parse_failure_after
         ).fetch_optional(&self.postgres_con).await?;
 
-        if word.is_none() {
-            return Ok(None);
+        let stored_word = row
+            .map(Word::try_from)
+            .transpose()
+            .map_err(|e| sqlx::Error::Decode(format!("stored definitions of {} can't be read: {}", word, e).into()))?;
+        if let (Some(word_cache), Some(stored_word)) = (&self.word_cache, &stored_word) {
+            if stored_word.has_meaning {
+                word_cache.set_word(stored_word).await;
+            }
         }
+        Ok(stored_word)
+    }
 
-        let word = word.unwrap();
-        let meanings_with_examples = word.meanings_with_examples;
-        let meanings_with_examples: Vec<MeaningWithExamples> = serde_json::from_str(&meanings_with_examples).unwrap();
-        let word = Word {
-            word: word.word,
-            meanings_with_examples,
-            has_meaning: word.has_meaning
+    /// The stored words among `words`, in no particular order. The words
+    /// have to be in lower case already. Unlike [`DatabaseClient::try_to_get_word`]
+    /// rows that can't be read are left out. Words in the Redis word cache
+    /// are not read from Postgres.
+    pub async fn try_to_get_words(&self, words: &[String]) -> Result<Vec<Word>, sqlx::Error> {
+        let Some(word_cache) = &self.word_cache else {
+            return self.try_to_get_stored_words(words).await;
         };
-        println!("{:?}", word.word);
+        let mut found_words = vec![];
+        let mut missing_words = vec![];
+        for (word, cached_word) in words.iter().zip(word_cache.get_words(words).await) {
+            match cached_word {
+                Some(cached_word) => found_words.push(cached_word),
+                None => missing_words.push(word.clone()),
+            }
+        }
+        if !missing_words.is_empty() {
+            let stored_words = self.try_to_get_stored_words(&missing_words).await?;
+            let words_with_meaning = stored_words
+                .iter()
+                .filter(|word| word.has_meaning)
+                .cloned()
+                .collect::<Vec<Word>>();
+            word_cache.set_words(&words_with_meaning).await;
+            found_words.extend(stored_words);
+        }
+
+        Ok(found_words)
+    }
 
-        Ok(Some(word))
+    async fn try_to_get_stored_words(&self, words: &[String]) -> Result<Vec<Word>, sqlx::Error> {
+        let (not_found_after, parse_failure_after) = self.not_found_fetched_after();
+        let rows = sqlx::query_as!(
+            WordRow,
+            r#"
+            SELECT
+                word,
+                meanings_with_examples,
+                has_meaning,
+                entries
+            FROM words WHERE lower(word) = ANY($1)
+            AND (has_meaning OR fetched_at >= CASE not_found_reason WHEN 'parse_failure' THEN $3::BIGINT ELSE $2::BIGINT END)
+            "#,
+            words,
+            not_found_after,
+            parse_failure_after
+        ).fetch_all(&self.postgres_con).await?;
+
+        Ok(readable_words(rows))
     }
 
-}
\ No newline at end of file
+    /// Words with a meaning fetched before `fetched_before`, the most read
+    /// first. Rows that can't be read are left out.
+    pub async fn stale_words(&self, fetched_before: i64, limit: i64) -> Result<Vec<Word>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            WordRow,
+            r#"
+            SELECT
+                word,
+                meanings_with_examples,
+                has_meaning,
+                entries
+            FROM words
+            WHERE has_meaning AND fetched_at < $1
+            ORDER BY access_count DESC, fetched_at
+            LIMIT $2
+            "#,
+            fetched_before,
+            limit
+        ).fetch_all(&self.postgres_con).await?;
+
+        Ok(readable_words(rows))
+    }
+
+    /// Replaces the definitions of a stored word with the ones fetched
+    /// again, a refresh doesn't count as an access.
+    pub async fn update_refreshed_word(&self, word: &Word) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE words SET
+                meanings_with_examples = $2,
+                has_meaning = $3,
+                entries = $4,
+                not_found_reason = NULL,
+                fetched_at = $5
+            WHERE lower(word) = lower($1)
+            "#,
+            word.word,
+            Json(&word.meanings_with_examples) as _,
+            word.has_meaning,
+            Json(&word.entries) as _,
+            chrono::Utc::now().timestamp()
+        ).execute(&self.postgres_con).await?;
+        if let Some(word_cache) = &self.word_cache {
+            word_cache.set_word(word).await;
+        }
+
+        Ok(())
+    }
+
+    /// Keeps the stored definitions of the word but counts them as just
+    /// fetched, when fetching them again gave nothing better.
+    pub async fn mark_word_refreshed(&self, word: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE words SET fetched_at = $2 WHERE lower(word) = lower($1)
+            "#,
+            word,
+            chrono::Utc::now().timestamp()
+        ).execute(&self.postgres_con).await?;
+
+        Ok(())
+    }
+
+    /// Words with a meaning or an example containing `phrase`, ignoring
+    /// case, in alphabetical order. Rows that can't be read are left out.
+    pub async fn search_definitions(
+        &self,
+        phrase: &str,
+        field: DefinitionField,
+        limit: i64,
+    ) -> Result<Vec<Word>, sqlx::Error> {
+        let pattern = format!("%{}%", escape_like(phrase));
+        let in_meanings = field != DefinitionField::Examples;
+        let in_examples = field != DefinitionField::Meanings;
+        let rows = sqlx::query_as!(
+            WordRow,
+            r#"
+            SELECT
+                word,
+                meanings_with_examples,
+                has_meaning,
+                entries
+            FROM words
+            WHERE has_meaning AND EXISTS (
+                -- Rows of another shape are skipped instead of failing the query
+                SELECT 1 FROM jsonb_array_elements(
+                    CASE jsonb_typeof(meanings_with_examples) WHEN 'array' THEN meanings_with_examples ELSE '[]' END
+                ) AS meaning
+                WHERE ($2 AND meaning->>'meaning' ILIKE $1)
+                    OR ($3 AND EXISTS (
+                        SELECT 1 FROM jsonb_array_elements_text(
+                            CASE jsonb_typeof(meaning->'examples') WHEN 'array' THEN meaning->'examples' ELSE '[]' END
+                        ) AS example
+                        WHERE example ILIKE $1
+                    ))
+            )
+            ORDER BY word
+            LIMIT $4
+            "#,
+            pattern,
+            in_meanings,
+            in_examples,
+            limit
+        ).fetch_all(&self.postgres_con).await?;
+
+        Ok(readable_words(rows))
+    }
+
+    /// Counts a read of each of the stored `words`, they are written to
+    /// Postgres with the next [`DatabaseClient::flush_access_counts`].
+    pub fn record_words_access(&self, words: &[String]) {
+        self.access_counts.record(words, chrono::Utc::now().timestamp());
+    }
+
+    pub fn record_word_access(&self, word: &str) {
+        self.record_words_access(&[word.to_string()]);
+    }
+
+    /// Adds the access counts recorded since the last flush to the stored
+    /// words in one query. They are kept for the next flush when it fails.
+    pub async fn flush_access_counts(&self) {
+        let counts = self.access_counts.take();
+        if counts.is_empty() {
+            return;
+        }
+        let mut words = Vec::with_capacity(counts.len());
+        let mut access_counts = Vec::with_capacity(counts.len());
+        let mut last_access_ats = Vec::with_capacity(counts.len());
+        for (word, access_count) in &counts {
+            words.push(word.clone());
+            access_counts.push(access_count.count);
+            last_access_ats.push(access_count.last_access_at);
+        }
+        let query = sqlx::query!(
+            r#"
+            UPDATE words SET
+                access_count = words.access_count + accessed.count,
+                last_access_at = GREATEST(words.last_access_at, accessed.last_access_at)
+            FROM UNNEST($1::TEXT[], $2::INT[], $3::BIGINT[]) AS accessed(word, count, last_access_at)
+            WHERE lower(words.word) = accessed.word
+            "#,
+            &words,
+            &access_counts,
+            &last_access_ats
+        );
+        match query.execute(&self.postgres_con).await {
+            Ok(_) => tracing::debug!("Access counts of {} words flushed", counts.len()),
+            Err(e) => {
+                tracing::warn!("Could not flush the access counts of {} words: {:?}", counts.len(), e);
+                self.access_counts.restore(counts);
+            }
+        }
+    }
+}
+
+/// Makes `%`, `_` and `\` match themselves in a LIKE pattern.
+fn escape_like(phrase: &str) -> String {
+    let mut escaped = String::with_capacity(phrase.len());
+    for character in phrase.chars() {
+        if matches!(character, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(meanings_with_examples: serde_json::Value, entries: serde_json::Value) -> WordRow {
+        WordRow {
+            word: "happy".to_string(),
+            meanings_with_examples,
+            has_meaning: true,
+            entries,
+        }
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("feeling pleasure"), "feeling pleasure");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a_b"), "a\\_b");
+        assert_eq!(escape_like("a\\b"), "a\\\\b");
+    }
+
+    #[test]
+    fn test_rows_that_cant_be_read_are_left_out() {
+        let meanings = serde_json::json!([{"meaning": "feeling pleasure", "examples": ["She looks happy."]}]);
+        let word = Word::try_from(row(meanings.clone(), serde_json::json!([]))).unwrap();
+        assert_eq!(word.word, "happy");
+        assert_eq!(word.meanings_with_examples[0].meaning, "feeling pleasure");
+        assert!(word.entries.is_empty());
+
+        assert!(Word::try_from(row(serde_json::json!("not a list"), serde_json::json!([]))).is_err());
+        assert!(Word::try_from(row(meanings.clone(), serde_json::json!([{"senses": "none"}]))).is_err());
+
+        let words = readable_words(vec![
+            row(serde_json::json!({"meaning": 1}), serde_json::json!([])),
+            row(meanings, serde_json::json!([])),
+        ]);
+        assert_eq!(words.len(), 1);
+    }
+}
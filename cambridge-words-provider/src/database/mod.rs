@@ -4,5 +4,12 @@ mod client;
 
 mod methods;
 
+mod word_cache;
+
+mod access_counts;
+
 pub (super) use postgres_preparation::prepare_postgres_con;
-pub use client::DatabaseClient;
\ No newline at end of file
+pub use client::DatabaseClient;
+pub use methods::{DefinitionField, NotFoundReason};
+pub use word_cache::WordCache;
+pub use access_counts::AccessCounts;
\ No newline at end of file
@@ -19,8 +19,9 @@ pub async fn prepare_postgres_con(
     db_connect_options = db_connect_options.log_statements(log::LevelFilter::Debug);
 
     let pool = PgPool::connect_with(db_connect_options).await?;
-    sqlx::query_file!("sql/init_messages_db.sql")
-        .execute(&pool)
+    // The migrations of the workspace root, embedded at compile time
+    sqlx::migrate!("../migrations")
+        .run(&pool)
         .await?;
 
     Ok(pool)
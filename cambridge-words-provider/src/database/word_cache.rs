@@ -0,0 +1,119 @@
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use tracing::warn;
+
+use crate::{configuration::RedisCacheConfig, routes::Word};
+
+const KEY_PREFIX: &str = "word:";
+
+/// Read-through cache of stored words in Redis, in front of Postgres.
+///
+/// The cache only makes lookups faster, when Redis can't be reached the
+/// words are read from Postgres and the error is counted in
+/// `word_cache_errors_total`.
+#[derive(Clone)]
+pub struct WordCache {
+    connection: ConnectionManager,
+    word_ttl_s: u64,
+}
+
+impl WordCache {
+    pub async fn new(redis_cache_config: &RedisCacheConfig) -> Result<Self> {
+        let client = redis::Client::open(redis_cache_config.url.as_str())?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self {
+            connection,
+            word_ttl_s: redis_cache_config.word_ttl_s,
+        })
+    }
+
+    fn key(word: &str) -> String {
+        format!("{}{}", KEY_PREFIX, word.to_lowercase())
+    }
+
+    /// The cached word of each of `words`, `None` for the misses.
+    pub async fn get_words(&self, words: &[String]) -> Vec<Option<Word>> {
+        if words.is_empty() {
+            return vec![];
+        }
+        // A pipeline always answers with one value per key, MGET of a single
+        // key doesn't
+        let mut pipe = redis::pipe();
+        for word in words {
+            pipe.get(Self::key(word));
+        }
+        let mut connection = self.connection.clone();
+        let values: Vec<Option<String>> = match pipe.query_async(&mut connection).await {
+            Ok(values) => values,
+            Err(e) => {
+                warn!("Could not read from the word cache: {}", e);
+                metrics::counter!("word_cache_errors_total").increment(1);
+                return vec![None; words.len()];
+            }
+        };
+
+        let cached_words = values
+            .into_iter()
+            .zip(words)
+            .map(|(value, word)| {
+                serde_json::from_str::<Word>(&value?)
+                    .map_err(|e| warn!("Cached word {} can't be read: {}", word, e))
+                    .ok()
+            })
+            .collect::<Vec<Option<Word>>>();
+        let hits = cached_words.iter().filter(|word| word.is_some()).count();
+        metrics::counter!("word_cache_hits_total").increment(hits as u64);
+        metrics::counter!("word_cache_misses_total").increment((words.len() - hits) as u64);
+        cached_words
+    }
+
+    pub async fn get_word(&self, word: &str) -> Option<Word> {
+        self.get_words(&[word.to_string()]).await.pop().flatten()
+    }
+
+    pub async fn set_words(&self, words: &[Word]) {
+        if words.is_empty() {
+            return;
+        }
+        let mut pipe = redis::pipe();
+        for word in words {
+            let value = serde_json::to_string(word).unwrap();
+            pipe.set_ex(Self::key(&word.word), value, self.word_ttl_s).ignore();
+        }
+        let mut connection = self.connection.clone();
+        if let Err(e) = pipe.query_async::<()>(&mut connection).await {
+            warn!("Could not write to the word cache: {}", e);
+            metrics::counter!("word_cache_errors_total").increment(1);
+        }
+    }
+
+    pub async fn set_word(&self, word: &Word) {
+        self.set_words(std::slice::from_ref(word)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn test_words_round_trip() {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+        let word_cache = WordCache::new(&RedisCacheConfig { url, word_ttl_s: 60 }).await.unwrap();
+        let happy = Word {
+            word: "happy".to_string(),
+            has_meaning: true,
+            ..Default::default()
+        };
+        word_cache.set_word(&happy).await;
+
+        let cached_words = word_cache
+            .get_words(&["happy".to_string(), "not-cached-word".to_string()])
+            .await;
+        assert_eq!(cached_words.len(), 2);
+        assert_eq!(cached_words[0].as_ref().map(|word| word.word.as_str()), Some("happy"));
+        assert!(cached_words[1].is_none());
+        assert!(word_cache.get_word("HAPPY").await.unwrap().has_meaning);
+    }
+}
@@ -49,7 +49,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("Connecting to database");
     let db_client = database::DatabaseClient::new(
-        &config.postgres_database
+        &config.postgres_database,
+        config.redis_cache.as_ref(),
+        &config.access_counts,
+        &config.negative_cache
     ).await?;
 
 
@@ -63,10 +66,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .make_span_with(DefaultMakeSpan::default().include_headers(true));
     
     let app = configure_routes(
-        db_client,
+        db_client.clone(),
         config.endpoint_rate_limiters.get_word.clone(),
-        config.cambrinary_session_tracker.clone()
-    ).await;
+        config.cambrinary_session_tracker.clone(),
+        config.batch_lookup.clone(),
+        config.refresh.clone()
+    ).await?;
 
     let app = app
         .route_layer(
@@ -88,6 +93,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let metrics_app = logs::metrics::metrics_app(
         trace_layer
     );
+    let metrics_app = if config.metrics_server.enable_admin_routes {
+        let Some(admin_token) = config.metrics_server.admin_token.clone().filter(|token| !token.is_empty()) else {
+            return Err("metrics_server.enable_admin_routes needs an admin_token".into());
+        };
+        metrics_app.merge(routes::admin_routes(db_client, admin_token))
+    } else {
+        metrics_app
+    };
     println!("{:?}", config.server.pem_cert_path);
     let server_tls_config: Option<RustlsConfig> = {
         if config.server.enable_https {
@@ -0,0 +1,121 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router
+};
+use serde::{
+    Serialize, Deserialize
+};
+use tracing::{error, info, warn};
+
+use crate::database::{DatabaseClient, NotFoundReason};
+
+/// `?reason=` is `not_found` or `parse_failure`, all words without a
+/// meaning when it is left out.
+#[derive(Deserialize, Debug)]
+pub struct PurgeParams {
+    reason: Option<NotFoundReason>,
+}
+
+#[derive(Serialize)]
+struct PurgeResponse {
+    purged: u64,
+}
+
+/// Maintenance routes, served on the metrics server when
+/// `enable_admin_routes` is set. Every request needs
+/// `Authorization: Bearer <admin_token>`.
+///
+/// - `GET /admin/not_found_words` counts the stored words without a meaning
+///   by reason
+/// - `DELETE /admin/not_found_words?reason=` deletes them so that their
+///   next lookup fetches them again
+pub fn admin_routes(db_client: DatabaseClient, admin_token: String) -> Router {
+    Router::new()
+        .route("/admin/not_found_words", get(count_not_found_words).delete(purge_not_found_words))
+        .route_layer(middleware::from_fn_with_state(Arc::<str>::from(admin_token), admin_token_middleware))
+        .with_state(db_client)
+}
+
+async fn admin_token_middleware(
+    State(admin_token): State<Arc<str>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if !is_authorized(request.headers(), &admin_token) {
+        warn!("Unauthorized admin request: {} {}", request.method(), request.uri());
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Compares the whole token so that the time taken doesn't tell how much
+/// of it was right.
+fn is_authorized(headers: &HeaderMap, admin_token: &str) -> bool {
+    let Some(token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    token.len() == admin_token.len()
+        && token
+            .bytes()
+            .zip(admin_token.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+async fn count_not_found_words(State(db_client): State<DatabaseClient>) -> Response {
+    let counts = db_client.count_not_found_words().await;
+    if counts.is_err() {
+        error!("{:?}", counts.err().unwrap());
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_string()).into_response();
+    }
+    let counts = counts.unwrap().into_iter().collect::<BTreeMap<String, i64>>();
+    Json(counts).into_response()
+}
+
+async fn purge_not_found_words(
+    Query(purge_params): Query<PurgeParams>,
+    State(db_client): State<DatabaseClient>,
+) -> Response {
+    let purged = db_client.purge_not_found_words(purge_params.reason).await;
+    if purged.is_err() {
+        error!("{:?}", purged.err().unwrap());
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_string()).into_response();
+    }
+    let purged = purged.unwrap();
+    info!("Purged {} words without a meaning ({:?})", purged, purge_params.reason);
+    Json(PurgeResponse { purged }).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(authorization).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(&headers("Bearer secret"), "secret"));
+
+        assert!(!is_authorized(&HeaderMap::new(), "secret"));
+        assert!(!is_authorized(&headers("secret"), "secret"));
+        assert!(!is_authorized(&headers("Bearer secreT"), "secret"));
+        assert!(!is_authorized(&headers("Bearer secret2"), "secret"));
+        assert!(!is_authorized(&headers("Bearer "), "secret"));
+    }
+}
@@ -1,3 +1,4 @@
+mod admin;
 mod hello_world;
 mod word;
 
@@ -5,25 +6,31 @@ use std::sync::Arc;
 
 use axum::{
     routing::{
-        get,
+        get, post,
     }, Router
 };
 
 use tower_governor::GovernorLayer;
-use word::{create_global_limiter_for_get_words_endpoint, create_limiter_for_get_words_endpoint, get_word, CambrinarySessionTracker};
+use word::{create_global_limiter_for_get_words_endpoint, create_limiter_for_get_words_endpoint, get_word, spawn_fetch_workers, spawn_refresh_job, words_get_method, words_method, CambrinarySessionTracker, CambridgeDictionary, FetchQueue, GetWordLimiters, InFlightFetches, LookupQueue};
 
 
+pub use admin::admin_routes;
+
 pub use word::{
     Word,
-    MeaningWithExamples
+    MeaningWithExamples,
+    Entry,
+    Sense
 };
 
-use crate::{configuration::{CambrinarySessionTrackerConfig, RateLimiterConfig}, database::DatabaseClient};
+use crate::{configuration::{BatchLookupConfig, CambrinarySessionTrackerConfig, RateLimiterConfig, RefreshConfig}, database::DatabaseClient};
 pub async fn configure_routes(
     db_client: DatabaseClient,
     rate_limiter_config: RateLimiterConfig,
-    cambrinary_session_tracker_config: CambrinarySessionTrackerConfig
-) -> Router {
+    cambrinary_session_tracker_config: CambrinarySessionTrackerConfig,
+    batch_lookup_config: BatchLookupConfig,
+    refresh_config: Option<RefreshConfig>
+) -> anyhow::Result<Router> {
     let get_word_limiter_config = create_limiter_for_get_words_endpoint(
         rate_limiter_config.max_per_second,
         rate_limiter_config.burst_size
@@ -42,10 +49,26 @@ pub async fn configure_routes(
     let get_word_state = word::GetWordState {
         db_client,
         cambrinary_session_tracker: cambrinary_session_tracker.clone(),
+        cambridge_dictionary: CambridgeDictionary::new()?,
+        lookup_queue: Arc::new(LookupQueue::default()),
+        fetch_queue: Arc::new(FetchQueue::default()),
+        in_flight_fetches: Arc::new(InFlightFetches::default()),
+        limiters: GetWordLimiters {
+            per_ip: get_word_limiter_config.clone(),
+            global: get_word_global_limiter_config.clone(),
+        },
+        batch_lookup_config,
     };
-    
-    Router::new()
+    spawn_fetch_workers(get_word_state.clone(), cambrinary_session_tracker_config.max_sessions);
+    if let Some(refresh_config) = refresh_config {
+        spawn_refresh_job(get_word_state.clone(), refresh_config);
+    }
+
+    let router = Router::new()
         .route("/get_word/:word", get(get_word))
+        // Matches `/v1/words:batch`, `/v1/words:stream` and
+        // `/v1/words:search`, the colon starts a parameter
+        .route("/v1/words:method", post(words_method).get(words_get_method))
         .with_state(get_word_state)
         .layer(
             GovernorLayer {
@@ -57,7 +80,8 @@ pub async fn configure_routes(
                 config: get_word_limiter_config,
             }
         )
-        .route("/health", get(|| async { "OK" }))
+        .route("/health", get(|| async { "OK" }));
+    Ok(router)
 }
 
 
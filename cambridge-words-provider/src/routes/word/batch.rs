@@ -0,0 +1,330 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State}, http::{header, StatusCode}, response::{IntoResponse, Response}, Json
+};
+use serde::{
+    Serialize, Deserialize
+};
+use tracing::{error, info};
+
+use super::{
+    get_word::{normalise_word, GetWordState},
+    response::{versioned_word, ResponseVersion, VersionedWord},
+};
+
+/// Longer "words" are not looked up, no entry has a headword that long.
+const MAX_WORD_LENGTH: usize = 64;
+/// How long a failed fetch is reported before the word is queued again.
+/// Failures of words nobody asks for again are dropped after it too.
+const FAILURE_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Debug)]
+pub struct BatchRequest {
+    pub(super) words: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BatchResponse<'a> {
+    results: Vec<BatchResult<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub(super) enum BatchResult<'a> {
+    Found {
+        word: String,
+        definition: VersionedWord<'a>,
+    },
+    NotFound {
+        word: String,
+    },
+    /// Not in the database yet and queued for a fetch, the client should
+    /// ask again later
+    Pending {
+        word: String,
+    },
+    Error {
+        word: String,
+        error: String,
+    },
+}
+
+enum QueuedLookup {
+    Pending,
+    Failed {
+        error: String,
+        failed_at: Instant,
+    },
+}
+
+impl QueuedLookup {
+    fn is_expired_failure(&self) -> bool {
+        matches!(self, QueuedLookup::Failed { failed_at, .. } if failed_at.elapsed() > FAILURE_TTL)
+    }
+}
+
+/// Words asked for in batches that weren't in the database, fetched in the
+/// background through the fetch queue. A failed fetch is reported to the
+/// next batch that asks for the word within `FAILURE_TTL`, which queues it
+/// again.
+#[derive(Default)]
+pub struct LookupQueue {
+    lookups: Mutex<HashMap<String, QueuedLookup>>,
+}
+
+impl LookupQueue {
+    /// The error of the last fetch of the word, forgotten once it is read.
+    fn take_failure(&self, word: &str) -> Option<String> {
+        let mut lookups = self.lookups.lock().unwrap();
+        match lookups.remove(word) {
+            Some(lookup) if lookup.is_expired_failure() => None,
+            Some(QueuedLookup::Failed { error, .. }) => Some(error),
+            Some(QueuedLookup::Pending) => {
+                lookups.insert(word.to_string(), QueuedLookup::Pending);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Returns `false` when the word was already queued.
+    fn queue(&self, word: &str) -> bool {
+        let mut lookups = self.lookups.lock().unwrap();
+        if lookups.contains_key(word) {
+            return false;
+        }
+        lookups.insert(word.to_string(), QueuedLookup::Pending);
+        true
+    }
+
+    fn finish(&self, word: &str, result: Result<(), String>) {
+        let mut lookups = self.lookups.lock().unwrap();
+        match result {
+            Ok(()) => {
+                lookups.remove(word);
+            }
+            Err(error) => {
+                lookups.retain(|_, lookup| !lookup.is_expired_failure());
+                lookups.insert(word.to_string(), QueuedLookup::Failed {
+                    error,
+                    failed_at: Instant::now(),
+                });
+            }
+        }
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.lookups.lock().unwrap().len()
+    }
+}
+
+/// Custom methods of the words collection posted to, `POST /v1/words:batch`
+/// is the only one. The router can't match a literal colon, so the method is the
+/// parameter, colon included.
+#[axum::debug_handler]
+pub async fn words_method(
+    Path(method): Path<String>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Query(response_version): Query<ResponseVersion>,
+    State(get_word_state): State<GetWordState>,
+    Json(batch_request): Json<BatchRequest>,
+) -> Response {
+    if method != ":batch" {
+        return (StatusCode::NOT_FOUND, "Not Found".to_string()).into_response();
+    }
+    let version = match response_version.version() {
+        Ok(version) => version,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let config = &get_word_state.batch_lookup_config;
+
+    let words = unique_words(batch_request.words);
+    if words.len() > config.max_words {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("A batch can have at most {} words", config.max_words)
+        ).into_response();
+    }
+
+    if let Err(wait_time) = charge_for_words(&get_word_state, address, words.len()) {
+        info!("Batch of {} words from {} is rate limited for {:?}", words.len(), address, wait_time);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, wait_time.as_secs().max(1).to_string())],
+            "Too Many Requests".to_string()
+        ).into_response();
+    }
+
+    let (valid_words, invalid_words): (Vec<String>, Vec<String>) = words
+        .iter()
+        .cloned()
+        .partition(|word| !is_too_long(word));
+    let found_words = get_word_state.db_client.try_to_get_words(&valid_words).await;
+    if found_words.is_err() {
+        error!("{:?}", found_words.err().unwrap());
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_string()).into_response();
+    }
+    let found_words = found_words.unwrap();
+    if !found_words.is_empty() {
+        let found = found_words.iter().map(|word| word.word.clone()).collect::<Vec<String>>();
+        get_word_state.db_client.record_words_access(&found);
+    }
+
+    let mut results = vec![];
+    for word in words {
+        if invalid_words.contains(&word) {
+            results.push(BatchResult::too_long(word));
+            continue;
+        }
+        if let Some(found_word) = found_words.iter().find(|found_word| found_word.word == word) {
+            if found_word.has_meaning {
+                results.push(BatchResult::Found {
+                    word,
+                    definition: versioned_word(found_word, version),
+                });
+            } else {
+                results.push(BatchResult::NotFound { word });
+            }
+            continue;
+        }
+        if let Some(error) = get_word_state.lookup_queue.take_failure(&word) {
+            results.push(BatchResult::Error { word, error });
+            continue;
+        }
+        if get_word_state.lookup_queue.queue(&word) {
+            spawn_lookup(get_word_state.clone(), word.clone());
+        }
+        results.push(BatchResult::Pending { word });
+    }
+    info!(
+        "Batch of {} words, {} queued in total, {} being fetched",
+        results.len(), get_word_state.lookup_queue.queued_count(), get_word_state.in_flight_fetches.running_count()
+    );
+
+    Json(BatchResponse { results }).into_response()
+}
+
+/// Normalises the words and drops empty and repeated ones, keeping the
+/// order. Results are reported for the normalised words.
+pub(super) fn unique_words(requested_words: Vec<String>) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    for word in requested_words {
+        let word = normalise_word(&word);
+        if !word.is_empty() && !words.contains(&word) {
+            words.push(word);
+        }
+    }
+    words
+}
+
+pub(super) fn is_too_long(word: &str) -> bool {
+    word.chars().count() > MAX_WORD_LENGTH
+}
+
+/// Charges a request for `word_count` words, one request for every
+/// `words_per_request` words, or returns how long to wait before retrying.
+pub(super) fn charge_for_words(
+    get_word_state: &GetWordState,
+    address: SocketAddr,
+    word_count: usize,
+) -> Result<(), Duration> {
+    let extra_cost = extra_request_cost(word_count, get_word_state.batch_lookup_config.words_per_request);
+    get_word_state.limiters.charge(address.ip(), extra_cost)
+}
+
+/// The requests `word_count` words cost on top of the one the governor
+/// layer already counted.
+fn extra_request_cost(word_count: usize, words_per_request: usize) -> u32 {
+    let cost = word_count.div_ceil(words_per_request.max(1)).max(1);
+    cost as u32 - 1
+}
+
+impl BatchResult<'_> {
+    pub(super) fn too_long(word: String) -> Self {
+        BatchResult::Error {
+            error: format!("Words can be at most {} characters long", MAX_WORD_LENGTH),
+            word,
+        }
+    }
+}
+
+fn spawn_lookup(get_word_state: GetWordState, word: String) {
+    tokio::spawn(async move {
+        let (_, result_rx) = get_word_state.fetch_queue.push(&word);
+        let result = match result_rx.await {
+            Ok(result) => result.map(|_| ()),
+            Err(_) => Err("The lookup was dropped".to_string()),
+        };
+        if let Err(e) = &result {
+            error!("Queued lookup of {} failed: {}", word, e);
+        }
+        get_word_state.lookup_queue.finish(&word, result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_words() {
+        let words = ["Happy", " sad ", "happy", "", "  ", "SAD", "angry"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        assert_eq!(unique_words(words), vec!["happy", "sad", "angry"]);
+    }
+
+    #[test]
+    fn test_extra_request_cost() {
+        assert_eq!(extra_request_cost(0, 10), 0);
+        assert_eq!(extra_request_cost(1, 10), 0);
+        assert_eq!(extra_request_cost(10, 10), 0);
+        assert_eq!(extra_request_cost(11, 10), 1);
+        assert_eq!(extra_request_cost(100, 10), 9);
+        // A misconfigured 0 counts every word
+        assert_eq!(extra_request_cost(3, 0), 2);
+    }
+
+    #[test]
+    fn test_failures_are_reported_once() {
+        let lookup_queue = LookupQueue::default();
+        assert!(lookup_queue.queue("happy"));
+        assert!(!lookup_queue.queue("happy"));
+        assert_eq!(lookup_queue.take_failure("happy"), None);
+
+        lookup_queue.finish("happy", Err("timed out".to_string()));
+        assert_eq!(lookup_queue.take_failure("happy"), Some("timed out".to_string()));
+        assert_eq!(lookup_queue.take_failure("happy"), None);
+        assert!(lookup_queue.queue("happy"));
+        lookup_queue.finish("happy", Ok(()));
+        assert_eq!(lookup_queue.queued_count(), 0);
+    }
+
+    #[test]
+    fn test_old_failures_are_dropped() {
+        let lookup_queue = LookupQueue::default();
+        let failed_at = Instant::now() - FAILURE_TTL - Duration::from_secs(1);
+        lookup_queue.lookups.lock().unwrap().insert("happy".to_string(), QueuedLookup::Failed {
+            error: "timed out".to_string(),
+            failed_at,
+        });
+        lookup_queue.lookups.lock().unwrap().insert("sad".to_string(), QueuedLookup::Failed {
+            error: "timed out".to_string(),
+            failed_at,
+        });
+
+        // Asked for again, the word is fetched again
+        assert_eq!(lookup_queue.take_failure("happy"), None);
+        // Never asked for again, dropped with the next failure
+        lookup_queue.finish("angry", Err("timed out".to_string()));
+        assert_eq!(lookup_queue.queued_count(), 1);
+        assert_eq!(lookup_queue.take_failure("angry"), Some("timed out".to_string()));
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, StatusCode, Url};
+use tracing::{debug, warn};
+
+use crate::{database::NotFoundReason, routes::Word};
+
+use super::parser::parse_entry_page;
+
+const CAMBRIDGE_DICTIONARY_URL: &str = "https://dictionary.cambridge.org/dictionary/english/";
+// Cambridge answers 403 to clients that don't look like a browser
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:130.0) Gecko/20100101 Firefox/130.0";
+const REQUEST_TIMEOUT_S: u64 = 15;
+
+/// What the Cambridge dictionary has for a word.
+pub enum FetchedWord {
+    Found(Word),
+    NotFound(NotFoundReason),
+}
+
+#[derive(Clone)]
+pub struct CambridgeDictionary {
+    client: Client,
+    base_url: Url,
+}
+
+impl CambridgeDictionary {
+    pub fn new() -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_S))
+            .build()?;
+        Ok(Self {
+            client,
+            base_url: Url::parse(CAMBRIDGE_DICTIONARY_URL)?,
+        })
+    }
+
+    /// Cambridge joins the words of a phrase with hyphens, "ice cream" is
+    /// under `ice-cream`.
+    pub fn entry_url(&self, word: &str) -> Result<Url> {
+        let slug = word
+            .trim()
+            .to_lowercase()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join("-");
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("{} can't be a base url", self.base_url))?
+            .pop_if_empty()
+            .push(&slug);
+        Ok(url)
+    }
+
+    /// Downloads the entry page of the word, `None` when Cambridge has no
+    /// page for it. Words it doesn't know are usually redirected to the
+    /// spellcheck page instead, which is left to the parser.
+    pub async fn fetch_entry_page(&self, word: &str) -> Result<Option<String>> {
+        let url = self.entry_url(word)?;
+        let response = self.client.get(url.clone()).send().await?;
+        debug!("{} answered {} from {}", url, response.status(), response.url());
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Cambridge dictionary answered {} for {}", response.status(), url));
+        }
+        Ok(Some(response.text().await?))
+    }
+
+    /// An error means Cambridge couldn't be asked, a page that can't be
+    /// parsed is a [`NotFoundReason::ParseFailure`].
+    pub async fn fetch_word(&self, word: &str) -> Result<FetchedWord> {
+        let page = match self.fetch_entry_page(word).await? {
+            Some(page) => page,
+            None => return Ok(FetchedWord::NotFound(NotFoundReason::NotFound)),
+        };
+        match parse_entry_page(word, &page) {
+            Ok(Some(word)) => Ok(FetchedWord::Found(word)),
+            Ok(None) => Ok(FetchedWord::NotFound(NotFoundReason::NotFound)),
+            Err(e) => {
+                warn!("Could not parse the entry page of {}: {:#}", word, e);
+                Ok(FetchedWord::NotFound(NotFoundReason::ParseFailure))
+            }
+        }
+    }
+}
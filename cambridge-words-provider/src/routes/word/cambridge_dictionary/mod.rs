@@ -0,0 +1,4 @@
+mod fetch;
+mod parser;
+
+pub use fetch::{CambridgeDictionary, FetchedWord};
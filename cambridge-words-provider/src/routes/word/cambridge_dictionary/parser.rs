@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Result};
+use scraper::{ElementRef, Html, Selector};
+
+use crate::routes::{Entry, MeaningWithExamples, Sense, Word};
+
+fn selector(selectors: &str) -> Selector {
+    Selector::parse(selectors).expect("invalid selector")
+}
+
+/// Reads the entries of a Cambridge entry page, each with its senses.
+///
+/// Returns `None` for pages without an entry, like the spellcheck page
+/// Cambridge redirects unknown words to, and an error for an entry without
+/// any definition, which means the markup changed under the parser.
+pub fn parse_entry_page(word: &str, page: &str) -> Result<Option<Word>> {
+    let document = Html::parse_document(page);
+
+    // The British dictionary comes first, the American and business ones
+    // after it repeat most of its definitions
+    let entry_body = match document.select(&selector(".entry-body")).next() {
+        Some(entry_body) => entry_body,
+        None => return Ok(None),
+    };
+
+    // Senses are grouped under the part of speech, phrasal verb or idiom
+    // block they are in, anything outside of those under the whole entry
+    let mut entries: Vec<(ElementRef, Entry)> = vec![];
+    for def_block in entry_body.select(&selector(".def-block")) {
+        let sense = match parse_sense(def_block) {
+            Some(sense) => sense,
+            None => continue,
+        };
+        let entry_element = closest_ancestor(def_block, &["entry-body__el", "pv-block", "idiom-block"])
+            .unwrap_or(entry_body);
+        match entries.iter_mut().find(|(element, _)| *element == entry_element) {
+            Some((_, entry)) => entry.senses.push(sense),
+            None => {
+                let mut entry = parse_entry_header(entry_element, word);
+                entry.senses.push(sense);
+                entries.push((entry_element, entry));
+            }
+        }
+    }
+
+    if entries.is_empty() {
+        return Err(anyhow!("entry page of {} has no definitions", word));
+    }
+    let entries = entries
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .collect::<Vec<Entry>>();
+    let meanings_with_examples = entries
+        .iter()
+        .flat_map(|entry| entry.senses.iter())
+        .map(|sense| MeaningWithExamples {
+            meaning: sense.definition.clone(),
+            examples: sense.examples.clone(),
+        })
+        .collect();
+    Ok(Some(Word {
+        word: word.to_string(),
+        meanings_with_examples,
+        has_meaning: true,
+        entries,
+    }))
+}
+
+fn parse_entry_header(entry_element: ElementRef, word: &str) -> Entry {
+    Entry {
+        headword: first_text(entry_element, ".dhw").unwrap_or_else(|| word.to_string()),
+        part_of_speech: first_text(entry_element, ".dpos"),
+        uk_ipa: first_text(entry_element, ".uk .ipa"),
+        us_ipa: first_text(entry_element, ".us .ipa"),
+        senses: vec![],
+    }
+}
+
+fn parse_sense(def_block: ElementRef) -> Option<Sense> {
+    let definition = first_text(def_block, ".def")?;
+    let definition = definition.trim_end_matches(':').trim_end().to_string();
+    let examples = def_block
+        .select(&selector(".def-body .examp .eg"))
+        .map(element_text)
+        .filter(|example| !example.is_empty())
+        .collect::<Vec<String>>();
+    if definition.is_empty() && examples.is_empty() {
+        return None;
+    }
+
+    let guide_word = closest_ancestor(def_block, &["dsense"])
+        .and_then(|sense| first_text(sense, ".dsense_h .guideword span"));
+    let phrase = closest_ancestor(def_block, &["phrase-block"])
+        .and_then(|phrase_block| first_text(phrase_block, ".phrase-title"));
+
+    let mut sense = Sense {
+        definition,
+        examples,
+        guide_word,
+        phrase,
+        ..Default::default()
+    };
+    // Examples have labels of their own in the def-body, only the ones of
+    // the definition itself are read
+    if let Some(def_info) = def_block.select(&selector(".ddef_h .def-info")).next() {
+        sense.cefr_level = first_text(def_info, ".epp-xref");
+        sense.grammar = def_info
+            .select(&selector(".gram .gc"))
+            .map(element_text)
+            .filter(|code| !code.is_empty())
+            .collect();
+        sense.register = first_text(def_info, ".usage");
+        sense.labels = def_info
+            .select(&selector(".lab"))
+            .map(element_text)
+            .filter(|label| !label.is_empty() && Some(label) != sense.register.as_ref())
+            .collect();
+    }
+    Some(sense)
+}
+
+fn closest_ancestor<'a>(element: ElementRef<'a>, classes: &[&str]) -> Option<ElementRef<'a>> {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|ancestor| ancestor.value().classes().any(|class| classes.contains(&class)))
+}
+
+fn first_text(element: ElementRef, selectors: &str) -> Option<String> {
+    element
+        .select(&selector(selectors))
+        .map(element_text)
+        .find(|text| !text.is_empty())
+}
+
+/// The text of the element and its children with the whitespace of the
+/// markup collapsed.
+fn element_text(element: ElementRef) -> String {
+    element
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAPPY: &str = include_str!("../../../../tests/fixtures/cambridge/happy.html");
+    const RUN: &str = include_str!("../../../../tests/fixtures/cambridge/run.html");
+    const SPELLCHECK: &str = include_str!("../../../../tests/fixtures/cambridge/spellcheck.html");
+
+    #[test]
+    fn test_entry_with_examples() {
+        let word = parse_entry_page("happy", HAPPY).unwrap().unwrap();
+        assert_eq!(word.word, "happy");
+        assert!(word.has_meaning);
+        // The American dictionary below the British one is left out
+        assert_eq!(word.meanings_with_examples.len(), 3);
+
+        let pleased = &word.meanings_with_examples[0];
+        assert_eq!(pleased.meaning, "feeling, showing, or causing pleasure or satisfaction");
+        assert_eq!(pleased.examples, vec![
+            "a happy child",
+            "a happy marriage/ending",
+            "Those were the happiest days of my life.",
+            "You'll be happy to know that Jean is coming with us.",
+        ]);
+        assert_eq!(word.meanings_with_examples[2].meaning, "suitable");
+    }
+
+    #[test]
+    fn test_entry_with_several_parts_of_speech() {
+        let word = parse_entry_page("run", RUN).unwrap().unwrap();
+        let meanings = word.meanings_with_examples
+            .iter()
+            .map(|meaning_with_examples| meaning_with_examples.meaning.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(meanings, vec![
+            "(of people and some animals) to move along, faster than walking, by taking quick steps in which each foot is lifted before the next foot touches the ground",
+            "to run in order to escape from someone or something",
+            "to be in control of something",
+            "an occasion when you move quickly on foot as a sport",
+        ]);
+        assert!(word.meanings_with_examples[1].examples.is_empty());
+        assert_eq!(word.meanings_with_examples[0].examples[1], "I ran to answer the phone.");
+        assert_eq!(word.meanings_with_examples[3].examples, vec!["I go for a run every morning."]);
+    }
+
+    #[test]
+    fn test_entries_and_sense_details() {
+        let word = parse_entry_page("happy", HAPPY).unwrap().unwrap();
+        assert_eq!(word.entries.len(), 1);
+        let entry = &word.entries[0];
+        assert_eq!(entry.headword, "happy");
+        assert_eq!(entry.part_of_speech.as_deref(), Some("adjective"));
+        assert_eq!(entry.uk_ipa.as_deref(), Some("ˈhæp.i"));
+        assert_eq!(entry.us_ipa.as_deref(), Some("ˈhæp.i"));
+
+        let pleased = &entry.senses[0];
+        assert_eq!(pleased.guide_word.as_deref(), Some("PLEASED"));
+        assert_eq!(pleased.cefr_level.as_deref(), Some("A1"));
+        assert!(pleased.grammar.is_empty());
+        assert_eq!(entry.senses[1].grammar, vec!["after verb"]);
+        let suitable = &entry.senses[2];
+        assert_eq!(suitable.guide_word.as_deref(), Some("SUITABLE"));
+        assert_eq!(suitable.cefr_level, None);
+        assert_eq!(suitable.register.as_deref(), Some("formal"));
+        assert!(suitable.labels.is_empty());
+    }
+
+    #[test]
+    fn test_senses_grouped_by_part_of_speech() {
+        let word = parse_entry_page("run", RUN).unwrap().unwrap();
+        let parts_of_speech = word.entries
+            .iter()
+            .map(|entry| (entry.part_of_speech.as_deref(), entry.senses.len()))
+            .collect::<Vec<(Option<&str>, usize)>>();
+        assert_eq!(parts_of_speech, vec![(Some("verb"), 3), (Some("noun"), 1)]);
+        let verb = &word.entries[0];
+        assert_eq!(verb.senses[0].grammar, vec!["I"]);
+        assert_eq!(verb.senses[1].phrase.as_deref(), Some("run for it"));
+        assert_eq!(verb.senses[1].guide_word.as_deref(), Some("GO QUICKLY"));
+        assert_eq!(verb.senses[2].guide_word.as_deref(), Some("BE IN CONTROL"));
+        assert_eq!(word.entries[1].us_ipa, None);
+    }
+
+    #[test]
+    fn test_spellcheck_page_is_not_found() {
+        assert!(parse_entry_page("qwertyuiop", SPELLCHECK).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_entry_without_definitions_is_an_error() {
+        let page = "<html><body><div class=\"entry-body\"><span class=\"hw\">happy</span></div></body></html>";
+        assert!(parse_entry_page("happy", page).is_err());
+    }
+}
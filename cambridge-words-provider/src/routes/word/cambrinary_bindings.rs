@@ -4,16 +4,18 @@ use rand::Rng;
 use serde::{
     Deserialize, Serialize
 };
-use tokio::{process::Command, sync::RwLock};
+use tokio::sync::RwLock;
 
-use anyhow::Result;
 use tracing::{debug, info};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Word {
     pub word: String,
+    /// Every sense of `entries` in order, the only thing version 1 responses have
     pub meanings_with_examples: Vec<MeaningWithExamples>,
     pub has_meaning: bool,
+    #[serde(default)]
+    pub entries: Vec<Entry>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -22,6 +24,36 @@ pub struct MeaningWithExamples {
     pub examples: Vec<String>,
 }
 
+/// The senses of one part of speech, a phrasal verb or an idiom.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Entry {
+    pub headword: String,
+    pub part_of_speech: Option<String>,
+    pub uk_ipa: Option<String>,
+    pub us_ipa: Option<String>,
+    pub senses: Vec<Sense>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Sense {
+    pub definition: String,
+    pub examples: Vec<String>,
+    /// e.g. `PLEASED` for the first sense of "happy"
+    pub guide_word: Option<String>,
+    /// The phrase the sense belongs to, e.g. "run for it"
+    pub phrase: Option<String>,
+    /// From A1 to C2
+    pub cefr_level: Option<String>,
+    /// Grammar codes like `C`, `T` or `after verb`
+    pub grammar: Vec<String>,
+    /// Usage labels other than the register, e.g. `UK` or `specialized`
+    pub labels: Vec<String>,
+    /// e.g. `formal`, `informal` or `old-fashioned`
+    pub register: Option<String>,
+}
+
 impl Word {
     pub fn to_json(&self) -> String {
         serde_json::to_string(&self).unwrap()
@@ -34,6 +66,7 @@ impl Default for Word {
             word: "".to_string(),
             meanings_with_examples: vec![],
             has_meaning: false,
+            entries: vec![],
         }
     }
     
@@ -125,76 +158,3 @@ impl CambrinarySessionTracker {
         *sessions
     }
 }
-
-
-pub async fn fetch_word_from_cambrinary(word: &str) -> Result<Option<Word>> {
-    let output = Command::new("cambrinary")
-        .arg("-w")
-        .arg(word)
-        .output();
-    let output = output.await?;
-    if output.status.code() != Some(0) {
-        let status = output.status;
-        let error = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        let error_message = format!(
-            "status: {}\nerror: {}\nstdout: {}",
-            status, error, stdout
-        );
-
-        return Err(anyhow::anyhow!(error_message));
-    }
-
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    if output_str.contains("No result for ") {
-        return Ok(None);
-    }
-
-    let meanings_with_examples = output_str.split("0;36;49m* ").collect::<Vec<&str>>();
-    let mut word = Word {
-        word: word.to_string(),
-        meanings_with_examples: vec![],
-        has_meaning: true,
-    };
-    for meaning_with_example in meanings_with_examples {
-        if meaning_with_example.contains("[0;34;49m") {
-            continue;
-        }
-        let meaning = meaning_with_example.split(": [0m").collect::<Vec<&str>>();
-        if meaning.len() == 0 {
-            // no meaning
-            continue;
-        }
-
-        let meaning = meaning[0].split("\u{1b}").collect::<Vec<&str>>()[0];
-        let meaning = sanitize_str(meaning);
-        let examples = meaning_with_example.split("  - ").collect::<Vec<&str>>();
-        let mut meaning_with_examples = MeaningWithExamples {
-            meaning: meaning.to_string(),
-            examples: vec![],
-        };
-        if examples.len() == 1 {
-            // no examples
-            word.meanings_with_examples.push(meaning_with_examples);
-            continue;
-        }
-        let examples = &examples[1..];
-        for example in examples {
-            let example = sanitize_str(&example);
-            let example: &str = &example.split("[0m").collect::<Vec<&str>>()[0];
-            meaning_with_examples.examples.push(example.to_string());
-        }
-        if meaning_with_examples.examples.len() == 0 && meaning_with_examples.meaning.len() == 0 {
-            continue;
-        }
-        word.meanings_with_examples.push(meaning_with_examples);
-    }
-
-
-    Ok(Some(word))
-}
-
-fn sanitize_str(word: &str) -> String {
-    word.replace("\n", "").replace("\r", "").trim().to_string()
-}
\ No newline at end of file
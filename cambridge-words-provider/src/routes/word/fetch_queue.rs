@@ -0,0 +1,136 @@
+use std::{
+    collections::VecDeque,
+    sync::{atomic::{AtomicU64, Ordering}, Mutex},
+};
+
+use tokio::sync::{oneshot, Notify};
+use tracing::{error, info};
+
+use crate::routes::Word;
+
+use super::get_word::{get_or_fetch_word, GetWordState};
+
+pub type FetchResult = Result<Word, String>;
+
+struct FetchJob {
+    id: u64,
+    word: String,
+    result_tx: oneshot::Sender<FetchResult>,
+}
+
+/// Words waiting for a fetch from the Cambridge dictionary, taken in order
+/// by as many workers as the session tracker allows sessions, so a place in
+/// the queue says how long a word will take.
+#[derive(Default)]
+pub struct FetchQueue {
+    jobs: Mutex<VecDeque<FetchJob>>,
+    next_id: AtomicU64,
+    job_added: Notify,
+}
+
+impl FetchQueue {
+    /// Queues the word, the id is its ticket for `position`.
+    pub fn push(&self, word: &str) -> (u64, oneshot::Receiver<FetchResult>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (result_tx, result_rx) = oneshot::channel();
+        self.jobs.lock().unwrap().push_back(FetchJob {
+            id,
+            word: word.to_string(),
+            result_tx,
+        });
+        self.job_added.notify_one();
+        (id, result_rx)
+    }
+
+    /// How many words are ahead of the job, `None` once a worker took it.
+    pub fn position(&self, id: u64) -> Option<usize> {
+        self.jobs.lock().unwrap().iter().position(|job| job.id == id)
+    }
+
+    pub fn waiting_count(&self) -> usize {
+        self.jobs.lock().unwrap().len()
+    }
+
+    /// Takes the next job somebody still waits for, the others, e.g. of a
+    /// closed stream, are dropped without a fetch.
+    async fn pop(&self) -> FetchJob {
+        loop {
+            let job = {
+                let mut jobs = self.jobs.lock().unwrap();
+                std::iter::from_fn(|| jobs.pop_front()).find(|job| !job.result_tx.is_closed())
+            };
+            if let Some(job) = job {
+                return job;
+            }
+            self.job_added.notified().await;
+        }
+    }
+}
+
+/// Starts the workers of the fetch queue of the state.
+pub fn spawn_fetch_workers(get_word_state: GetWordState, workers: u32) {
+    info!("Starting {} fetch workers", workers);
+    for _ in 0..workers.max(1) {
+        let get_word_state = get_word_state.clone();
+        tokio::spawn(async move {
+            loop {
+                let job = get_word_state.fetch_queue.pop().await;
+                let result = get_or_fetch_word(&get_word_state, &job.word).await;
+                if let Err(e) = &result {
+                    error!("Fetch of {} failed: {:?}", job.word, e);
+                }
+                let _ = job.result_tx.send(result.map_err(|e| e.to_string()));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_jobs_are_taken_in_order() {
+        let fetch_queue = FetchQueue::default();
+        let (happy, _happy_rx) = fetch_queue.push("happy");
+        let (sad, _sad_rx) = fetch_queue.push("sad");
+        let (angry, _angry_rx) = fetch_queue.push("angry");
+        assert_eq!(fetch_queue.position(happy), Some(0));
+        assert_eq!(fetch_queue.position(sad), Some(1));
+        assert_eq!(fetch_queue.position(angry), Some(2));
+        assert_eq!(fetch_queue.waiting_count(), 3);
+
+        let job = fetch_queue.pop().await;
+        assert_eq!((job.id, job.word.as_str()), (happy, "happy"));
+        assert_eq!(fetch_queue.position(happy), None);
+        assert_eq!(fetch_queue.position(sad), Some(0));
+        assert_eq!(fetch_queue.pop().await.word, "sad");
+        assert_eq!(fetch_queue.pop().await.word, "angry");
+        assert_eq!(fetch_queue.waiting_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_nobody_waits_for_are_skipped() {
+        let fetch_queue = FetchQueue::default();
+        let (_, happy_rx) = fetch_queue.push("happy");
+        let (_, sad_rx) = fetch_queue.push("sad");
+        let (_, _angry_rx) = fetch_queue.push("angry");
+        drop(happy_rx);
+        drop(sad_rx);
+
+        assert_eq!(fetch_queue.pop().await.word, "angry");
+        assert_eq!(fetch_queue.waiting_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pop_waits_for_a_job() {
+        let fetch_queue = std::sync::Arc::new(FetchQueue::default());
+        let popped = tokio::spawn({
+            let fetch_queue = fetch_queue.clone();
+            async move { fetch_queue.pop().await.word }
+        });
+        tokio::task::yield_now().await;
+        let (_, _happy_rx) = fetch_queue.push("happy");
+        assert_eq!(popped.await.unwrap(), "happy");
+    }
+}
@@ -0,0 +1,43 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ws::WebSocketUpgrade, ConnectInfo, Path, Query, State},
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+
+use super::{
+    get_word::GetWordState,
+    response::ResponseVersion,
+    search::{search_words_response, SearchParams},
+    stream::stream_words_response,
+};
+
+/// Custom methods of the words collection read with GET,
+/// `GET /v1/words:stream` opened as a WebSocket and `GET /v1/words:search`.
+///
+/// The search parameters are only read for `:search`, other methods don't
+/// fail on them.
+#[axum::debug_handler]
+pub async fn words_get_method(
+    Path(method): Path<String>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Query(response_version): Query<ResponseVersion>,
+    uri: Uri,
+    State(get_word_state): State<GetWordState>,
+    web_socket: Option<WebSocketUpgrade>,
+) -> Response {
+    let version = match response_version.version() {
+        Ok(version) => version,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    match (method.as_str(), web_socket) {
+        (":stream", Some(web_socket)) => stream_words_response(web_socket, address, version, get_word_state),
+        (":stream", None) => (StatusCode::BAD_REQUEST, "Expected a WebSocket upgrade".to_string()).into_response(),
+        (":search", _) => match Query::<SearchParams>::try_from_uri(&uri) {
+            Ok(Query(search_params)) => search_words_response(search_params, version, &get_word_state).await,
+            Err(e) => (StatusCode::BAD_REQUEST, e.body_text()).into_response(),
+        },
+        _ => (StatusCode::NOT_FOUND, "Not Found".to_string()).into_response(),
+    }
+}
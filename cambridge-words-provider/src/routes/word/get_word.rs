@@ -1,16 +1,27 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State}, http::StatusCode, response::IntoResponse
+    extract::{Path, Query, State}, http::StatusCode, response::IntoResponse
 };
 use log::info;
 use serde::{
     Serialize, Deserialize
 };
 
-use crate::{database::DatabaseClient, routes::Word};
+use anyhow::Result;
 
-use super::cambrinary_bindings::{fetch_word_from_cambrinary, CambrinarySessionTracker};
+use crate::{configuration::BatchLookupConfig, database::DatabaseClient, routes::Word};
+
+use super::{
+    batch::LookupQueue,
+    cambrinary_bindings::CambrinarySessionTracker,
+    fetch_queue::FetchQueue,
+    in_flight::InFlightFetches,
+    limiter::GetWordLimiters,
+    cambridge_dictionary::FetchedWord,
+    response::{render_word, ResponseVersion},
+    CambridgeDictionary
+};
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Params {
@@ -21,40 +32,102 @@ pub struct Params {
 pub struct GetWordState {
     pub db_client: DatabaseClient,
     pub cambrinary_session_tracker: Arc<CambrinarySessionTracker>,
+    pub cambridge_dictionary: CambridgeDictionary,
+    pub lookup_queue: Arc<LookupQueue>,
+    pub fetch_queue: Arc<FetchQueue>,
+    pub in_flight_fetches: Arc<InFlightFetches>,
+    pub limiters: GetWordLimiters,
+    pub batch_lookup_config: BatchLookupConfig,
 }
 
 
 #[axum::debug_handler]
 pub async fn get_word(
     Path(requested_word): Path<Params>,
+    Query(response_version): Query<ResponseVersion>,
     State(get_word_state): State<GetWordState>,
 ) -> impl IntoResponse {
+    let version = match response_version.version() {
+        Ok(version) => version,
+        Err(e) => return (StatusCode::BAD_REQUEST, e),
+    };
+
+    let word = get_or_queue_word(&get_word_state, &requested_word.word).await;
+    if word.is_err() {
+        tracing::error!("{:?}", word.err().unwrap());
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_string());
+    }
+    let word = word.unwrap();
+    if !word.has_meaning {
+        return (StatusCode::NOT_FOUND, "Word not found".to_string());
+    }
+    let word = render_word(&word, version);
+    (StatusCode::OK, word)
+}
+
+/// The form words are stored and coalesced in, the dictionary doesn't care
+/// about case.
+pub fn normalise_word(word: &str) -> String {
+    word.trim().to_lowercase()
+}
+
+/// Looks for the word in the database and fetches it from the Cambridge
+/// dictionary when it isn't there, waiting for a free cambrinary session.
+/// Words the dictionary doesn't know are stored and returned without a
+/// meaning, and fetched again once that expires.
+///
+/// Concurrent lookups of the same missing word share one fetch.
+pub async fn get_or_fetch_word(get_word_state: &GetWordState, requested_word: &str) -> Result<Word> {
+    let requested_word = normalise_word(requested_word);
+    if let Some(word) = get_word_from_db(get_word_state, &requested_word).await? {
+        return Ok(word);
+    }
+    let fetch = get_word_state.in_flight_fetches.join_or_start(
+        &requested_word,
+        fetch_and_store_word(get_word_state.clone(), requested_word.clone())
+    );
+    fetch.await.map_err(anyhow::Error::msg)
+}
+
+/// Like [`get_or_fetch_word`], with the fetch of a missing word taking its
+/// turn in the fetch queue, so that the positions the batch and stream
+/// lookups report count single lookups too.
+async fn get_or_queue_word(get_word_state: &GetWordState, requested_word: &str) -> Result<Word> {
+    let requested_word = normalise_word(requested_word);
+    if let Some(word) = get_word_from_db(get_word_state, &requested_word).await? {
+        return Ok(word);
+    }
+    let (_, result_rx) = get_word_state.fetch_queue.push(&requested_word);
+    result_rx
+        .await
+        .unwrap_or_else(|_| Err("The lookup was dropped".to_string()))
+        .map_err(anyhow::Error::msg)
+}
+
+async fn get_word_from_db(get_word_state: &GetWordState, requested_word: &str) -> Result<Option<Word>> {
     let db_client = &get_word_state.db_client;
-    let cambrinary_session_tracker = &get_word_state.cambrinary_session_tracker;
+    let word_from_db = db_client.try_to_get_word(requested_word).await?;
+    if let Some(word) = &word_from_db {
+        db_client.record_word_access(&word.word);
+    }
+    Ok(word_from_db)
+}
+
+async fn fetch_and_store_word(get_word_state: GetWordState, requested_word: String) -> Result<Word, String> {
+    fetch_and_store(&get_word_state, &requested_word)
+        .await
+        .map_err(|e| format!("{:#}", e))
+}
 
-    let requested_word = requested_word.word;
+async fn fetch_and_store(get_word_state: &GetWordState, requested_word: &str) -> Result<Word> {
+    let db_client = &get_word_state.db_client;
+    let cambrinary_session_tracker = &get_word_state.cambrinary_session_tracker;
 
     loop {
-        // Look for the word in the database
-        let word_from_db = db_client.try_to_get_word(&requested_word).await;
-        println!("{:?}", word_from_db);
-        if word_from_db.is_err() {
-            tracing::error!("{:?}", word_from_db.err().unwrap());
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_string());
-        }
-        let word_from_db = word_from_db.unwrap();
-        if word_from_db.is_some() {
-            let word = word_from_db.unwrap();
-            let res = (&db_client).update_word_metadata(&word.word).await;
-            match res {
-                Ok(_) => info!("Word {} metadata updated", word.word),
-                Err(e) => tracing::warn!("{:?}", e),
-            }
-            if !word.has_meaning {
-                return (StatusCode::NOT_FOUND, "Word not found".to_string());
-            }
-            let word = serde_json::to_string(&word).unwrap();
-            return (StatusCode::OK, word);
+        // Look for the word in the database, a fetch that ended while this
+        // one was starting or waiting for a session may have stored it
+        if let Some(word) = get_word_from_db(get_word_state, requested_word).await? {
+            return Ok(word);
         }
         info!("Word {} not found in the database, attempting to acquire a session", requested_word);
 
@@ -67,26 +140,20 @@ pub async fn get_word(
     }
     // Start a session and fetch the word
     cambrinary_session_tracker.start_session().await;
-    let word = fetch_word_from_cambrinary(&requested_word).await;
+    let word = get_word_state.cambridge_dictionary.fetch_word(requested_word).await;
     cambrinary_session_tracker.end_session().await;
 
-    if word.is_err() {
-        tracing::error!("{:?}", word.err().unwrap());
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_string());
-    }
-    let word = word.unwrap();
-    if word.is_none() {
-        let mut word = Word::default();
-        word.word = requested_word.clone();
-        tracing::info!("Word {} not found, inserting to db", requested_word);
-        db_client.insert_word(word.clone()).await.unwrap();
-        return (StatusCode::NOT_FOUND, "Word not found".to_string());
-    }
-    let word = word.unwrap();
-    db_client.insert_word(word.clone()).await.unwrap();
+    let word = match word? {
+        FetchedWord::Found(word) => {
+            db_client.insert_word(word.clone()).await?;
+            word
+        }
+        FetchedWord::NotFound(reason) => {
+            tracing::info!("Word {} not found ({}), inserting to db", requested_word, reason.as_str());
+            db_client.insert_not_found_word(requested_word, reason).await?
+        }
+    };
     info!("Word {} inserted into the database", requested_word);
 
-    let word = serde_json::to_string(&word).unwrap();
-    (StatusCode::OK, word)
+    Ok(word)
 }
-
@@ -0,0 +1,91 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+
+use crate::routes::Word;
+
+pub type SharedFetch = Shared<BoxFuture<'static, Result<Word, String>>>;
+
+/// Fetches of missing words that are running, by normalised word, so that
+/// lookups of the same word at the same time share one fetch from the
+/// Cambridge dictionary and one insert.
+///
+/// The fetch runs in its own task, a client that goes away doesn't cancel
+/// it for the others.
+#[derive(Default)]
+pub struct InFlightFetches {
+    fetches: Mutex<HashMap<String, SharedFetch>>,
+}
+
+impl InFlightFetches {
+    /// Joins the fetch of the word that is running or starts `fetch`.
+    pub fn join_or_start<F>(self: &Arc<Self>, word: &str, fetch: F) -> SharedFetch
+    where
+        F: Future<Output = Result<Word, String>> + Send + 'static,
+    {
+        let mut fetches = self.fetches.lock().unwrap();
+        if let Some(running_fetch) = fetches.get(word) {
+            return running_fetch.clone();
+        }
+        // The entry is added before the lock is released, the task can't
+        // remove it before that
+        let in_flight_fetches = Arc::clone(self);
+        let key = word.to_string();
+        let task = tokio::spawn(async move {
+            let result = fetch.await;
+            in_flight_fetches.fetches.lock().unwrap().remove(&key);
+            result
+        });
+        let shared_fetch = async move {
+            task.await.unwrap_or_else(|e| Err(format!("Fetch task failed: {}", e)))
+        }
+        .boxed()
+        .shared();
+        fetches.insert(word.to_string(), shared_fetch.clone());
+        shared_fetch
+    }
+
+    pub fn running_count(&self) -> usize {
+        self.fetches.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_fetches_of_a_word_are_shared() {
+        let in_flight_fetches = Arc::new(InFlightFetches::default());
+        let fetch_count = Arc::new(AtomicU32::new(0));
+        let fetch = |fetch_count: Arc<AtomicU32>| async move {
+            fetch_count.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            Ok(Word { word: "happy".to_string(), ..Default::default() })
+        };
+
+        let first = in_flight_fetches.join_or_start("happy", fetch(fetch_count.clone()));
+        let second = in_flight_fetches.join_or_start("happy", fetch(fetch_count.clone()));
+        let other = in_flight_fetches.join_or_start("sad", fetch(fetch_count.clone()));
+        assert_eq!(in_flight_fetches.running_count(), 2);
+
+        let (first, second, _) = futures::join!(first, second, other);
+        assert_eq!(first.unwrap().word, "happy");
+        assert_eq!(second.unwrap().word, "happy");
+        assert_eq!(fetch_count.load(Ordering::SeqCst), 2);
+        assert_eq!(in_flight_fetches.running_count(), 0);
+
+        // Once it is done the next lookup fetches again
+        in_flight_fetches.join_or_start("happy", fetch(fetch_count.clone())).await.unwrap();
+        assert_eq!(fetch_count.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,12 +1,54 @@
-use std::{sync::Arc, time::Duration};
+use std::{net::IpAddr, num::NonZeroU32, sync::Arc, time::Duration};
 
+use governor::{clock::{Clock, QuantaClock, QuantaInstant}, middleware::NoOpMiddleware, InsufficientCapacity};
 use tower_governor::{governor::{GovernorConfig, GovernorConfigBuilder}, key_extractor::{GlobalKeyExtractor, PeerIpKeyExtractor}};
 
+pub type GetWordLimiterConfig = Arc<GovernorConfig<PeerIpKeyExtractor, NoOpMiddleware<QuantaInstant>>>;
+pub type GetWordGlobalLimiterConfig = Arc<GovernorConfig<GlobalKeyExtractor, NoOpMiddleware<QuantaInstant>>>;
+
+/// Both limiters of the word endpoints, for requests that cost more than
+/// the one request the governor layers already counted.
+#[derive(Clone)]
+pub struct GetWordLimiters {
+    pub per_ip: GetWordLimiterConfig,
+    pub global: GetWordGlobalLimiterConfig,
+}
+
+impl GetWordLimiters {
+    /// Takes `extra_cost` more requests from the global budget and from the
+    /// one of `ip`, or returns how long to wait before retrying. Costs over
+    /// the burst size are capped to it, otherwise they could never pass.
+    ///
+    /// Taken requests can't be given back, so the global budget is checked
+    /// first: a request it rejects leaves the budget of `ip` alone.
+    pub fn charge(&self, ip: IpAddr, extra_cost: u32) -> Result<(), Duration> {
+        let extra_cost = match NonZeroU32::new(extra_cost) {
+            Some(extra_cost) => extra_cost,
+            None => return Ok(()),
+        };
+        let now = QuantaClock::default().now();
+
+        let global = self.global.limiter();
+        let outcome = match global.check_key_n(&(), extra_cost) {
+            Err(InsufficientCapacity(burst_size)) => global.check_key_n(&(), NonZeroU32::new(burst_size).unwrap()).unwrap(),
+            Ok(outcome) => outcome,
+        };
+        outcome.map_err(|not_until| not_until.wait_time_from(now))?;
+
+        let per_ip = self.per_ip.limiter();
+        let outcome = match per_ip.check_key_n(&ip, extra_cost) {
+            Err(InsufficientCapacity(burst_size)) => per_ip.check_key_n(&ip, NonZeroU32::new(burst_size).unwrap()).unwrap(),
+            Ok(outcome) => outcome,
+        };
+        outcome.map_err(|not_until| not_until.wait_time_from(now))
+    }
+}
+
 
 pub fn create_limiter_for_get_words_endpoint(
     per_second: u64,
     burst_size: u32,
-) -> Arc<GovernorConfig<PeerIpKeyExtractor, governor::middleware::NoOpMiddleware<governor::clock::QuantaInstant>>> {
+) -> GetWordLimiterConfig {
     let governor_conf = Arc::new(
         GovernorConfigBuilder::default()
             .per_second(per_second)
@@ -31,7 +73,7 @@ pub fn create_limiter_for_get_words_endpoint(
 pub fn create_global_limiter_for_get_words_endpoint(
     per_second: u64,
     burst_size: u32,
-) -> Arc<GovernorConfig<GlobalKeyExtractor, governor::middleware::NoOpMiddleware<governor::clock::QuantaInstant>>> {
+) -> GetWordGlobalLimiterConfig {
     let governor_conf = Arc::new(
         GovernorConfigBuilder::default()
             .per_second(per_second)
@@ -52,4 +94,52 @@ pub fn create_global_limiter_for_get_words_endpoint(
     });
 
     governor_conf
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Limiters that don't refill during a test.
+    fn limiters(per_ip_burst_size: u32, global_burst_size: u32) -> GetWordLimiters {
+        GetWordLimiters {
+            per_ip: Arc::new(GovernorConfigBuilder::default().per_second(3600).burst_size(per_ip_burst_size).finish().unwrap()),
+            global: Arc::new(
+                GovernorConfigBuilder::default()
+                    .per_second(3600)
+                    .burst_size(global_burst_size)
+                    .key_extractor(GlobalKeyExtractor)
+                    .finish()
+                    .unwrap()
+            ),
+        }
+    }
+
+    #[test]
+    fn test_charge_takes_the_extra_cost() {
+        let limiters = limiters(5, 100);
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        assert!(limiters.charge(ip, 0).is_ok());
+        assert!(limiters.charge(ip, 3).is_ok());
+        assert!(limiters.charge(ip, 3).is_err());
+        assert!(limiters.charge(ip, 2).is_ok());
+        assert!(limiters.charge(ip, 1).is_err());
+        // Nothing left, but free requests still pass
+        assert!(limiters.charge(ip, 0).is_ok());
+
+        // Capped to the burst size
+        let other_ip = IpAddr::from([127, 0, 0, 2]);
+        assert!(limiters.charge(other_ip, 50).is_ok());
+        assert!(limiters.charge(other_ip, 1).is_err());
+    }
+
+    #[test]
+    fn test_global_rejection_leaves_the_ip_budget_alone() {
+        let limiters = limiters(10, 4);
+        assert!(limiters.charge(IpAddr::from([127, 0, 0, 1]), 4).is_ok());
+
+        let other_ip = IpAddr::from([127, 0, 0, 2]);
+        assert!(limiters.charge(other_ip, 1).is_err());
+        let per_ip = limiters.per_ip.limiter();
+        assert!(per_ip.check_key_n(&other_ip, NonZeroU32::new(10).unwrap()).unwrap().is_ok());
+    }
+}
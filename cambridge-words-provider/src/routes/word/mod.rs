@@ -1,19 +1,48 @@
 mod get_word;
+mod batch;
 mod cambrinary_bindings;
+mod cambridge_dictionary;
+mod fetch_queue;
+mod get_method;
+mod in_flight;
 mod limiter;
+mod refresh;
+mod response;
+mod search;
+mod stream;
 
 pub use get_word::{
     get_word,
     GetWordState
 };
 
+pub use batch::{
+    words_method,
+    LookupQueue
+};
+
+pub use get_method::words_get_method;
+
+pub use in_flight::InFlightFetches;
+
+pub use refresh::spawn_refresh_job;
+
+pub use fetch_queue::{
+    spawn_fetch_workers,
+    FetchQueue
+};
+
 pub use cambrinary_bindings::{
     Word,
-    MeaningWithExamples
+    MeaningWithExamples,
+    Entry,
+    Sense
 };
 
 pub use limiter::{
     create_global_limiter_for_get_words_endpoint,
-    create_limiter_for_get_words_endpoint
+    create_limiter_for_get_words_endpoint,
+    GetWordLimiters
 };
-pub use cambrinary_bindings::CambrinarySessionTracker;
\ No newline at end of file
+pub use cambrinary_bindings::CambrinarySessionTracker;
+pub use cambridge_dictionary::CambridgeDictionary;
\ No newline at end of file
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use tracing::{error, info, warn};
+
+use crate::configuration::RefreshConfig;
+
+use super::{
+    cambridge_dictionary::FetchedWord,
+    get_word::GetWordState,
+    Word
+};
+
+/// Starts the job that fetches stored definitions again once they are older
+/// than `max_age_s`, the most read words first.
+///
+/// It takes cambrinary sessions like lookups do but only when no lookup is
+/// waiting in the fetch queue, and keeps the stored definitions when the new
+/// ones have less in them, e.g. because the parser misses part of a changed
+/// page.
+pub fn spawn_refresh_job(get_word_state: GetWordState, refresh_config: RefreshConfig) {
+    info!(
+        "Refreshing definitions older than {}s every {}s",
+        refresh_config.max_age_s, refresh_config.interval_s
+    );
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(refresh_config.interval_s)).await;
+            refresh_stale_words(&get_word_state, &refresh_config).await;
+        }
+    });
+}
+
+async fn refresh_stale_words(get_word_state: &GetWordState, refresh_config: &RefreshConfig) {
+    let db_client = &get_word_state.db_client;
+    let cambrinary_session_tracker = &get_word_state.cambrinary_session_tracker;
+
+    let fetched_before = chrono::Utc::now().timestamp() - refresh_config.max_age_s as i64;
+    let stale_words = match db_client.stale_words(fetched_before, refresh_config.batch_size).await {
+        Ok(stale_words) => stale_words,
+        Err(e) => {
+            error!("Could not read the stale words: {:?}", e);
+            return;
+        }
+    };
+    if stale_words.is_empty() {
+        return;
+    }
+    info!("Refreshing {} stale words", stale_words.len());
+
+    let mut refreshed = 0;
+    for stored_word in stale_words {
+        // Lookups of clients go first
+        while get_word_state.fetch_queue.waiting_count() > 0
+            || !cambrinary_session_tracker.can_start_session().await
+        {
+            cambrinary_session_tracker.wait_for_session().await;
+        }
+        cambrinary_session_tracker.start_session().await;
+        let fetched_word = get_word_state.cambridge_dictionary.fetch_word(&stored_word.word).await;
+        cambrinary_session_tracker.end_session().await;
+
+        let result = match fetched_word {
+            Ok(FetchedWord::Found(word)) if is_at_least_as_complete(&word, &stored_word) => {
+                refreshed += 1;
+                db_client.update_refreshed_word(&word).await
+            }
+            Ok(FetchedWord::Found(_)) => {
+                warn!("The new definitions of {} have less in them, keeping the stored ones", stored_word.word);
+                db_client.mark_word_refreshed(&stored_word.word).await
+            }
+            Ok(FetchedWord::NotFound(reason)) => {
+                warn!("{} is gone from the dictionary ({}), keeping the stored definitions", stored_word.word, reason.as_str());
+                db_client.mark_word_refreshed(&stored_word.word).await
+            }
+            Err(e) => {
+                // The dictionary is likely down, the next round tries again
+                error!("Could not refresh {}: {:#}", stored_word.word, e);
+                break;
+            }
+        };
+        if let Err(e) = result {
+            error!("Could not store the refresh of {}: {:?}", stored_word.word, e);
+        }
+    }
+    info!("Refreshed the definitions of {} words", refreshed);
+}
+
+/// Whether `word` has at least as many senses, entries and examples as
+/// `stored_word`. Words stored before entries existed have none.
+fn is_at_least_as_complete(word: &Word, stored_word: &Word) -> bool {
+    fn example_count(word: &Word) -> usize {
+        word.meanings_with_examples.iter().map(|meaning| meaning.examples.len()).sum()
+    }
+    fn sense_count(word: &Word) -> usize {
+        word.entries.iter().map(|entry| entry.senses.len()).sum()
+    }
+
+    word.has_meaning
+        && word.meanings_with_examples.len() >= stored_word.meanings_with_examples.len()
+        && example_count(word) >= example_count(stored_word)
+        && word.entries.len() >= stored_word.entries.len()
+        && sense_count(word) >= sense_count(stored_word)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::routes::{Entry, MeaningWithExamples, Sense};
+
+    use super::*;
+
+    fn word(meanings: &[(&str, usize)], with_entries: bool) -> Word {
+        let meanings_with_examples = meanings
+            .iter()
+            .map(|(meaning, examples)| MeaningWithExamples {
+                meaning: meaning.to_string(),
+                examples: vec!["an example".to_string(); *examples],
+            })
+            .collect::<Vec<MeaningWithExamples>>();
+        let entries = if with_entries {
+            vec![Entry {
+                headword: "happy".to_string(),
+                senses: meanings_with_examples
+                    .iter()
+                    .map(|meaning| Sense {
+                        definition: meaning.meaning.clone(),
+                        examples: meaning.examples.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }]
+        } else {
+            vec![]
+        };
+        Word {
+            word: "happy".to_string(),
+            has_meaning: true,
+            meanings_with_examples,
+            entries,
+        }
+    }
+
+    #[test]
+    fn test_refresh_keeps_the_more_complete_definitions() {
+        let stored_word = word(&[("feeling pleasure", 2), ("lucky", 1)], false);
+
+        // The same page parsed again, now with entries
+        assert!(is_at_least_as_complete(&word(&[("feeling pleasure", 2), ("lucky", 1)], true), &stored_word));
+        assert!(is_at_least_as_complete(&word(&[("feeling pleasure", 3), ("lucky", 1), ("willing", 0)], true), &stored_word));
+
+        // A sense or an example went missing
+        assert!(!is_at_least_as_complete(&word(&[("feeling pleasure", 2)], true), &stored_word));
+        assert!(!is_at_least_as_complete(&word(&[("feeling pleasure", 1), ("lucky", 1)], true), &stored_word));
+        // Entries aren't given up for the old format
+        let stored_word = word(&[("feeling pleasure", 2)], true);
+        assert!(!is_at_least_as_complete(&word(&[("feeling pleasure", 2)], false), &stored_word));
+        assert!(!is_at_least_as_complete(&Word::default(), &stored_word));
+    }
+}
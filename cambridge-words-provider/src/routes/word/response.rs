@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use super::{MeaningWithExamples, Word};
+
+/// Version 1 only has the meanings with their examples, version 2 adds the
+/// entries with the part of speech, pronunciation and details of each sense.
+pub const LATEST_RESPONSE_VERSION: u32 = 2;
+
+/// `?version=` of the word endpoints, clients that don't send it get
+/// version 1 so they keep working.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct ResponseVersion {
+    version: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum VersionedWord<'a> {
+    V1(WordV1<'a>),
+    V2(WordV2<'a>),
+}
+
+#[derive(Serialize)]
+pub struct WordV1<'a> {
+    word: &'a str,
+    meanings_with_examples: &'a [MeaningWithExamples],
+    has_meaning: bool,
+}
+
+#[derive(Serialize)]
+pub struct WordV2<'a> {
+    version: u32,
+    #[serde(flatten)]
+    word: &'a Word,
+}
+
+impl ResponseVersion {
+    /// The requested version, or an error message for versions this
+    /// provider doesn't know.
+    pub fn version(&self) -> Result<u32, String> {
+        match self.version.unwrap_or(1) {
+            version @ 1..=LATEST_RESPONSE_VERSION => Ok(version),
+            version => Err(format!(
+                "Unsupported response version {}, the latest is {}", version, LATEST_RESPONSE_VERSION
+            )),
+        }
+    }
+}
+
+pub fn render_word(word: &Word, version: u32) -> String {
+    serde_json::to_string(&versioned_word(word, version)).unwrap()
+}
+
+/// The word in the shape of the requested version.
+pub fn versioned_word(word: &Word, version: u32) -> VersionedWord<'_> {
+    match version {
+        1 => VersionedWord::V1(WordV1 {
+            word: &word.word,
+            meanings_with_examples: &word.meanings_with_examples,
+            has_meaning: word.has_meaning,
+        }),
+        _ => VersionedWord::V2(WordV2 {
+            version,
+            word,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::routes::{Entry, Sense};
+
+    use super::*;
+
+    fn happy() -> Word {
+        Word {
+            word: "happy".to_string(),
+            meanings_with_examples: vec![MeaningWithExamples {
+                meaning: "feeling pleasure".to_string(),
+                examples: vec!["She looks happy.".to_string()],
+            }],
+            has_meaning: true,
+            entries: vec![Entry {
+                headword: "happy".to_string(),
+                part_of_speech: Some("adjective".to_string()),
+                uk_ipa: Some("ˈhæp.i".to_string()),
+                senses: vec![Sense {
+                    definition: "feeling pleasure".to_string(),
+                    examples: vec!["She looks happy.".to_string()],
+                    cefr_level: Some("A1".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn test_response_version() {
+        assert_eq!(ResponseVersion::default().version(), Ok(1));
+        assert_eq!(ResponseVersion { version: Some(1) }.version(), Ok(1));
+        assert_eq!(ResponseVersion { version: Some(2) }.version(), Ok(2));
+        assert!(ResponseVersion { version: Some(0) }.version().is_err());
+        assert!(ResponseVersion { version: Some(LATEST_RESPONSE_VERSION + 1) }.version().is_err());
+    }
+
+    #[test]
+    fn test_version_1_has_no_entries() {
+        let rendered = serde_json::from_str::<serde_json::Value>(&render_word(&happy(), 1)).unwrap();
+        assert_eq!(rendered, serde_json::json!({
+            "word": "happy",
+            "meanings_with_examples": [{"meaning": "feeling pleasure", "examples": ["She looks happy."]}],
+            "has_meaning": true,
+        }));
+    }
+
+    #[test]
+    fn test_version_2_has_the_entries() {
+        let rendered = serde_json::from_str::<serde_json::Value>(&render_word(&happy(), 2)).unwrap();
+        assert_eq!(rendered["version"], 2);
+        assert_eq!(rendered["word"], "happy");
+        assert_eq!(rendered["has_meaning"], true);
+        assert_eq!(rendered["meanings_with_examples"][0]["meaning"], "feeling pleasure");
+        assert_eq!(rendered["entries"][0]["part_of_speech"], "adjective");
+        assert_eq!(rendered["entries"][0]["uk_ipa"], "ˈhæp.i");
+        assert_eq!(rendered["entries"][0]["senses"][0]["cefr_level"], "A1");
+    }
+}
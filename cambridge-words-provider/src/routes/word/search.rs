@@ -0,0 +1,108 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json
+};
+use serde::{
+    Serialize, Deserialize
+};
+use tracing::error;
+
+use crate::database::DefinitionField;
+
+use super::{
+    get_word::GetWordState,
+    response::{versioned_word, VersionedWord},
+};
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 100;
+const MAX_PHRASE_LENGTH: usize = 100;
+
+/// `?q=` is the phrase, `?in=` is `meanings`, `examples` or `both` (the
+/// default).
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchParams {
+    q: Option<String>,
+    #[serde(rename = "in")]
+    field: Option<DefinitionField>,
+    limit: Option<i64>,
+}
+
+impl SearchParams {
+    /// The trimmed phrase, the field and the limit to search with, or why
+    /// the parameters can't be used.
+    fn resolve(self) -> Result<(String, DefinitionField, i64), String> {
+        let phrase = self.q.unwrap_or_default().trim().to_string();
+        if phrase.is_empty() {
+            return Err("Missing ?q= phrase".to_string());
+        }
+        if phrase.chars().count() > MAX_PHRASE_LENGTH {
+            return Err(format!("The phrase can be at most {} characters long", MAX_PHRASE_LENGTH));
+        }
+        let field = self.field.unwrap_or(DefinitionField::Both);
+        let limit = self.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+        Ok((phrase, field, limit))
+    }
+}
+
+#[derive(Serialize)]
+struct SearchResponse<'a> {
+    results: Vec<VersionedWord<'a>>,
+}
+
+/// Answers `GET /v1/words:search`, the stored words whose meanings or
+/// examples contain a phrase.
+pub(super) async fn search_words_response(
+    search_params: SearchParams,
+    version: u32,
+    get_word_state: &GetWordState,
+) -> Response {
+    let (phrase, field, limit) = match search_params.resolve() {
+        Ok(search) => search,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let words = get_word_state.db_client.search_definitions(&phrase, field, limit).await;
+    if words.is_err() {
+        error!("{:?}", words.err().unwrap());
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_string()).into_response();
+    }
+    let words = words.unwrap();
+    let results = words
+        .iter()
+        .map(|word| versioned_word(word, version))
+        .collect();
+    Json(SearchResponse { results }).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::Query, http::Uri};
+
+    use super::*;
+
+    fn search_params(query: &str) -> SearchParams {
+        let uri: Uri = format!("/v1/words:search?{}", query).parse().unwrap();
+        Query::<SearchParams>::try_from_uri(&uri).unwrap().0
+    }
+
+    #[test]
+    fn test_search_params() {
+        assert_eq!(
+            search_params("q=%20feeling%20pleasure%20").resolve(),
+            Ok(("feeling pleasure".to_string(), DefinitionField::Both, DEFAULT_SEARCH_LIMIT))
+        );
+        assert_eq!(
+            search_params("q=pleasure&in=examples&limit=5").resolve(),
+            Ok(("pleasure".to_string(), DefinitionField::Examples, 5))
+        );
+        // The limit is clamped
+        assert_eq!(search_params("q=pleasure&limit=0").resolve().unwrap().2, 1);
+        assert_eq!(search_params("q=pleasure&limit=1000").resolve().unwrap().2, MAX_SEARCH_LIMIT);
+
+        assert!(search_params("").resolve().is_err());
+        assert!(search_params("q=%20%20").resolve().is_err());
+        assert!(search_params(&format!("q={}", "a".repeat(MAX_PHRASE_LENGTH + 1))).resolve().is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    response::Response,
+};
+use futures::{stream::FuturesUnordered, StreamExt};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use super::{
+    batch::{charge_for_words, is_too_long, unique_words, BatchRequest, BatchResult},
+    get_word::GetWordState,
+    response::versioned_word,
+};
+
+/// How long a client has to send its words after connecting.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the queue position is sent while nothing resolves.
+const QUEUE_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Messages sent to a stream client, as JSON text messages.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamMessage<'a> {
+    /// A word resolved, the fields are those of a batch result.
+    Result {
+        #[serde(flatten)]
+        result: BatchResult<'a>,
+    },
+    /// `position` is the number of words of other clients ahead of the
+    /// first word of this one still waiting for a fetch.
+    Queue {
+        position: usize,
+        waiting: usize,
+        resolved: usize,
+        total: usize,
+    },
+    /// Every word resolved, the server closes the socket after it.
+    Done,
+    /// The request was rejected, nothing follows it.
+    Error {
+        error: String,
+        retry_after: Option<u64>,
+    },
+}
+
+/// Answers `GET /v1/words:stream`, opened as a WebSocket.
+///
+/// The client sends `{"words": [...]}` once. Words in the database are sent
+/// right away, the others as their fetch ends, with `queue` messages in
+/// between so the client can show how far along its words are.
+pub(super) fn stream_words_response(
+    web_socket: WebSocketUpgrade,
+    address: SocketAddr,
+    version: u32,
+    get_word_state: GetWordState,
+) -> Response {
+    web_socket.on_upgrade(move |socket| async move {
+        if let Err(e) = stream_words(socket, address, version, get_word_state).await {
+            warn!("Word stream of {} ended: {:?}", address, e);
+        }
+    })
+}
+
+async fn send(socket: &mut WebSocket, message: &StreamMessage<'_>) -> anyhow::Result<()> {
+    let message = serde_json::to_string(message)?;
+    socket.send(Message::Text(message)).await?;
+    Ok(())
+}
+
+async fn reject(socket: &mut WebSocket, error: String, retry_after: Option<u64>) -> anyhow::Result<()> {
+    send(socket, &StreamMessage::Error { error, retry_after }).await?;
+    socket.send(Message::Close(None)).await?;
+    Ok(())
+}
+
+async fn receive_request(socket: &mut WebSocket) -> anyhow::Result<Option<BatchRequest>> {
+    loop {
+        let message = match tokio::time::timeout(REQUEST_TIMEOUT, socket.recv()).await {
+            Ok(Some(message)) => message?,
+            Ok(None) | Err(_) => return Ok(None),
+        };
+        match message {
+            Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+            Message::Close(_) => return Ok(None),
+            _ => continue,
+        }
+    }
+}
+
+async fn stream_words(
+    mut socket: WebSocket,
+    address: SocketAddr,
+    version: u32,
+    get_word_state: GetWordState,
+) -> anyhow::Result<()> {
+    let request = match receive_request(&mut socket).await {
+        Ok(Some(request)) => request,
+        Ok(None) => return Ok(()),
+        Err(e) => return reject(&mut socket, format!("Invalid request: {}", e), None).await,
+    };
+    let config = &get_word_state.batch_lookup_config;
+    let words = unique_words(request.words);
+    if words.len() > config.max_words {
+        let error = format!("A stream can have at most {} words", config.max_words);
+        return reject(&mut socket, error, None).await;
+    }
+    if let Err(wait_time) = charge_for_words(&get_word_state, address, words.len()) {
+        info!("Stream of {} words from {} is rate limited for {:?}", words.len(), address, wait_time);
+        let retry_after = wait_time.as_secs().max(1);
+        return reject(&mut socket, "Too Many Requests".to_string(), Some(retry_after)).await;
+    }
+    let total = words.len();
+    let mut resolved = 0;
+
+    // Words in the database first
+    let (valid_words, invalid_words): (Vec<String>, Vec<String>) = words
+        .into_iter()
+        .partition(|word| !is_too_long(word));
+    for word in invalid_words {
+        send(&mut socket, &StreamMessage::Result { result: BatchResult::too_long(word) }).await?;
+        resolved += 1;
+    }
+    let found_words = match get_word_state.db_client.try_to_get_words(&valid_words).await {
+        Ok(found_words) => found_words,
+        Err(e) => {
+            error!("{:?}", e);
+            return reject(&mut socket, "Internal Server Error".to_string(), None).await;
+        }
+    };
+    if !found_words.is_empty() {
+        let found = found_words.iter().map(|word| word.word.clone()).collect::<Vec<String>>();
+        get_word_state.db_client.record_words_access(&found);
+    }
+    let mut missing_words = vec![];
+    for word in valid_words {
+        match found_words.iter().find(|found_word| found_word.word == word) {
+            Some(found_word) => {
+                let result = if found_word.has_meaning {
+                    BatchResult::Found { word, definition: versioned_word(found_word, version) }
+                } else {
+                    BatchResult::NotFound { word }
+                };
+                send(&mut socket, &StreamMessage::Result { result }).await?;
+                resolved += 1;
+            }
+            None => missing_words.push(word),
+        }
+    }
+
+    // Then the others as they are fetched
+    let fetch_queue = &get_word_state.fetch_queue;
+    let mut waiting_ids = vec![];
+    let mut fetches = FuturesUnordered::new();
+    for word in missing_words {
+        let (id, result_rx) = fetch_queue.push(&word);
+        waiting_ids.push(id);
+        fetches.push(async move {
+            let result = result_rx
+                .await
+                .unwrap_or_else(|_| Err("The lookup was dropped".to_string()));
+            (id, word, result)
+        });
+    }
+    info!(
+        "Stream of {} words from {}, {} to fetch, {} in the fetch queue",
+        total, address, fetches.len(), fetch_queue.waiting_count()
+    );
+
+    let mut queue_updates = tokio::time::interval(QUEUE_UPDATE_INTERVAL);
+    while !fetches.is_empty() {
+        tokio::select! {
+            Some((id, word, result)) = fetches.next() => {
+                waiting_ids.retain(|waiting_id| *waiting_id != id);
+                let result = match &result {
+                    Ok(found_word) if found_word.has_meaning => BatchResult::Found {
+                        word,
+                        definition: versioned_word(found_word, version),
+                    },
+                    Ok(_) => BatchResult::NotFound { word },
+                    Err(error) => BatchResult::Error { word, error: error.clone() },
+                };
+                send(&mut socket, &StreamMessage::Result { result }).await?;
+                resolved += 1;
+            }
+            _ = queue_updates.tick() => {}
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                        info!("Stream of {} closed with {} words left", address, fetches.len());
+                        return Ok(());
+                    }
+                    _ => continue,
+                }
+            }
+        }
+        // Jobs already taken by a worker have no position, they are next
+        let position = waiting_ids
+            .iter()
+            .filter_map(|id| fetch_queue.position(*id))
+            .min()
+            .unwrap_or(0);
+        send(&mut socket, &StreamMessage::Queue {
+            position,
+            waiting: fetches.len(),
+            resolved,
+            total,
+        }).await?;
+    }
+
+    send(&mut socket, &StreamMessage::Done).await?;
+    socket.send(Message::Close(None)).await?;
+    Ok(())
+}
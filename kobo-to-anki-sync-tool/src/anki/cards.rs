@@ -1,78 +1,213 @@
-use std::sync::{Arc, Mutex};
-
 use serde::{Deserialize, Serialize};
 
-use super::AnkiClient;
+use super::{AnkiClient, LookupHistory};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Word {
     pub word: String,
     pub meanings_with_examples: Vec<MeaningWithExamples>,
+    #[serde(default)]
+    pub uk_ipa: Option<String>,
+    #[serde(default)]
+    pub us_ipa: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct MeaningWithExamples {
     pub meaning: String,
     pub examples: Vec<String>,
+    #[serde(default)]
+    pub details: SenseDetails,
+}
+
+/// What the provider knows about a meaning besides its definition, empty
+/// for providers that only send meanings and examples.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SenseDetails {
+    pub part_of_speech: Option<String>,
+    pub guide_word: Option<String>,
+    pub cefr_level: Option<String>,
+    pub grammar: Vec<String>,
+    pub labels: Vec<String>,
+    pub register: Option<String>,
 }
 
-impl<'a> AnkiClient<'a> {
-    pub async fn add_card_with_fromating(&self, word: &str, deck_name: &str, connection_status: Arc<Mutex<bool>>) {
-        let front = format!("<center><span style=\"font-size:3em;color:red\">{}</span></center>", word);
-        let mut back = String::new();
-        let word = self.get_word_from_api(word, connection_status).await.unwrap();
-        for meaning_with_examples in word.meanings_with_examples {
-            back.push_str(&format!("<span style=\"font-size:1.5em;color:cyan\">{}</span><br />", meaning_with_examples.meaning));
-            for example in meaning_with_examples.examples {
-                back.push_str(&format!("<span style=\"font-size:0.7em;color:white\">{}</span><br />", example));
-            }
+impl SenseDetails {
+    /// e.g. "adjective (PLEASED) A1 [after verb] formal", empty when there
+    /// are no details.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![];
+        parts.extend(self.part_of_speech.clone());
+        parts.extend(self.guide_word.as_ref().map(|guide_word| format!("({})", guide_word)));
+        parts.extend(self.cefr_level.clone());
+        if !self.grammar.is_empty() {
+            parts.push(format!("[{}]", self.grammar.join(", ")));
         }
+        parts.extend(self.register.clone());
+        parts.extend(self.labels.iter().cloned());
+        parts.join(" ")
+    }
+}
+
+/// HTML used to build the cards sent to Anki.
+///
+/// `front` gets `{word}`, `back` gets `{meanings}`, which is every meaning
+/// rendered with `meaning` (`{meaning}` and `{examples}`), and each example is
+/// rendered with `example` (`{example}`). Both sides also get the
+/// pronunciation as `{ipa_uk}` and `{ipa_us}`, and `meaning` gets the details
+/// of the sense as `{details}` or one by one as `{part_of_speech}`,
+/// `{guide_word}`, `{cefr_level}`, `{grammar}`, `{labels}` and `{register}`,
+/// all of them empty when the provider doesn't know them. The rendered front
+/// and back go into `front_field` and `back_field` of a note of type
+/// `note_type`. The forms a word was looked up in and the books go into
+/// `forms_field` and `books_field` when the note type has them.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CardTemplate {
+    pub front: String,
+    pub back: String,
+    pub meaning: String,
+    pub example: String,
+    pub note_type: String,
+    pub front_field: String,
+    pub back_field: String,
+    pub forms_field: Option<String>,
+    pub books_field: Option<String>,
+}
+
+impl Default for CardTemplate {
+    fn default() -> Self {
+        Self {
+            front: "<center><center><span style=\"font-size:3em;color:red\">{word}</span></center></center>".to_string(),
+            back: "<center>{meanings}</center>".to_string(),
+            meaning: "<span style=\"font-size:0.8em;color:gray\">{details}</span> <span style=\"font-size:1.5em;color:cyan\">{meaning}</span><br />{examples}".to_string(),
+            example: "<span style=\"font-size:0.7em;color:white\">{example}</span><br />".to_string(),
+            note_type: "Basic".to_string(),
+            front_field: "Front".to_string(),
+            back_field: "Back".to_string(),
+            forms_field: None,
+            books_field: None,
+        }
+    }
+}
+
+impl CardTemplate {
+    pub fn records_lookups(&self) -> bool {
+        self.forms_field.is_some() || self.books_field.is_some()
+    }
+}
+
+/// Builds the front and back of a card exactly as it is sent to Anki.
+pub fn format_card(word: &Word, template: &CardTemplate) -> (String, String) {
+    let front = replace_pronunciation(&template.front, word).replace("{word}", &word.word);
+    let mut meanings = String::new();
+    for meaning_with_examples in &word.meanings_with_examples {
+        let mut examples = String::new();
+        for example in &meaning_with_examples.examples {
+            examples.push_str(&template.example.replace("{example}", example));
+        }
+        let details = &meaning_with_examples.details;
+        meanings.push_str(
+            &template.meaning
+                .replace("{details}", &details.summary())
+                .replace("{part_of_speech}", details.part_of_speech.as_deref().unwrap_or_default())
+                .replace("{guide_word}", details.guide_word.as_deref().unwrap_or_default())
+                .replace("{cefr_level}", details.cefr_level.as_deref().unwrap_or_default())
+                .replace("{grammar}", &details.grammar.join(", "))
+                .replace("{labels}", &details.labels.join(", "))
+                .replace("{register}", details.register.as_deref().unwrap_or_default())
+                .replace("{meaning}", &meaning_with_examples.meaning)
+                .replace("{examples}", &examples)
+        );
+    }
+    let back = replace_pronunciation(&template.back, word).replace("{meanings}", &meanings);
+    (front, back)
+}
+
+/// The IPA goes between slashes so a template can use it as it is.
+fn replace_pronunciation(template: &str, word: &Word) -> String {
+    let ipa = |ipa: &Option<String>| ipa
+        .as_ref()
+        .map(|ipa| format!("/{}/", ipa))
+        .unwrap_or_default();
+    template
+        .replace("{ipa_uk}", &ipa(&word.uk_ipa))
+        .replace("{ipa_us}", &ipa(&word.us_ipa))
+}
+
+impl AnkiClient {
+    pub async fn add_card_with_fromating(
+        &self,
+        word: &Word,
+        deck_name: &str,
+        template: &CardTemplate,
+        lookup_history: &LookupHistory,
+    ) -> anyhow::Result<bool> {
+        let (front, back) = format_card(word, template);
         self.add_card_to_deck(
             deck_name,
-            format!("<center>{}</center>", front).as_str(),
-            format!("<center>{}</center>", back).as_str()
-        ).await.unwrap();
+            template,
+            front.as_str(),
+            back.as_str(),
+            lookup_history,
+        ).await
     }
+}
 
-    pub async fn get_word_from_api(&self, word: &str, connection_status: Arc<Mutex<bool>>) -> Option<Word> {
-        let cambridge_words_provider_url = &String::from_utf8_lossy(include_bytes!("../../server_addr")).to_string();
-        let url = format!("{}/get_word/{}", cambridge_words_provider_url, word);
-        let response = reqwest::get(url).await;
-        let word = match response {
-            Ok(response) => {
-                if response.status() == 404 {
-                    println!("Word {} not found", word);
-                    return None;
-                }
-                if response.status() == 500 {
-                    println!("Internal server error");
-                    return None;
-                }
-                if response.status() == 429 {
-                    tokio::time::sleep(std::time::Duration::from_secs(25)).await;
-                    let url = format!("{}/get_word/{}", cambridge_words_provider_url, word);
-                    let response = reqwest::get(url).await;
-                    if response.is_err() {
-                        let mut status = connection_status.lock().unwrap();
-                        *status = false;
-                        println!("{:?}", response.err().unwrap());
-                        return None;
-                    }
-                    let response = response.unwrap();
-                    let word = response.json::<Word>().await.unwrap();
-                    return Some(word);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                }
-                let word = response.json::<Word>().await.unwrap();
-                Some(word)
-            }
-            Err(e) => {
-                let mut status = connection_status.lock().unwrap();
-                *status = false;
-                println!("{:?}", e);
-                None
-            }
+    #[test]
+    fn test_format_card_fills_every_placeholder() {
+        let word = Word {
+            word: "happy".to_string(),
+            meanings_with_examples: vec![MeaningWithExamples {
+                meaning: "feeling pleasure".to_string(),
+                examples: vec!["She looks happy.".to_string(), "I'm happy for you.".to_string()],
+                details: SenseDetails {
+                    part_of_speech: Some("adjective".to_string()),
+                    guide_word: Some("PLEASED".to_string()),
+                    cefr_level: Some("A1".to_string()),
+                    grammar: vec!["after verb".to_string()],
+                    labels: vec!["UK".to_string(), "informal".to_string()],
+                    register: Some("formal".to_string()),
+                },
+            }],
+            uk_ipa: Some("ˈhæp.i".to_string()),
+            us_ipa: None,
+        };
+        let template = CardTemplate {
+            front: "{word} {ipa_uk} {ipa_us}".to_string(),
+            back: "<ul>{meanings}</ul>".to_string(),
+            meaning: "<li>{part_of_speech}|{guide_word}|{cefr_level}|{grammar}|{labels}|{register}|{meaning}|{examples}</li>".to_string(),
+            example: "<i>{example}</i>".to_string(),
+            ..Default::default()
         };
-        word
+        let (front, back) = format_card(&word, &template);
+        assert_eq!(front, "happy /ˈhæp.i/ ");
+        assert_eq!(
+            back,
+            "<ul><li>adjective|PLEASED|A1|after verb|UK, informal|formal|feeling pleasure|<i>She looks happy.</i><i>I'm happy for you.</i></li></ul>"
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_format_card_leaves_missing_details_empty() {
+        let word = Word {
+            word: "happy".to_string(),
+            meanings_with_examples: vec![MeaningWithExamples {
+                meaning: "feeling pleasure".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let template = CardTemplate {
+            meaning: "[{details}] {meaning} {cefr_level}{examples}".to_string(),
+            ..Default::default()
+        };
+        let (_, back) = format_card(&word, &template);
+        assert_eq!(back, "<center>[] feeling pleasure </center>");
+    }
+}
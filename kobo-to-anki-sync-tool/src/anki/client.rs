@@ -1,10 +1,20 @@
-        use anki_bridge::{prelude::{CardsInfoRequest, CardsInfoResponse, DeckNamesRequest, FindCardsRequest}, AnkiRequestable};
+        use anki_bridge::prelude::CardsInfoResponse;
         use anyhow::Result;
+        use serde::{de::DeserializeOwned, Deserialize};
 
+        use tokio::sync::mpsc::error::TryRecvError;
+
+        use crate::cards::extract_words_from_anki_cards;
         use crate::AppState;
 
-        pub struct AnkiClient<'a> {
-            communication_client: anki_bridge::AnkiClient<'a>
+        use super::{CardTemplate, LookupHistory};
+
+        pub const DEFAULT_ANKI_CONNECT_URL: &str = "http://localhost:8765";
+
+        pub struct AnkiClient {
+            http_client: reqwest::Client,
+            anki_connect_url: String,
+            anki_connect_key: Option<String>,
         }
 
         #[derive(Debug, Clone, Copy, PartialEq)]
@@ -15,57 +25,109 @@
             CouldNotConnect,
         }
 
-        impl<'a> AnkiClient<'a> {
-            pub fn new() -> Self {
-                let communication_client = anki_bridge::AnkiClient::new("http://localhost:8765");
+        /// The Anki search for the cards of the deck and its subdecks. The name
+        /// is quoted so that spaces don't split it into separate terms, and
+        /// `*` and `_` in it don't act as wildcards.
+        pub(super) fn deck_query(deck_name: &str) -> String {
+            let mut escaped = String::with_capacity(deck_name.len());
+            for c in deck_name.chars() {
+                if matches!(c, '"' | '*' | '_' | '\\') {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            format!("deck:\"{}\"", escaped)
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct AnkiConnectResponse<T> {
+            result: Option<T>,
+            error: Option<String>,
+        }
+
+        impl AnkiClient {
+            pub fn new(anki_connect_url: &str, anki_connect_key: Option<String>) -> Self {
                 let anki_client = AnkiClient {
-                    communication_client
+                    http_client: reqwest::Client::new(),
+                    anki_connect_url: anki_connect_url.to_string(),
+                    anki_connect_key: anki_connect_key.filter(|key| !key.is_empty()),
                 };
                 anki_client
             }
 
-            pub async fn get_decks(&self) -> Vec<String> {
-                let decks = self.communication_client.request(DeckNamesRequest).await.unwrap();
-                decks
+            /// Sends a single AnkiConnect action, see https://foosoft.net/projects/anki-connect/
+            /// Every action goes through here since anki_bridge can't send the
+            /// API key.
+            pub(super) async fn invoke<T: DeserializeOwned>(&self, action: &str, params: serde_json::Value) -> Result<T> {
+                let response = self.send::<T>(action, params).await?;
+                response.result.ok_or_else(|| anyhow::anyhow!("Anki Connect returned no result for {}", action))
             }
 
-            pub async fn check_connection(&self) -> Result<()> {
-                let _res = self.communication_client.request(DeckNamesRequest).await?;
+            /// For actions such as `updateNoteFields` whose result is `null`.
+            pub(super) async fn invoke_without_result(&self, action: &str, params: serde_json::Value) -> Result<()> {
+                self.send::<serde_json::Value>(action, params).await?;
                 Ok(())
             }
 
-            pub async fn add_card_to_deck(&self, deck_name: &str, front: &str, back: &str) -> Result<()> {
-                //println!("Front: {}", front);
-                //println!("Back: {}", back);
-                let request = reqwest::Client::new()
-                    .post("http://localhost:8765")
-                    .json(&serde_json::json!({
-                        "action": "addNote",
-                        "version": 6,
-                        "params": {
-                            "note": {
-                                "deckName": deck_name,
-                                "modelName": "Basic",
-                                "fields": {
-                                    "Front": front,
-                                    "Back": back
-                                },
-                                "options": {
-                                    "allowDuplicate": false
-                                },
-                                "tags": []
-                            }
-                        }
-                    }))
+            async fn send<T: DeserializeOwned>(&self, action: &str, params: serde_json::Value) -> Result<AnkiConnectResponse<T>> {
+                let mut request = serde_json::json!({
+                    "action": action,
+                    "version": 6,
+                    "params": params,
+                });
+                if let Some(key) = &self.anki_connect_key {
+                    request["key"] = serde_json::Value::String(key.clone());
+                }
+                let response = self.http_client
+                    .post(&self.anki_connect_url)
+                    .json(&request)
                     .send()
+                    .await?
+                    .json::<AnkiConnectResponse<T>>()
                     .await?;
-                let response = request.text().await?;
-                if response.contains("error\": null") {
-                    Ok(())
-                } else if response.contains("cannot create note because it is a duplicate") {
-                    Ok(())
-                } else {
-                    Err(anyhow::anyhow!("Anki Connect returned : {:?}", response))
+                if let Some(error) = response.error {
+                    return Err(anyhow::anyhow!("Anki Connect returned : {:?}", error));
+                }
+                Ok(response)
+            }
+
+            pub async fn get_decks(&self) -> Result<Vec<String>> {
+                let decks = self.invoke::<Vec<String>>("deckNames", serde_json::json!({})).await?;
+                Ok(decks)
+            }
+
+            pub async fn check_connection(&self) -> Result<()> {
+                let _res = self.get_decks().await?;
+                Ok(())
+            }
+
+            /// Returns `false` when Anki already has the note.
+            pub async fn add_card_to_deck(
+                &self,
+                deck_name: &str,
+                template: &CardTemplate,
+                front: &str,
+                back: &str,
+                lookup_history: &LookupHistory,
+            ) -> Result<bool> {
+                let mut fields = lookup_history.note_fields(template);
+                fields.insert(template.front_field.clone(), front.into());
+                fields.insert(template.back_field.clone(), back.into());
+                let response = self.invoke::<serde_json::Value>("addNote", serde_json::json!({
+                    "note": {
+                        "deckName": deck_name,
+                        "modelName": template.note_type,
+                        "fields": fields,
+                        "options": {
+                            "allowDuplicate": false
+                        },
+                        "tags": []
+                    }
+                })).await;
+                match response {
+                    Ok(_) => Ok(true),
+                    Err(e) if e.to_string().contains("cannot create note because it is a duplicate") => Ok(false),
+                    Err(e) => Err(e),
                 }
 
             }
@@ -75,8 +137,8 @@
                     app_state.anki_connection_status = AnkiConnectionStatus::Connecting;
                     let (tx, rx) = tokio::sync::mpsc::channel(1);
                     app_state.channels.anki_connection_status_rc = Some(rx);
+                    let anki_client = app_state.anki_client.clone();
                     app_state.async_rt.spawn(async move {
-                        let anki_client = AnkiClient::new();
                         let res = anki_client.check_connection().await;
                         match res {
                             Ok(_) => {
@@ -100,19 +162,118 @@
                 Ok(app_state.anki_connection_status)
             }
 
-            pub async fn get_cards_from_anki_deck(&self, deck_name: &str) -> Vec<CardsInfoResponse> {
-                let card_ids = self.communication_client.request(
-                    FindCardsRequest{
-                        query: format!("deck:{}", deck_name)
-                    }
-                ).await.unwrap();
-                let cards = self.communication_client.request(
-                    CardsInfoRequest{
-                        cards: card_ids
+            pub async fn get_cards_from_anki_deck(&self, deck_name: &str) -> Result<Vec<CardsInfoResponse>> {
+                let card_ids = self.invoke::<Vec<u64>>(
+                    "findCards",
+                    serde_json::json!({
+                        "query": deck_query(deck_name)
+                    })
+                ).await?;
+                let cards = self.invoke::<Vec<CardsInfoResponse>>(
+                    "cardsInfo",
+                    serde_json::json!({
+                        "cards": card_ids
+                    })
+                ).await?;
+
+                return Ok(cards);
+            }
+
+            /// The cards of the deck and the words they stand for: the front
+            /// of every card and the forms recorded in the notes.
+            pub async fn get_known_words_from_anki_deck(&self, deck_name: &str, template: &CardTemplate) -> Result<(Vec<CardsInfoResponse>, Vec<String>)> {
+                let cards = self.get_cards_from_anki_deck(deck_name).await?;
+                let mut words = extract_words_from_anki_cards(&cards);
+                words.extend(self.get_deck_notes(deck_name, template).await?.recorded_forms());
+                Ok((cards, words))
+            }
+
+            pub fn load_deck_names_non_blocking(app_state: &mut AppState) {
+                if app_state.deck_names.is_some() {
+                    return;
+                }
+                if app_state.channels.deck_names_rc.is_none() {
+                    let (tx, rx) = tokio::sync::mpsc::channel(1);
+                    app_state.channels.deck_names_rc = Some(rx);
+                    let anki_client = app_state.anki_client.clone();
+                    app_state.async_rt.spawn(async move {
+                        let res = anki_client.get_decks().await;
+                        let _ = tx.send(res).await;
+                    });
+                }
+
+                if let Some(rx) = &mut app_state.channels.deck_names_rc {
+                    match rx.try_recv() {
+                        Ok(Ok(decks)) => {
+                            app_state.deck_names = Some(decks);
+                            app_state.channels.deck_names_rc = None;
+                        }
+                        Ok(Err(err)) => {
+                            eprintln!("Could not load the decks: {:?}", err);
+                            app_state.channels.deck_names_rc = None;
+                            // Reconnecting brings us back here once Anki answers again
+                            app_state.anki_connection_status = AnkiConnectionStatus::CouldNotConnect;
+                        }
+                        Err(TryRecvError::Empty) => {}
+                        Err(TryRecvError::Disconnected) => {
+                            app_state.channels.deck_names_rc = None;
+                        }
                     }
-                ).await.unwrap();
+                }
+            }
 
-                return cards;
+            pub fn load_cards_from_anki_deck_non_blocking(app_state: &mut AppState) {
+                let Some(deck_name) = app_state.selected_deck_name.clone() else {
+                    return;
+                };
+                if app_state.raw_cards_from_anki.is_some() {
+                    return;
+                }
+                if app_state.channels.anki_cards_rc.is_none() {
+                    let (tx, rx) = tokio::sync::mpsc::channel(1);
+                    app_state.channels.anki_cards_rc = Some(rx);
+                    let anki_client = app_state.anki_client.clone();
+                    let card_template = app_state.effective_settings().card_template;
+                    app_state.async_rt.spawn(async move {
+                        let res = anki_client.get_known_words_from_anki_deck(&deck_name, &card_template).await;
+                        let _ = tx.send((deck_name, res)).await;
+                    });
+                }
+
+                if let Some(rx) = &mut app_state.channels.anki_cards_rc {
+                    match rx.try_recv() {
+                        Ok((loaded_deck_name, Ok((cards, words)))) => {
+                            app_state.channels.anki_cards_rc = None;
+                            // The deck was changed while its cards were loading
+                            if Some(&loaded_deck_name) != app_state.selected_deck_name.as_ref() {
+                                return;
+                            }
+                            app_state.prepared_words_from_anki = Some(words);
+                            app_state.raw_cards_from_anki = Some(cards);
+                        }
+                        Ok((loaded_deck_name, Err(err))) => {
+                            eprintln!("Could not load the cards from {}: {:?}", loaded_deck_name, err);
+                            app_state.channels.anki_cards_rc = None;
+                            app_state.anki_connection_status = AnkiConnectionStatus::CouldNotConnect;
+                        }
+                        Err(TryRecvError::Empty) => {}
+                        Err(TryRecvError::Disconnected) => {
+                            app_state.channels.anki_cards_rc = None;
+                        }
+                    }
+                }
             }
 
-        }
\ No newline at end of file
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn test_deck_query_quotes_the_deck_name() {
+                assert_eq!(deck_query("Default"), r#"deck:"Default""#);
+                assert_eq!(deck_query("English Vocab"), r#"deck:"English Vocab""#);
+                assert_eq!(deck_query(r#"Words "*_\ here"#), r#"deck:"Words \"\*\_\\ here""#);
+            }
+        }
@@ -1,7 +1,19 @@
 mod client;
 mod cards;
+mod notes;
 
 pub use client::AnkiClient;
 pub use client::AnkiConnectionStatus;
+pub use client::DEFAULT_ANKI_CONNECT_URL;
 
+pub use cards::{
+    CardTemplate,
+    MeaningWithExamples,
+    SenseDetails,
+    Word,
+};
 
+pub use notes::{
+    DeckNotes,
+    LookupHistory,
+};
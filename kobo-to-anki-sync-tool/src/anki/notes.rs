@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::cards::{normalise_word, strip_html};
+
+use super::{client::deck_query, AnkiClient, CardTemplate};
+
+/// Separates the forms in the forms field.
+const FORMS_SEPARATOR: &str = ", ";
+/// Separates the books in the books field, titles often contain commas.
+const BOOKS_SEPARATOR: &str = "; ";
+
+#[derive(Debug, Clone, Deserialize)]
+struct NoteInfo {
+    #[serde(rename = "noteId")]
+    note_id: u64,
+    fields: HashMap<String, NoteField>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NoteField {
+    value: String,
+}
+
+/// Every form a word was looked up in on the Kobo and the books it was
+/// looked up in, kept in the forms and books fields of its note.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LookupHistory {
+    pub forms: Vec<String>,
+    pub books: Vec<String>,
+}
+
+impl LookupHistory {
+    /// Returns `true` when `other` had a form or book this history didn't.
+    pub fn merge(&mut self, other: &LookupHistory) -> bool {
+        let mut changed = false;
+        for (values, other_values) in [(&mut self.forms, &other.forms), (&mut self.books, &other.books)] {
+            for value in other_values {
+                if !values.contains(value) {
+                    values.push(value.clone());
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    fn from_note(note: &NoteInfo, template: &CardTemplate) -> Self {
+        let field_values = |field_name: &Option<String>, separator: &str| {
+            field_name
+                .as_ref()
+                .and_then(|field_name| note.fields.get(field_name))
+                .map(|field| {
+                    strip_html(&field.value)
+                        .split(separator)
+                        .map(|value| value.trim().to_string())
+                        .filter(|value| !value.is_empty())
+                        .collect::<Vec<String>>()
+                })
+                .unwrap_or_default()
+        };
+        // Forms never contain a comma, titles may contain a semicolon
+        Self {
+            forms: field_values(&template.forms_field, FORMS_SEPARATOR.trim()),
+            books: field_values(&template.books_field, BOOKS_SEPARATOR),
+        }
+    }
+
+    /// The forms and books fields of the template that are in use.
+    pub fn note_fields(&self, template: &CardTemplate) -> serde_json::Map<String, serde_json::Value> {
+        let mut fields = serde_json::Map::new();
+        if let Some(forms_field) = &template.forms_field {
+            fields.insert(forms_field.clone(), self.forms.join(FORMS_SEPARATOR).into());
+        }
+        if let Some(books_field) = &template.books_field {
+            fields.insert(books_field.clone(), self.books.join(BOOKS_SEPARATOR).into());
+        }
+        fields
+    }
+}
+
+/// A note already in the deck, found by its front.
+#[derive(Debug, Clone)]
+pub struct DeckNote {
+    pub note_id: u64,
+    /// The front as it is compared with looked up words
+    pub word: String,
+    pub lookup_history: LookupHistory,
+}
+
+/// The notes of a deck with their lookup history, used to add new forms to
+/// an existing note instead of creating another one.
+#[derive(Debug, Clone, Default)]
+pub struct DeckNotes {
+    notes: Vec<DeckNote>,
+}
+
+impl DeckNotes {
+    /// The note whose front is one of `words`, or failing that the one that
+    /// recorded one of them as a form.
+    pub fn find_mut(&mut self, words: &[String]) -> Option<&mut DeckNote> {
+        let index = self.notes
+            .iter()
+            .position(|note| words.contains(&note.word))
+            .or_else(|| self.notes
+                .iter()
+                .position(|note| note.lookup_history.forms.iter().any(|form| words.contains(form))))?;
+        Some(&mut self.notes[index])
+    }
+
+    pub fn recorded_forms(&self) -> Vec<String> {
+        self.notes
+            .iter()
+            .flat_map(|note| note.lookup_history.forms.iter().cloned())
+            .collect()
+    }
+}
+
+impl AnkiClient {
+    /// Reads the notes of the deck, only when the template records forms or
+    /// books, otherwise there is nothing to update and no notes are returned.
+    pub async fn get_deck_notes(&self, deck_name: &str, template: &CardTemplate) -> Result<DeckNotes> {
+        if !template.records_lookups() {
+            return Ok(DeckNotes::default());
+        }
+        let note_ids = self.invoke::<Vec<u64>>(
+            "findNotes",
+            serde_json::json!({
+                "query": deck_query(deck_name)
+            })
+        ).await?;
+        let notes = self.invoke::<Vec<NoteInfo>>(
+            "notesInfo",
+            serde_json::json!({
+                "notes": note_ids
+            })
+        ).await?;
+        let notes = notes
+            .iter()
+            .filter_map(|note| {
+                let front = note.fields.get(&template.front_field)?;
+                Some(DeckNote {
+                    note_id: note.note_id,
+                    word: normalise_word(&strip_html(&front.value)),
+                    lookup_history: LookupHistory::from_note(note, template),
+                })
+            })
+            .collect();
+        Ok(DeckNotes { notes })
+    }
+
+    pub async fn update_lookup_history(&self, note: &DeckNote, template: &CardTemplate) -> Result<()> {
+        self.invoke_without_result("updateNoteFields", serde_json::json!({
+            "note": {
+                "id": note.note_id,
+                "fields": note.lookup_history.note_fields(template),
+            }
+        })).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(forms: &[&str], books: &[&str]) -> LookupHistory {
+        LookupHistory {
+            forms: forms.iter().map(|form| form.to_string()).collect(),
+            books: books.iter().map(|book| book.to_string()).collect(),
+        }
+    }
+
+    fn note(note_id: u64, fields: &[(&str, &str)]) -> NoteInfo {
+        NoteInfo {
+            note_id,
+            fields: fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), NoteField { value: value.to_string() }))
+                .collect(),
+        }
+    }
+
+    fn template() -> CardTemplate {
+        CardTemplate {
+            forms_field: Some("Forms".to_string()),
+            books_field: Some("Books".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_adds_only_new_forms_and_books() {
+        let mut lookup_history = history(&["ran"], &["Dracula"]);
+        assert!(lookup_history.merge(&history(&["ran", "running"], &["Dracula"])));
+        assert_eq!(lookup_history, history(&["ran", "running"], &["Dracula"]));
+
+        assert!(!lookup_history.merge(&history(&["running"], &[])));
+        assert!(lookup_history.merge(&history(&[], &["Emma"])));
+        assert_eq!(lookup_history, history(&["ran", "running"], &["Dracula", "Emma"]));
+    }
+
+    #[test]
+    fn test_from_note_reads_the_fields_of_the_template() {
+        let dracula = note(1, &[
+            ("Front", "run"),
+            ("Forms", "ran,<b>running</b>, "),
+            ("Books", "Dracula; Emma&nbsp;; Ready;Steady"),
+        ]);
+        assert_eq!(
+            LookupHistory::from_note(&dracula, &template()),
+            history(&["ran", "running"], &["Dracula", "Emma", "Ready;Steady"])
+        );
+
+        // Fields the template doesn't use, or the note doesn't have, are empty
+        assert_eq!(LookupHistory::from_note(&dracula, &CardTemplate::default()), LookupHistory::default());
+        let without_fields = note(2, &[("Front", "run")]);
+        assert_eq!(LookupHistory::from_note(&without_fields, &template()), LookupHistory::default());
+    }
+
+    #[test]
+    fn test_note_fields_round_trip() {
+        let lookup_history = history(&["ran", "running"], &["Ready;Steady", "Emma, or Pride"]);
+        let fields = lookup_history
+            .note_fields(&template())
+            .into_iter()
+            .map(|(name, value)| (name, value.as_str().unwrap().to_string()))
+            .collect::<Vec<(String, String)>>();
+        let fields = fields.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect::<Vec<(&str, &str)>>();
+        assert_eq!(LookupHistory::from_note(&note(1, &fields), &template()), lookup_history);
+    }
+
+    #[test]
+    fn test_find_mut_prefers_the_front_over_recorded_forms() {
+        let mut deck_notes = DeckNotes {
+            notes: vec![
+                DeckNote { note_id: 1, word: "run".to_string(), lookup_history: history(&["running"], &[]) },
+                DeckNote { note_id: 2, word: "running".to_string(), lookup_history: history(&[], &[]) },
+                DeckNote { note_id: 3, word: "go".to_string(), lookup_history: history(&["went"], &[]) },
+            ],
+        };
+        let find = |deck_notes: &mut DeckNotes, words: &[&str]| {
+            let words = words.iter().map(|word| word.to_string()).collect::<Vec<String>>();
+            deck_notes.find_mut(&words).map(|note| note.note_id)
+        };
+        assert_eq!(find(&mut deck_notes, &["running"]), Some(2));
+        assert_eq!(find(&mut deck_notes, &["went", "go"]), Some(3));
+        assert_eq!(find(&mut deck_notes, &["went"]), Some(3));
+        assert_eq!(find(&mut deck_notes, &["walk"]), None);
+        assert_eq!(deck_notes.recorded_forms(), vec!["running", "went"]);
+    }
+}
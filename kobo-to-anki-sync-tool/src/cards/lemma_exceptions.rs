@@ -0,0 +1,215 @@
+//! Inflected forms the suffix rules in [`super::lemmatisation`] would get
+//! wrong, mapped to their dictionary headword.
+
+pub const ENGLISH: &[(&str, &str)] = &[
+    // be, have, do
+    ("am", "be"), ("is", "be"), ("are", "be"), ("was", "be"), ("were", "be"), ("been", "be"), ("being", "be"),
+    ("has", "have"), ("had", "have"), ("having", "have"),
+    ("does", "do"), ("did", "do"), ("done", "do"),
+    // irregular verbs
+    ("arose", "arise"), ("arisen", "arise"),
+    ("awoke", "awake"), ("awoken", "awake"),
+    ("borne", "bear"),
+    ("beat", "beat"), ("beaten", "beat"),
+    ("became", "become"),
+    ("began", "begin"), ("begun", "begin"),
+    ("bent", "bend"),
+    ("bit", "bite"), ("bitten", "bite"),
+    ("bled", "bleed"),
+    ("blew", "blow"), ("blown", "blow"),
+    ("broke", "break"), ("broken", "break"),
+    ("bred", "breed"),
+    ("brought", "bring"),
+    ("built", "build"),
+    ("burnt", "burn"),
+    ("bought", "buy"),
+    ("caught", "catch"),
+    ("chose", "choose"), ("chosen", "choose"),
+    ("clung", "cling"),
+    ("came", "come"),
+    ("crept", "creep"),
+    ("dealt", "deal"),
+    ("dug", "dig"),
+    ("drew", "draw"), ("drawn", "draw"),
+    ("dreamt", "dream"),
+    ("drank", "drink"), ("drunk", "drink"),
+    ("drove", "drive"), ("driven", "drive"),
+    ("ate", "eat"), ("eaten", "eat"),
+    ("fell", "fall"), ("fallen", "fall"),
+    ("fed", "feed"),
+    ("felt", "feel"),
+    ("fought", "fight"),
+    ("found", "find"),
+    ("fled", "flee"),
+    ("flung", "fling"),
+    ("flew", "fly"), ("flown", "fly"),
+    ("forbade", "forbid"), ("forbidden", "forbid"),
+    ("forgot", "forget"), ("forgotten", "forget"),
+    ("forgave", "forgive"), ("forgiven", "forgive"),
+    ("froze", "freeze"), ("frozen", "freeze"),
+    ("got", "get"), ("gotten", "get"),
+    ("gave", "give"), ("given", "give"),
+    ("went", "go"), ("gone", "go"), ("goes", "go"),
+    ("grew", "grow"), ("grown", "grow"),
+    ("hung", "hang"),
+    ("heard", "hear"),
+    ("hid", "hide"), ("hidden", "hide"),
+    ("held", "hold"),
+    ("kept", "keep"),
+    ("knelt", "kneel"),
+    ("knew", "know"), ("known", "know"),
+    ("laid", "lay"),
+    ("led", "lead"),
+    ("leapt", "leap"),
+    ("lent", "lend"),
+    ("lain", "lie"), ("lying", "lie"),
+    ("lit", "light"),
+    ("lost", "lose"),
+    ("made", "make"),
+    ("meant", "mean"),
+    ("met", "meet"),
+    ("paid", "pay"),
+    ("rode", "ride"), ("ridden", "ride"),
+    ("rang", "ring"), ("rung", "ring"),
+    ("risen", "rise"),
+    ("ran", "run"),
+    ("said", "say"),
+    ("saw", "see"), ("seen", "see"),
+    ("sought", "seek"),
+    ("sold", "sell"),
+    ("sent", "send"),
+    ("shook", "shake"), ("shaken", "shake"),
+    ("shone", "shine"),
+    ("shot", "shoot"),
+    ("shrank", "shrink"), ("shrunk", "shrink"),
+    ("sang", "sing"), ("sung", "sing"),
+    ("sank", "sink"), ("sunk", "sink"),
+    ("sat", "sit"),
+    ("slept", "sleep"),
+    ("slid", "slide"),
+    ("spoke", "speak"), ("spoken", "speak"),
+    ("sped", "speed"),
+    ("spent", "spend"),
+    ("spun", "spin"),
+    ("sprang", "spring"), ("sprung", "spring"),
+    ("stood", "stand"),
+    ("stole", "steal"), ("stolen", "steal"),
+    ("stuck", "stick"),
+    ("stung", "sting"),
+    ("stank", "stink"),
+    ("strode", "stride"),
+    ("struck", "strike"), ("stricken", "strike"),
+    ("strove", "strive"), ("striven", "strive"),
+    ("swore", "swear"), ("sworn", "swear"),
+    ("swept", "sweep"),
+    ("swam", "swim"), ("swum", "swim"),
+    ("swung", "swing"),
+    ("took", "take"), ("taken", "take"),
+    ("taught", "teach"),
+    ("tore", "tear"), ("torn", "tear"),
+    ("told", "tell"),
+    ("thought", "think"),
+    ("threw", "throw"), ("thrown", "throw"),
+    ("trod", "tread"), ("trodden", "tread"),
+    ("understood", "understand"),
+    ("woke", "wake"), ("woken", "wake"),
+    ("wore", "wear"), ("worn", "wear"),
+    ("wove", "weave"), ("woven", "weave"),
+    ("wept", "weep"),
+    ("won", "win"),
+    ("wrung", "wring"),
+    ("wrote", "write"), ("written", "write"),
+    // irregular plurals
+    ("children", "child"), ("men", "man"), ("women", "woman"), ("people", "person"),
+    ("feet", "foot"), ("teeth", "tooth"), ("geese", "goose"), ("mice", "mouse"), ("lice", "louse"),
+    ("oxen", "ox"),
+    ("wives", "wife"), ("knives", "knife"), ("lives", "life"), ("leaves", "leaf"), ("loaves", "loaf"),
+    ("halves", "half"), ("calves", "calf"), ("selves", "self"), ("shelves", "shelf"),
+    ("thieves", "thief"), ("wolves", "wolf"), ("scarves", "scarf"), ("hooves", "hoof"),
+    ("criteria", "criterion"), ("phenomena", "phenomenon"), ("analyses", "analysis"),
+    ("crises", "crisis"), ("theses", "thesis"), ("hypotheses", "hypothesis"),
+    ("cacti", "cactus"), ("fungi", "fungus"), ("nuclei", "nucleus"), ("stimuli", "stimulus"),
+    ("shoes", "shoe"), ("toes", "toe"), ("canoes", "canoe"),
+    ("movies", "movie"), ("cookies", "cookie"), ("ties", "tie"), ("lies", "lie"), ("dies", "die"), ("pies", "pie"),
+    // irregular comparatives
+    ("better", "good"), ("best", "good"), ("worse", "bad"), ("worst", "bad"),
+    ("more", "much"), ("most", "much"), ("less", "little"), ("least", "little"),
+    ("further", "far"), ("furthest", "far"), ("farther", "far"), ("farthest", "far"),
+    // words the rules would cut
+    ("this", "this"), ("thus", "thus"), ("yes", "yes"), ("gas", "gas"), ("bias", "bias"),
+    ("news", "news"), ("series", "series"), ("species", "species"), ("means", "means"),
+    ("always", "always"),
+    ("whereas", "whereas"),
+    ("sometimes", "sometimes"), ("perhaps", "perhaps"), ("besides", "besides"), ("towards", "towards"),
+    ("during", "during"), ("nothing", "nothing"), ("something", "something"),
+    ("anything", "anything"), ("everything", "everything"), ("morning", "morning"),
+    ("evening", "evening"), ("ceiling", "ceiling"), ("building", "building"),
+    ("feeling", "feeling"), ("meeting", "meeting"), ("wedding", "wedding"), ("ring", "ring"),
+    ("need", "need"), ("seed", "seed"), ("feed", "feed"), ("bed", "bed"), ("red", "red"),
+    ("hundred", "hundred"), ("sacred", "sacred"), ("naked", "naked"), ("wicked", "wicked"),
+    ("lens", "lens"), ("sibling", "sibling"),
+    // plurals of words ending in a vowel the rules leave alone
+    ("ideas", "idea"), ("areas", "area"), ("eras", "era"), ("cameras", "camera"), ("dramas", "drama"),
+    ("photos", "photo"), ("videos", "video"), ("radios", "radio"), ("pianos", "piano"), ("zeros", "zero"),
+];
+
+pub const GERMAN: &[(&str, &str)] = &[
+    // sein, haben, werden
+    ("bin", "sein"), ("bist", "sein"), ("ist", "sein"), ("sind", "sein"), ("seid", "sein"),
+    ("war", "sein"), ("warst", "sein"), ("waren", "sein"), ("wart", "sein"), ("gewesen", "sein"),
+    ("wäre", "sein"), ("wären", "sein"),
+    ("habe", "haben"), ("hast", "haben"), ("hat", "haben"), ("habt", "haben"),
+    ("hatte", "haben"), ("hattest", "haben"), ("hatten", "haben"), ("gehabt", "haben"), ("hätte", "haben"),
+    ("wird", "werden"), ("wirst", "werden"), ("wurde", "werden"), ("wurden", "werden"),
+    ("geworden", "werden"), ("würde", "werden"), ("würden", "werden"),
+    // modal verbs
+    ("kann", "können"), ("kannst", "können"), ("konnte", "können"), ("konnten", "können"), ("könnte", "können"),
+    ("muss", "müssen"), ("musst", "müssen"), ("musste", "müssen"), ("mussten", "müssen"), ("müsste", "müssen"),
+    ("will", "wollen"), ("willst", "wollen"), ("wollte", "wollen"), ("wollten", "wollen"),
+    ("soll", "sollen"), ("sollst", "sollen"), ("sollte", "sollen"), ("sollten", "sollen"),
+    ("darf", "dürfen"), ("darfst", "dürfen"), ("durfte", "dürfen"), ("durften", "dürfen"), ("dürfte", "dürfen"),
+    ("mag", "mögen"), ("magst", "mögen"), ("mochte", "mögen"), ("möchte", "mögen"), ("möchten", "mögen"),
+    ("weiß", "wissen"), ("weißt", "wissen"), ("wusste", "wissen"), ("wussten", "wissen"), ("gewusst", "wissen"),
+    // strong and mixed verbs
+    ("ging", "gehen"), ("gingen", "gehen"), ("gegangen", "gehen"),
+    ("kam", "kommen"), ("kamen", "kommen"), ("gekommen", "kommen"),
+    ("sah", "sehen"), ("sahen", "sehen"), ("sieht", "sehen"), ("siehst", "sehen"), ("gesehen", "sehen"),
+    ("gab", "geben"), ("gaben", "geben"), ("gibt", "geben"), ("gibst", "geben"), ("gegeben", "geben"),
+    ("nahm", "nehmen"), ("nahmen", "nehmen"), ("nimmt", "nehmen"), ("nimmst", "nehmen"), ("genommen", "nehmen"),
+    ("fand", "finden"), ("fanden", "finden"), ("gefunden", "finden"),
+    ("stand", "stehen"), ("standen", "stehen"), ("gestanden", "stehen"),
+    ("lag", "liegen"), ("lagen", "liegen"), ("gelegen", "liegen"),
+    ("saß", "sitzen"), ("saßen", "sitzen"), ("gesessen", "sitzen"),
+    ("sprach", "sprechen"), ("sprachen", "sprechen"), ("spricht", "sprechen"), ("gesprochen", "sprechen"),
+    ("las", "lesen"), ("lasen", "lesen"), ("liest", "lesen"), ("gelesen", "lesen"),
+    ("schrieb", "schreiben"), ("schrieben", "schreiben"), ("geschrieben", "schreiben"),
+    ("blieb", "bleiben"), ("blieben", "bleiben"), ("geblieben", "bleiben"),
+    ("hielt", "halten"), ("hielten", "halten"), ("hält", "halten"), ("gehalten", "halten"),
+    ("ließ", "lassen"), ("ließen", "lassen"), ("lässt", "lassen"), ("gelassen", "lassen"),
+    ("lief", "laufen"), ("liefen", "laufen"), ("läuft", "laufen"), ("gelaufen", "laufen"),
+    ("fuhr", "fahren"), ("fuhren", "fahren"), ("fährt", "fahren"), ("gefahren", "fahren"),
+    ("trug", "tragen"), ("trugen", "tragen"), ("trägt", "tragen"), ("getragen", "tragen"),
+    ("fiel", "fallen"), ("fielen", "fallen"), ("fällt", "fallen"), ("gefallen", "fallen"),
+    ("zog", "ziehen"), ("zogen", "ziehen"), ("gezogen", "ziehen"),
+    ("trank", "trinken"), ("tranken", "trinken"), ("getrunken", "trinken"),
+    ("aß", "essen"), ("aßen", "essen"), ("isst", "essen"), ("gegessen", "essen"),
+    ("schlief", "schlafen"), ("schliefen", "schlafen"), ("schläft", "schlafen"), ("geschlafen", "schlafen"),
+    ("dachte", "denken"), ("dachten", "denken"), ("gedacht", "denken"),
+    ("brachte", "bringen"), ("brachten", "bringen"), ("gebracht", "bringen"),
+    ("kannte", "kennen"), ("kannten", "kennen"), ("gekannt", "kennen"),
+    ("rief", "rufen"), ("riefen", "rufen"), ("gerufen", "rufen"),
+    ("tat", "tun"), ("taten", "tun"), ("getan", "tun"),
+    ("half", "helfen"), ("halfen", "helfen"), ("hilft", "helfen"), ("geholfen", "helfen"),
+    ("starb", "sterben"), ("starben", "sterben"), ("stirbt", "sterben"), ("gestorben", "sterben"),
+    ("vergaß", "vergessen"), ("vergaßen", "vergessen"), ("vergisst", "vergessen"),
+    ("verstand", "verstehen"), ("verstanden", "verstehen"),
+    ("begann", "beginnen"), ("begannen", "beginnen"), ("begonnen", "beginnen"),
+    ("gewann", "gewinnen"), ("gewannen", "gewinnen"), ("gewonnen", "gewinnen"),
+    ("schwieg", "schweigen"), ("schwiegen", "schweigen"), ("geschwiegen", "schweigen"),
+    ("wusch", "waschen"), ("wäscht", "waschen"), ("gewaschen", "waschen"),
+    // irregular comparatives
+    ("besser", "gut"), ("beste", "gut"), ("besten", "gut"),
+    ("mehr", "viel"), ("meiste", "viel"), ("meisten", "viel"),
+    ("lieber", "gern"), ("liebsten", "gern"),
+    ("höher", "hoch"), ("höchste", "hoch"), ("näher", "nah"), ("nächste", "nah"),
+];
@@ -0,0 +1,278 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::lemma_exceptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::German => "German",
+        }
+    }
+}
+
+/// Turns inflected forms ("running", "studies", "Zeitungen") into the
+/// headword a dictionary lists them under.
+///
+/// Known irregular forms are looked up in the exception table of the
+/// language first, everything else goes through a few conservative suffix
+/// rules. Phrases are left alone.
+pub struct Lemmatiser {
+    language: Language,
+    exceptions: HashMap<&'static str, &'static str>,
+    /// The headwords of the exceptions, which are never changed by the
+    /// rules, e.g. "beginnen" isn't a plural of "begin"
+    headwords: HashSet<&'static str>,
+}
+
+impl Lemmatiser {
+    pub fn new(language: Language) -> Self {
+        let exceptions = match language {
+            Language::English => lemma_exceptions::ENGLISH,
+            Language::German => lemma_exceptions::GERMAN,
+        };
+        Self {
+            language,
+            exceptions: exceptions.iter().copied().collect(),
+            headwords: exceptions.iter().map(|(_, headword)| *headword).collect(),
+        }
+    }
+
+    /// Expects a word already passed through [`super::normalise_word`].
+    pub fn lemmatise(&self, word: &str) -> String {
+        if word.contains(' ') {
+            return word.to_string();
+        }
+        if let Some(headword) = self.exceptions.get(word) {
+            return headword.to_string();
+        }
+        if self.headwords.contains(word) {
+            return word.to_string();
+        }
+        let headword = match self.language {
+            Language::English => lemmatise_english(word),
+            Language::German => lemmatise_german(word),
+        };
+        // A rule may have produced an irregular form, e.g. "lying" -> "ly"
+        match self.exceptions.get(headword.as_str()) {
+            Some(exception) => exception.to_string(),
+            None => headword,
+        }
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+fn has_vowel(word: &str) -> bool {
+    word.chars().any(|c| is_vowel(c) || c == 'y')
+}
+
+fn lemmatise_english(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("'s").or(word.strip_suffix("s'")) {
+        if !stem.is_empty() {
+            return stem.to_string();
+        }
+    }
+    if word.chars().count() <= 3 || word.contains('-') || word.contains('\'') {
+        return word.to_string();
+    }
+
+    if let Some(stem) = word.strip_suffix("ies") {
+        if stem.len() >= 2 {
+            return format!("{}y", stem);
+        }
+    }
+    if let Some(stem) = word.strip_suffix("es") {
+        if ["ss", "sh", "ch", "x", "zz"].iter().any(|ending| stem.ends_with(ending)) {
+            return stem.to_string();
+        }
+    }
+    if let Some(stem) = word.strip_suffix('s') {
+        // After a, i, o or u the "s" is usually part of the word ("canvas",
+        // "bonus"), a final "e" is the silent one of the stem ("hopes")
+        if !["s", "a", "i", "o", "u"].iter().any(|ending| stem.ends_with(ending)) {
+            return stem.to_string();
+        }
+        return word.to_string();
+    }
+
+    if let Some(stem) = word.strip_suffix("ied") {
+        // "died", "tied" and "lied" come from verbs ending in "ie"
+        if stem.len() >= 2 {
+            return format!("{}y", stem);
+        }
+        return format!("{}ie", stem);
+    }
+    for suffix in ["ed", "ing"] {
+        // "proceed" and "indeed" end in "eed" without being past tenses
+        if suffix == "ed" && word.ends_with("eed") {
+            continue;
+        }
+        if let Some(stem) = word.strip_suffix(suffix) {
+            if stem.len() >= 2 && has_vowel(stem) {
+                return restore_english_stem(stem);
+            }
+        }
+    }
+    word.to_string()
+}
+
+/// Undoes the spelling changes made when adding "-ed" or "-ing": doubled
+/// final consonants ("stopped") and the dropped silent "e" ("hoped").
+fn restore_english_stem(stem: &str) -> String {
+    let chars = stem.chars().collect::<Vec<char>>();
+    let last = chars[chars.len() - 1];
+    let before_last = chars[chars.len() - 2];
+    // Short stems such as "add" and "egg" end in a double letter themselves
+    let is_short_double = matches!(last, 'd' | 'g') && chars.len() - 1 < 3;
+    if last == before_last && !is_vowel(last) && !matches!(last, 'l' | 's' | 'z' | 'f') && !is_short_double {
+        return chars[..chars.len() - 1].iter().collect();
+    }
+    if ["at", "bl", "iz", "iv", "ur", "dg", "uc", "anc", "enc", "rc"].iter().any(|ending| stem.ends_with(ending)) {
+        return format!("{}e", stem);
+    }
+    // A single short vowel between consonants in a one-syllable stem, as in
+    // "hop(e)", "lik(e)" or "us(e)", had a silent "e"
+    let vowel_groups = chars
+        .windows(2)
+        .filter(|pair| !is_vowel(pair[0]) && is_vowel(pair[1]))
+        .count() + usize::from(is_vowel(chars[0]));
+    let ends_consonant_vowel_consonant = !is_vowel(last)
+        && !matches!(last, 'w' | 'x' | 'y')
+        && is_vowel(before_last)
+        && (chars.len() < 3 || !is_vowel(chars[chars.len() - 3]));
+    if vowel_groups == 1 && ends_consonant_vowel_consonant {
+        return format!("{}e", stem);
+    }
+    stem.to_string()
+}
+
+/// German inflection mostly changes the word in ways suffix stripping can't
+/// undo without a dictionary, so only the plural endings of derived nouns,
+/// which always map back the same way, are handled by rules.
+///
+/// The minimum stem length keeps verbs such as "beginnen" or "spinnen" whole,
+/// and participles such as "gesungen" or "gezwungen" aren't plurals of "-ung".
+fn lemmatise_german(word: &str) -> String {
+    const PLURAL_SUFFIXES: [(&str, &str, usize); 7] = [
+        ("ungen", "ung", 2),
+        ("heiten", "heit", 2),
+        ("keiten", "keit", 2),
+        ("schaften", "schaft", 2),
+        ("innen", "in", 4),
+        ("nisse", "nis", 2),
+        ("tionen", "tion", 2),
+    ];
+    for (suffix, replacement, min_stem_len) in PLURAL_SUFFIXES {
+        if suffix == "ungen" && word.starts_with("ge") {
+            continue;
+        }
+        if let Some(stem) = word.strip_suffix(suffix) {
+            if stem.chars().count() >= min_stem_len {
+                return format!("{}{}", stem, replacement);
+            }
+        }
+    }
+    word.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_forms() {
+        let lemmatiser = Lemmatiser::new(Language::English);
+        for (word, headword) in [
+            ("running", "run"),
+            ("ran", "run"),
+            ("runs", "run"),
+            ("studies", "study"),
+            ("studied", "study"),
+            ("stopped", "stop"),
+            ("hoped", "hope"),
+            ("hoping", "hope"),
+            ("liked", "like"),
+            ("walked", "walk"),
+            ("helped", "help"),
+            ("opened", "open"),
+            ("visited", "visit"),
+            ("played", "play"),
+            ("boxes", "box"),
+            ("churches", "church"),
+            ("cats", "cat"),
+            ("glass", "glass"),
+            ("status", "status"),
+            ("children", "child"),
+            ("lying", "lie"),
+            ("dog's", "dog"),
+            ("well-known", "well-known"),
+            ("don't", "don't"),
+            ("look up", "look up"),
+            ("thing", "thing"),
+            ("king", "king"),
+            ("proceed", "proceed"),
+            ("succeed", "succeed"),
+            ("exceed", "exceed"),
+            ("indeed", "indeed"),
+            ("bleed", "bleed"),
+            ("canvas", "canvas"),
+            ("lens", "lens"),
+            ("sibling", "sibling"),
+            ("bonus", "bonus"),
+            ("hopes", "hope"),
+            ("ideas", "idea"),
+            ("photos", "photo"),
+            ("proceeded", "proceed"),
+            ("proceeding", "proceed"),
+            ("adding", "add"),
+            ("added", "add"),
+            ("egged", "egg"),
+            ("nodded", "nod"),
+            ("begged", "beg"),
+            ("died", "die"),
+            ("tied", "tie"),
+            ("lied", "lie"),
+        ] {
+            assert_eq!(lemmatiser.lemmatise(word), headword, "{}", word);
+        }
+    }
+
+    #[test]
+    fn german_forms() {
+        let lemmatiser = Lemmatiser::new(Language::German);
+        for (word, headword) in [
+            ("zeitungen", "zeitung"),
+            ("möglichkeiten", "möglichkeit"),
+            ("lehrerinnen", "lehrerin"),
+            ("ging", "gehen"),
+            ("leben", "leben"),
+            ("übungen", "übung"),
+            ("freundinnen", "freundin"),
+            ("ärztinnen", "ärztin"),
+            ("beginnen", "beginnen"),
+            ("gewinnen", "gewinnen"),
+            ("begann", "beginnen"),
+            ("gewann", "gewinnen"),
+            ("spinnen", "spinnen"),
+            ("gesungen", "gesungen"),
+            ("gelungen", "gelungen"),
+            ("gezwungen", "gezwungen"),
+        ] {
+            assert_eq!(lemmatiser.lemmatise(word), headword, "{}", word);
+        }
+    }
+}
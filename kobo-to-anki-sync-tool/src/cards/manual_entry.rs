@@ -0,0 +1,99 @@
+use crate::anki::{MeaningWithExamples, Word};
+
+use super::StagedWord;
+
+/// What the user typed for a word the providers couldn't define.
+#[derive(Debug, Clone, Default)]
+pub struct DefinitionDraft {
+    pub meaning: String,
+    /// One example per line
+    pub examples: String,
+    /// Another spelling or the lemma to ask the providers for instead
+    pub alternate_spelling: String,
+    pub lookup_in_progress: bool,
+    pub lookup_error: Option<String>,
+}
+
+impl DefinitionDraft {
+    /// The word as it would come from a provider, `None` until there is a
+    /// meaning.
+    pub fn to_word(&self, headword: &str) -> Option<Word> {
+        let meaning = self.meaning.trim();
+        if meaning.is_empty() {
+            return None;
+        }
+        let examples = self.examples
+            .lines()
+            .map(|example| example.trim().to_string())
+            .filter(|example| !example.is_empty())
+            .collect();
+        Some(Word {
+            word: headword.to_string(),
+            meanings_with_examples: vec![MeaningWithExamples {
+                meaning: meaning.to_string(),
+                examples,
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+    }
+}
+
+impl StagedWord {
+    /// Gives a word without a definition one that was typed in or found
+    /// under another spelling, it then goes through the review like the rest.
+    pub fn set_definition(&mut self, word: Word, provider_url: Option<String>) {
+        self.selected_meanings = vec![true; word.meanings_with_examples.len()];
+        self.word = Some(word);
+        self.include = true;
+        self.provider_url = provider_url;
+        self.missing_reason = None;
+        self.draft = DefinitionDraft::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_word_needs_a_meaning() {
+        assert!(DefinitionDraft::default().to_word("happy").is_none());
+        let draft = DefinitionDraft {
+            meaning: "  \n ".to_string(),
+            examples: "She looks happy.".to_string(),
+            ..Default::default()
+        };
+        assert!(draft.to_word("happy").is_none());
+    }
+
+    #[test]
+    fn test_to_word_has_one_example_per_line() {
+        let draft = DefinitionDraft {
+            meaning: " feeling pleasure ".to_string(),
+            examples: "She looks happy.\n\n  I'm happy for you.  \r\n".to_string(),
+            ..Default::default()
+        };
+        let word = draft.to_word("happy").unwrap();
+        assert_eq!(word.word, "happy");
+        assert_eq!(word.meanings_with_examples.len(), 1);
+        assert_eq!(word.meanings_with_examples[0].meaning, "feeling pleasure");
+        assert_eq!(word.meanings_with_examples[0].examples, vec!["She looks happy.", "I'm happy for you."]);
+
+        let draft = DefinitionDraft {
+            meaning: "feeling pleasure".to_string(),
+            ..Default::default()
+        };
+        assert!(draft.to_word("happy").unwrap().meanings_with_examples[0].examples.is_empty());
+    }
+
+    #[test]
+    fn test_to_word_keeps_the_headword_over_the_alternate_spelling() {
+        let draft = DefinitionDraft {
+            meaning: "feeling pleasure".to_string(),
+            alternate_spelling: "happie".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(draft.to_word("happy").unwrap().word, "happy");
+    }
+}
@@ -1,6 +1,30 @@
+mod lemma_exceptions;
+mod lemmatisation;
+mod manual_entry;
+mod normalisation;
 mod preparation;
+mod staging;
+
+pub use lemmatisation::{
+    Language,
+    Lemmatiser,
+};
+
+pub use manual_entry::DefinitionDraft;
+
+pub use normalisation::normalise_word;
 
 pub use preparation::{
     extract_words_from_anki_cards,
+    find_new_words,
+    prepare_lookups,
     prepare_words,
-};
\ No newline at end of file
+    strip_html,
+    KoboWord,
+};
+
+pub use staging::{
+    approved_words,
+    ApprovedWord,
+    StagedWord,
+};
@@ -0,0 +1,59 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Brings a looked up word into the form words are compared and stored in.
+///
+/// The word is NFC normalised and lowercased, typographic apostrophes and
+/// hyphens become their ASCII versions and are kept inside words ("don't",
+/// "well-known"), anything else that isn't a letter is dropped.
+pub fn normalise_word(word: &str) -> String {
+    let mut normalised = String::with_capacity(word.len());
+    for c in word.nfc().flat_map(char::to_lowercase) {
+        match c {
+            '\'' | '\u{2018}' | '\u{2019}' | '\u{02BC}' | '`' | '\u{00B4}' => normalised.push('\''),
+            '-' | '\u{2010}' | '\u{2011}' => normalised.push('-'),
+            // Soft hyphens only mark where a word may be broken
+            '\u{00AD}' => {}
+            c if c.is_alphabetic() => normalised.push(c),
+            c if c.is_whitespace() => normalised.push(' '),
+            _ => {}
+        }
+    }
+    normalised
+        .split(' ')
+        .map(|part| part.trim_matches(|c| c == '\'' || c == '-'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apostrophes_and_hyphens_stay_inside_words() {
+        assert_eq!(normalise_word("Don't"), "don't");
+        assert_eq!(normalise_word("don\u{2019}t"), "don't");
+        assert_eq!(normalise_word("don\u{2018}t"), "don't");
+        assert_eq!(normalise_word("Well-known"), "well-known");
+        assert_eq!(normalise_word("well\u{2010}known"), "well-known");
+        assert_eq!(normalise_word("\u{2019}tis"), "tis");
+        assert_eq!(normalise_word("dogs\u{2019}"), "dogs");
+        assert_eq!(normalise_word("-well-"), "well");
+    }
+
+    #[test]
+    fn soft_hyphens_and_punctuation_are_dropped() {
+        assert_eq!(normalise_word("ex\u{00AD}traordinary"), "extraordinary");
+        assert_eq!(normalise_word("\u{201C}Hello,\u{201D}"), "hello");
+        assert_eq!(normalise_word("  look   up! "), "look up");
+    }
+
+    #[test]
+    fn decomposed_input_is_composed() {
+        // "café" and "Mädchen" with combining accents
+        assert_eq!(normalise_word("cafe\u{0301}"), "caf\u{00E9}");
+        assert_eq!(normalise_word("Ma\u{0308}dchen"), "m\u{00E4}dchen");
+        assert_eq!(normalise_word("caf\u{00E9}"), normalise_word("cafe\u{0301}"));
+    }
+}
@@ -1,33 +1,160 @@
 use anki_bridge::prelude::CardsInfoResponse;
 
+use crate::anki::LookupHistory;
+use crate::kobo::KoboLookup;
+use crate::storage::IgnoreList;
 
+use super::{normalise_word, Lemmatiser};
 
 pub fn prepare_words(words: Vec<String>) -> Vec<String> {
-    let mut prepared_words = Vec::new();
-    
-    assert!(!','.is_alphabetic());
-    assert!('a'.is_alphabetic());
+    words
+        .iter()
+        .map(|word| normalise_word(word))
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+pub fn prepare_lookups(lookups: Vec<KoboLookup>) -> Vec<KoboLookup> {
+    lookups
+        .into_iter()
+        .map(|lookup| KoboLookup {
+            word: normalise_word(&lookup.word),
+            book: lookup.book,
+        })
+        .filter(|lookup| !lookup.word.is_empty())
+        .collect()
+}
+
+/// A headword together with the forms it was looked up in on the Kobo and
+/// the books they were looked up in. Without lemmatisation the only lookup
+/// is usually the headword itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KoboWord {
+    pub headword: String,
+    pub lookups: Vec<String>,
+    pub books: Vec<String>,
+}
 
-    for word in words {
-        let mut prepared_word = word.to_lowercase();
-        prepared_word.retain(|c| (c.is_alphabetic() || c == ' '));
-        prepared_words.push(prepared_word.trim().to_string());
+impl KoboWord {
+    pub fn new(headword: String) -> Self {
+        Self {
+            lookups: vec![headword.clone()],
+            headword,
+            books: vec![],
+        }
+    }
+
+    /// Adds the lookups and books of a word that turned out to share this
+    /// word's headword.
+    pub fn merge(&mut self, other: &KoboWord) {
+        for lookup in &other.lookups {
+            if !self.lookups.contains(lookup) {
+                self.lookups.push(lookup.clone());
+            }
+        }
+        for book in &other.books {
+            if !self.books.contains(book) {
+                self.books.push(book.clone());
+            }
+        }
+    }
+
+    pub fn lookup_history(&self) -> LookupHistory {
+        LookupHistory {
+            forms: self.lookups.clone(),
+            books: self.books.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WordDiff {
+    /// Words looked up on the Kobo that are neither in the deck nor ignored
+    pub new_words: Vec<KoboWord>,
+    /// Words that already have a note, their lookups may still be new forms
+    pub known_words: Vec<KoboWord>,
+    pub ignored_count: usize,
+}
+
+/// Groups the Kobo lookups by headword and sorts out the ones already in the
+/// deck or on the ignore list, checking both the lookup and its headword.
+pub fn find_new_words(
+    kobo_lookups: &[KoboLookup],
+    anki_words: &[String],
+    ignore_list: &IgnoreList,
+    lemmatiser: Option<&Lemmatiser>,
+) -> WordDiff {
+    let mut diff = WordDiff::default();
+    for lookup in kobo_lookups {
+        let headword = match lemmatiser {
+            Some(lemmatiser) => lemmatiser.lemmatise(&lookup.word),
+            None => lookup.word.clone(),
+        };
+        if anki_words.contains(&lookup.word) || anki_words.contains(&headword) {
+            group_lookup(&mut diff.known_words, headword, lookup);
+            continue;
+        }
+        if ignore_list.contains(&lookup.word) || ignore_list.contains(&headword) {
+            diff.ignored_count += 1;
+            continue;
+        }
+        group_lookup(&mut diff.new_words, headword, lookup);
+    }
+    diff
+}
+
+fn group_lookup(words: &mut Vec<KoboWord>, headword: String, lookup: &KoboLookup) {
+    let kobo_word = KoboWord {
+        headword,
+        lookups: vec![lookup.word.clone()],
+        books: lookup.book.iter().cloned().collect(),
+    };
+    match words.iter_mut().find(|word| word.headword == kobo_word.headword) {
+        Some(word) => word.merge(&kobo_word),
+        None => words.push(kobo_word),
     }
-    prepared_words
 }
 
 pub fn extract_words_from_anki_cards(cards: &Vec<CardsInfoResponse>) -> Vec<String> {
     let cards = cards.clone();
     let mut words = Vec::new();
     for card in cards {
-        let mut word = card.question;
-        word = word.split("\">").last().unwrap().to_string();
-        word = word.split("</span></center></center>").nth(0).unwrap().to_string();
-        word = word.trim().to_string();
-        words.push(word);
+        // The front can come from any card template, so instead of relying on
+        // the default markup just keep the text of the rendered question
+        let word = strip_html(&card.question);
+        words.push(word.trim().to_string());
     }
 
     words = prepare_words(words);
 
     words
+}
+
+pub fn strip_html(html: &str) -> String {
+    let mut html = html.to_string();
+    // Anki puts the note type CSS in front of the rendered question
+    while let Some(start) = html.find("<style") {
+        let end = html[start..]
+            .find("</style>")
+            .map(|end| start + end + "</style>".len())
+            .unwrap_or(html.len());
+        html.replace_range(start..end, "");
+    }
+
+    let mut text = String::new();
+    let mut inside_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => inside_tag = true,
+            '>' => inside_tag = false,
+            c if !inside_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+use crate::anki::Word;
+use crate::dictionary::Lookup;
+
+use super::{normalise_word, DefinitionDraft, KoboWord};
+
+/// A candidate word waiting for the user's approval before it is sent to Anki.
+#[derive(Debug, Clone)]
+pub struct StagedWord {
+    pub kobo_word: KoboWord,
+    pub include: bool,
+    pub word: Option<Word>,
+    pub selected_meanings: Vec<bool>,
+    /// The provider the definition came from
+    pub provider_url: Option<String>,
+    /// Why there is no definition
+    pub missing_reason: Option<String>,
+    pub draft: DefinitionDraft,
+}
+
+impl StagedWord {
+    pub fn new(kobo_word: KoboWord, lookup: Lookup) -> Self {
+        let (word, provider_url, missing_reason) = match lookup {
+            Lookup::Found { word, provider_url } => (Some(word), Some(provider_url), None),
+            Lookup::NotFound => (None, None, Some("Not found by any words provider".to_string())),
+            Lookup::Failed(error) => (None, None, Some(format!("Lookup failed: {}", error))),
+        };
+        let selected_meanings = match &word {
+            Some(word) => vec![true; word.meanings_with_examples.len()],
+            None => vec![],
+        };
+        Self {
+            kobo_word,
+            // Words without a definition can't become a card, so they start unchecked
+            include: word.is_some(),
+            word,
+            selected_meanings,
+            provider_url,
+            missing_reason,
+            draft: DefinitionDraft::default(),
+        }
+    }
+
+    /// The headword the provider returned, which can group forms the
+    /// lemmatiser didn't, e.g. "stranded" and "strands" both under "strand".
+    pub fn provider_headword(&self) -> Option<String> {
+        let word = self.word.as_ref()?;
+        Some(normalise_word(&word.word)).filter(|headword| !headword.is_empty())
+    }
+
+    pub fn has_definition(&self) -> bool {
+        self.word.is_some()
+    }
+
+    /// Returns the word with only the meanings the user decided to keep,
+    /// or `None` if the word is skipped or nothing is left to put on a card.
+    pub fn approved_word(&self) -> Option<Word> {
+        if !self.include {
+            return None;
+        }
+        let word = self.word.as_ref()?;
+        let meanings_with_examples = word.meanings_with_examples
+            .iter()
+            .zip(self.selected_meanings.iter())
+            .filter(|(_, selected)| **selected)
+            .map(|(meaning_with_examples, _)| meaning_with_examples.clone())
+            .collect::<Vec<_>>();
+        if meanings_with_examples.is_empty() || word.word.trim().is_empty() {
+            return None;
+        }
+        Some(Word {
+            word: word.word.trim().to_string(),
+            meanings_with_examples,
+            uk_ipa: word.uk_ipa.clone(),
+            us_ipa: word.us_ipa.clone(),
+        })
+    }
+}
+
+/// A word ready to be sent to Anki, with the lookups its note records.
+#[derive(Debug, Clone)]
+pub struct ApprovedWord {
+    pub word: Word,
+    pub kobo_word: KoboWord,
+    pub provider_url: Option<String>,
+}
+
+pub fn approved_words(staged_words: &[StagedWord]) -> Vec<ApprovedWord> {
+    staged_words
+        .iter()
+        .filter_map(|staged_word| Some(ApprovedWord {
+            word: staged_word.approved_word()?,
+            kobo_word: staged_word.kobo_word.clone(),
+            provider_url: staged_word.provider_url.clone(),
+        }))
+        .collect()
+}
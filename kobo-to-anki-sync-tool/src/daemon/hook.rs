@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::report::SyncReport;
+
+use super::log::log_error;
+
+/// Runs the user's hook command through the shell. What happened is passed
+/// in `KOBO_TO_ANKI_*` environment variables so the hook can decide how to
+/// notify (desktop notification, mail, chat message, ...).
+pub async fn run_hook(
+    hook_command: &str,
+    target_name: &str,
+    serial: &str,
+    kobo_path: &Path,
+    deck_name: &str,
+    result: &Result<()>,
+    report: &SyncReport,
+    report_path: Option<&Path>,
+) {
+    let mut command = shell_command(hook_command);
+    command
+        .env("KOBO_TO_ANKI_PROFILE", target_name)
+        .env("KOBO_TO_ANKI_SERIAL", serial)
+        .env("KOBO_TO_ANKI_KOBO_PATH", kobo_path)
+        .env("KOBO_TO_ANKI_DECK", deck_name)
+        .env("KOBO_TO_ANKI_ADDED_WORDS", report.added.len().to_string())
+        .env("KOBO_TO_ANKI_UPDATED_NOTES", report.updated.len().to_string())
+        .env("KOBO_TO_ANKI_WORDS_WITHOUT_DEFINITION", report.without_definition.len().to_string());
+    if let Some(report_path) = report_path {
+        command.env("KOBO_TO_ANKI_REPORT", report_path);
+    }
+    match result {
+        Ok(()) => {
+            command.env("KOBO_TO_ANKI_STATUS", "success");
+        }
+        Err(err) => {
+            command
+                .env("KOBO_TO_ANKI_STATUS", "failure")
+                .env("KOBO_TO_ANKI_ERROR", err.to_string());
+        }
+    }
+    match command.status().await {
+        Ok(status) if status.success() => {}
+        Ok(status) => log_error(&format!("[watch] Hook command exited with {}", status)),
+        Err(err) => log_error(&format!("[watch] Could not run the hook command: {:?}", err)),
+    }
+}
+
+#[cfg(windows)]
+fn shell_command(hook_command: &str) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("cmd");
+    command.arg("/C").arg(hook_command);
+    command
+}
+
+#[cfg(not(windows))]
+fn shell_command(hook_command: &str) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("sh");
+    command.arg("-c").arg(hook_command);
+    command
+}
@@ -0,0 +1,43 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::storage::app_data_dir;
+
+const LOG_FILE_NAME: &str = "daemon.log";
+/// The log is moved to `daemon.log.1` once it is this big, replacing the
+/// previous one.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Prints a line of `--watch` or `--once` and appends it to `daemon.log` in
+/// the data directory. The Windows build has no console, so the file is the
+/// only place its output ends up.
+pub fn log(line: &str) {
+    println!("{}", line);
+    append_to_log_file(line);
+}
+
+/// Same as `log` on stderr.
+pub fn log_error(line: &str) {
+    eprintln!("{}", line);
+    append_to_log_file(line);
+}
+
+fn log_file_path() -> Option<PathBuf> {
+    app_data_dir().ok().map(|data_dir| data_dir.join(LOG_FILE_NAME))
+}
+
+/// Logging never fails the sync, lines that can't be written are only
+/// printed.
+fn append_to_log_file(line: &str) {
+    let Some(path) = log_file_path() else {
+        return;
+    };
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+        let _ = std::fs::rename(&path, path.with_extension("log.1"));
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{} {}", httpdate::fmt_http_date(SystemTime::now()), line);
+    }
+}
@@ -0,0 +1,9 @@
+mod hook;
+mod log;
+mod once;
+mod sync_target;
+mod watch_mode;
+
+pub use log::log_error;
+pub use once::run_once;
+pub use watch_mode::run_watch_mode;
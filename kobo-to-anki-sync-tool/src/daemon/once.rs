@@ -0,0 +1,33 @@
+use anyhow::Result;
+
+use crate::{kobo, settings};
+
+use super::sync_target::SyncTarget;
+
+/// Syncs a reader that is connected right now and returns, for scripts and
+/// scheduled tasks. With `--profile` the profile's reader is used when it
+/// has a serial number, otherwise the first connected reader.
+pub async fn run_once(settings: settings::Settings, cli_args: &settings::CliArgs) -> Result<()> {
+    let target = match SyncTarget::from_cli_profile(&settings, cli_args)? {
+        Some(target) => target,
+        None => SyncTarget::from_settings(&settings, None, cli_args),
+    };
+    target.deck_name()?;
+
+    let (kobo_path, serial) = kobo::find_kobo_paths()?
+        .into_iter()
+        .map(|kobo_path| {
+            let serial = kobo::read_kobo_serial(&kobo_path);
+            (kobo_path, serial)
+        })
+        .find(|(_, serial)| target.matches_serial(serial.as_deref()))
+        .ok_or_else(|| match &target.serial {
+            Some(serial) => anyhow::anyhow!("The reader {} is not connected", serial),
+            None => anyhow::anyhow!("No Kobo reader is connected"),
+        })?;
+
+    let anki_client = crate::create_anki_client(&target.settings);
+    anki_client.check_connection().await
+        .map_err(|err| anyhow::anyhow!("Anki is not reachable: {:#}", err))?;
+    target.sync_and_notify(serial.as_deref().unwrap_or("unknown"), &kobo_path, &anki_client).await
+}
@@ -0,0 +1,164 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use crate::{anki, cards, dictionary, kobo, settings, storage, AppState};
+use crate::report::SyncReport;
+
+use super::hook::run_hook;
+use super::log::{log, log_error};
+
+/// Name used in logs and hooks when no profile is involved.
+const DEFAULT_TARGET_NAME: &str = "default";
+
+/// One kind of sync a headless run can do: the plain settings, or a profile
+/// with its own deck, filters and providers.
+pub struct SyncTarget {
+    pub name: String,
+    /// Serial number of the reader it syncs, any reader when `None`
+    pub serial: Option<String>,
+    pub settings: settings::Settings,
+    pub word_filter: kobo::WordFilter,
+    pub definition_fetcher: dictionary::DefinitionFetcher,
+}
+
+impl SyncTarget {
+    pub fn from_settings(settings: &settings::Settings, serial: Option<String>, cli_args: &settings::CliArgs) -> Self {
+        let settings = crate::effective_settings(settings, None, cli_args);
+        Self {
+            name: DEFAULT_TARGET_NAME.to_string(),
+            serial,
+            definition_fetcher: crate::create_definition_fetcher(&settings),
+            settings,
+            word_filter: kobo::WordFilter::default(),
+        }
+    }
+
+    pub fn from_profile(settings: &settings::Settings, profile: &settings::SyncProfile, cli_args: &settings::CliArgs) -> Self {
+        let settings = crate::effective_settings(settings, Some(profile), cli_args);
+        Self {
+            name: profile.name.clone(),
+            serial: profile.serial.clone(),
+            definition_fetcher: crate::create_definition_fetcher(&settings),
+            settings,
+            word_filter: profile.word_filter(),
+        }
+    }
+
+    /// The profile given with `--profile`, it is an error if it doesn't exist.
+    pub fn from_cli_profile(settings: &settings::Settings, cli_args: &settings::CliArgs) -> Result<Option<Self>> {
+        let Some(profile_name) = &cli_args.profile else {
+            return Ok(None);
+        };
+        let profile = settings.profile(profile_name)
+            .ok_or_else(|| anyhow::anyhow!("There is no sync profile named {:?}", profile_name))?;
+        Ok(Some(SyncTarget::from_profile(settings, profile, cli_args)))
+    }
+
+    pub fn deck_name(&self) -> Result<&str> {
+        self.settings.default_deck
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("No deck for {:?}, set the default deck in the settings, the deck of the profile or pass --deck", self.name))
+    }
+
+    pub fn matches_serial(&self, serial: Option<&str>) -> bool {
+        match &self.serial {
+            Some(target_serial) => Some(target_serial.as_str()) == serial,
+            None => true,
+        }
+    }
+
+    /// Syncs the reader, logs the outcome, saves the report and runs the
+    /// hook command.
+    pub async fn sync_and_notify(&self, serial: &str, kobo_path: &Path, anki_client: &anki::AnkiClient) -> Result<()> {
+        let deck_name = self.deck_name()?;
+        log(&format!("[{}] Syncing reader {} to \"{}\"", self.name, serial, deck_name));
+        let profile_name = Some(self.name.as_str()).filter(|name| *name != DEFAULT_TARGET_NAME);
+        let report = Arc::new(Mutex::new(SyncReport::new(deck_name, profile_name, &self.settings.provider_urls, 0)));
+        let result = self.sync_reader(kobo_path, deck_name, anki_client, &report).await;
+        let mut report = report.lock().unwrap().clone();
+        match &result {
+            Ok(()) => log(&format!(
+                "[{}] Reader {} synced, {} cards added, {} notes with new forms, {} words without a definition",
+                self.name, serial, report.added.len(), report.updated.len(), report.without_definition.len()
+            )),
+            Err(err) => {
+                log_error(&format!("[{}] Could not sync reader {}: {:#}", self.name, serial, err));
+                if report.errors.is_empty() {
+                    report.record_error(None, format!("{:#}", err));
+                }
+            }
+        }
+        let report_path = match report.save_to_reports_dir(&self.name) {
+            Ok(report_path) => {
+                log(&format!("[{}] Report saved to {}", self.name, report_path.display()));
+                Some(report_path)
+            }
+            Err(err) => {
+                log_error(&format!("[{}] Could not save the report: {:#}", self.name, err));
+                None
+            }
+        };
+        if let Some(hook_command) = &self.settings.watch.hook_command {
+            run_hook(hook_command, &self.name, serial, kobo_path, deck_name, &result, &report, report_path.as_deref()).await;
+        }
+        result
+    }
+
+    /// The same steps as the window goes through, without the review: every
+    /// new word is added with all its meanings.
+    async fn sync_reader(
+        &self,
+        kobo_path: &Path,
+        deck_name: &str,
+        anki_client: &anki::AnkiClient,
+        report: &Arc<Mutex<SyncReport>>,
+    ) -> Result<()> {
+        let kobo_path = kobo_path.to_path_buf();
+        let word_filter = self.word_filter.clone();
+        let kobo_words = tokio::task::spawn_blocking(move || kobo::get_words_from_kobo_db(&kobo_path, &word_filter)).await??;
+        let kobo_words = cards::prepare_lookups(kobo_words);
+        let (_, anki_words) = anki_client.get_known_words_from_anki_deck(deck_name, &self.settings.card_template).await?;
+        // Loaded every time so words ignored from the window are picked up
+        let ignore_list = storage::IgnoreList::load()?;
+        let lemmatiser = self.settings.lemmatisation.map(cards::Lemmatiser::new);
+        let diff = cards::find_new_words(&kobo_words, &anki_words, &ignore_list, lemmatiser.as_ref());
+        report.lock().unwrap().ignored_count = diff.ignored_count;
+
+        let staged_words = Arc::new(Mutex::new(vec![]));
+        let connection_status = Arc::new(Mutex::new(true));
+        AppState::fetch_definitions_for_staging(
+            Arc::new(Mutex::new(0.0)),
+            Arc::new(Mutex::new(None)),
+            diff.new_words,
+            &self.definition_fetcher,
+            Arc::clone(&staged_words),
+            Arc::clone(&connection_status),
+            Arc::new(Mutex::new(0)),
+            Arc::clone(report),
+        ).await;
+        if connection_status.lock().unwrap().clone() == false {
+            anyhow::bail!("Could not connect to the words provider");
+        }
+
+        let staged_words = staged_words.lock().unwrap().clone();
+        report.lock().unwrap().record_review(&staged_words);
+        let sync_error = Arc::new(Mutex::new(None));
+        AppState::sync_kobo_to_anki(
+            Arc::new(Mutex::new(0.0)),
+            Arc::clone(&sync_error),
+            cards::approved_words(&staged_words),
+            diff.known_words,
+            anki_client,
+            deck_name,
+            &self.settings.card_template,
+            Arc::new(Mutex::new(0)),
+            Arc::clone(report),
+        ).await;
+        if let Some(err) = sync_error.lock().unwrap().take() {
+            anyhow::bail!(err);
+        }
+        Ok(())
+    }
+}
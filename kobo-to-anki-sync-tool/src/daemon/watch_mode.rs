@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::{kobo, settings};
+
+use super::log::{log, log_error};
+use super::sync_target::SyncTarget;
+
+/// How often AnkiConnect is asked again while a known reader is waiting to
+/// be synced.
+const ANKI_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Syncs every known reader once each time it is connected. Readers that are
+/// connected while Anki is closed are synced as soon as AnkiConnect answers.
+///
+/// Known readers are the serials in the `[watch]` settings, synced with the
+/// plain settings, and the serials of the profiles, synced with their
+/// profile. With `--profile` only that profile is used.
+pub async fn run_watch_mode(settings: settings::Settings, cli_args: &settings::CliArgs) -> Result<()> {
+    let targets = watch_targets(&settings, cli_args)?;
+    for target in &targets {
+        target.deck_name()?;
+    }
+    let anki_client = crate::create_anki_client(&crate::effective_settings(&settings, None, cli_args));
+    let mut device_events = kobo::spawn_device_watcher(None);
+    // Known readers that are connected but not synced yet, with their serial
+    let mut pending_readers: BTreeMap<PathBuf, String> = BTreeMap::new();
+
+    for target in &targets {
+        log(&format!("[{}] Waiting for reader {} to be connected", target.name, target.serial.as_deref().unwrap_or_default()));
+    }
+    loop {
+        if !pending_readers.is_empty() {
+            if anki_client.check_connection().await.is_ok() {
+                for (kobo_path, serial) in std::mem::take(&mut pending_readers) {
+                    for target in targets.iter().filter(|target| target.matches_serial(Some(&serial))) {
+                        // Failures are logged and passed to the hook, the
+                        // reader is synced again when it is reconnected
+                        let _ = target.sync_and_notify(&serial, &kobo_path, &anki_client).await;
+                    }
+                }
+            } else {
+                log(&format!("[watch] Anki is not reachable, trying again in {}s", ANKI_RETRY_INTERVAL.as_secs()));
+            }
+        }
+
+        let event = if pending_readers.is_empty() {
+            device_events.recv().await
+        } else {
+            match tokio::time::timeout(ANKI_RETRY_INTERVAL, device_events.recv()).await {
+                Ok(event) => event,
+                Err(_) => continue,
+            }
+        };
+        let Some(event) = event else {
+            anyhow::bail!("Stopped watching for Kobo readers");
+        };
+        match event {
+            kobo::DeviceEvent::Attached(kobo_path) => match kobo::read_kobo_serial(&kobo_path) {
+                Some(serial) if targets.iter().any(|target| target.matches_serial(Some(&serial))) => {
+                    log(&format!("[watch] Reader {} connected at {}", serial, kobo_path.display()));
+                    pending_readers.insert(kobo_path, serial);
+                }
+                Some(serial) => {
+                    log(&format!("[watch] Ignoring unknown reader {} at {}", serial, kobo_path.display()));
+                }
+                None => {
+                    log_error(&format!("[watch] Could not read the serial number of the reader at {}", kobo_path.display()));
+                }
+            },
+            kobo::DeviceEvent::Detached(kobo_path) => {
+                if let Some(serial) = pending_readers.remove(&kobo_path) {
+                    log(&format!("[watch] Reader {} was disconnected before it could be synced", serial));
+                }
+            }
+        }
+    }
+}
+
+fn watch_targets(settings: &settings::Settings, cli_args: &settings::CliArgs) -> Result<Vec<SyncTarget>> {
+    if let Some(target) = SyncTarget::from_cli_profile(settings, cli_args)? {
+        if target.serial.is_none() {
+            anyhow::bail!("The profile {:?} has no reader serial number, watch mode would not know when to sync it", target.name);
+        }
+        return Ok(vec![target]);
+    }
+    let mut targets = settings.watch.known_serials
+        .iter()
+        .map(|serial| SyncTarget::from_settings(settings, Some(serial.clone()), cli_args))
+        .collect::<Vec<SyncTarget>>();
+    targets.extend(settings.profiles
+        .iter()
+        .filter(|profile| profile.serial.is_some())
+        .map(|profile| SyncTarget::from_profile(settings, profile, cli_args)));
+    if targets.is_empty() {
+        anyhow::bail!("No known readers, add their serial numbers to `known_serials` in the `[watch]` section of the settings or to a profile");
+    }
+    Ok(targets)
+}
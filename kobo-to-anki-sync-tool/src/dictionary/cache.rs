@@ -0,0 +1,238 @@
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::Result;
+use rusqlite::{params, OptionalExtension};
+
+use crate::anki::Word;
+use crate::storage::app_data_dir;
+
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+pub const DEFAULT_NOT_FOUND_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 50_000;
+
+#[derive(Debug, Clone)]
+pub enum CachedDefinition {
+    Found(Word),
+    NotFound,
+}
+
+/// Local SQLite cache of definitions fetched from a words provider.
+///
+/// Entries are keyed by the normalised word and the provider they came from,
+/// so switching providers never serves another provider's definitions.
+pub struct DefinitionCache {
+    connection: Mutex<rusqlite::Connection>,
+    ttl: Duration,
+    not_found_ttl: Duration,
+    max_entries: usize,
+}
+
+impl DefinitionCache {
+    pub fn open_default() -> Result<Self> {
+        let path = app_data_dir()?.join("definitions_cache.sqlite");
+        Self::open(&path, DEFAULT_CACHE_TTL, DEFAULT_NOT_FOUND_TTL, DEFAULT_CACHE_MAX_ENTRIES)
+    }
+
+    pub fn open(
+        path: &Path,
+        ttl: Duration,
+        not_found_ttl: Duration,
+        max_entries: usize,
+    ) -> Result<Self> {
+        let connection = rusqlite::Connection::open(path)?;
+        connection.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS definitions (
+                word TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                found INTEGER NOT NULL,
+                definition TEXT,
+                fetched_at INTEGER NOT NULL,
+                PRIMARY KEY (word, provider)
+            );
+            CREATE INDEX IF NOT EXISTS definitions_fetched_at ON definitions (fetched_at);
+            "
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            ttl,
+            not_found_ttl,
+            max_entries,
+        })
+    }
+
+    /// Returns `None` when there is no entry or when the entry has expired.
+    pub fn get(&self, word: &str, provider: &str) -> Result<Option<CachedDefinition>> {
+        let connection = self.connection.lock().unwrap();
+        let row = connection.query_row(
+            "SELECT found, definition, fetched_at FROM definitions WHERE word = ?1 AND provider = ?2",
+            params![normalise_word(word), provider],
+            |row| Ok((
+                row.get::<_, bool>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        ).optional()?;
+
+        let Some((found, definition, fetched_at)) = row else {
+            return Ok(None);
+        };
+        let ttl = if found { self.ttl } else { self.not_found_ttl };
+        if now() - fetched_at > ttl.as_secs() as i64 {
+            return Ok(None);
+        }
+        if !found {
+            return Ok(Some(CachedDefinition::NotFound));
+        }
+        let definition = definition
+            .ok_or_else(|| anyhow::anyhow!("Cached definition of {} is empty", word))?;
+        let word = serde_json::from_str::<Word>(&definition)?;
+        Ok(Some(CachedDefinition::Found(word)))
+    }
+
+    pub fn insert(&self, word: &str, provider: &str, definition: &CachedDefinition) -> Result<()> {
+        let (found, definition) = match definition {
+            CachedDefinition::Found(word) => (true, Some(serde_json::to_string(word)?)),
+            CachedDefinition::NotFound => (false, None),
+        };
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT OR REPLACE INTO definitions (word, provider, found, definition, fetched_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![normalise_word(word), provider, found, definition, now()],
+        )?;
+        Self::enforce_size_limit(&connection, self.max_entries)?;
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute("DELETE FROM definitions", [])?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        let connection = self.connection.lock().unwrap();
+        let count = connection.query_row("SELECT COUNT(*) FROM definitions", [], |row| row.get::<_, i64>(0))?;
+        Ok(count as usize)
+    }
+
+    fn enforce_size_limit(connection: &rusqlite::Connection, max_entries: usize) -> Result<()> {
+        // Oldest entries go first
+        connection.execute(
+            "DELETE FROM definitions WHERE rowid IN (
+                SELECT rowid FROM definitions ORDER BY fetched_at DESC LIMIT -1 OFFSET ?1
+            )",
+            params![max_entries as i64],
+        )?;
+        Ok(())
+    }
+}
+
+fn normalise_word(word: &str) -> String {
+    word.trim().to_lowercase()
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const PROVIDER: &str = "https://provider.example";
+    const DAY: i64 = 60 * 60 * 24;
+
+    /// A cache in its own SQLite file, removed when the test is done.
+    struct TestCache {
+        cache: DefinitionCache,
+        path: PathBuf,
+    }
+
+    impl TestCache {
+        fn open(name: &str, max_entries: usize) -> Self {
+            let path = std::env::temp_dir().join(format!("kobo-to-anki-cache-{}-{}.sqlite", name, std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            let cache = DefinitionCache::open(&path, DEFAULT_CACHE_TTL, DEFAULT_NOT_FOUND_TTL, max_entries).unwrap();
+            Self { cache, path }
+        }
+
+        fn set_fetched_at(&self, word: &str, fetched_at: i64) {
+            self.cache.connection.lock().unwrap().execute(
+                "UPDATE definitions SET fetched_at = ?1 WHERE word = ?2",
+                params![fetched_at, word],
+            ).unwrap();
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn found(word: &str) -> CachedDefinition {
+        CachedDefinition::Found(Word {
+            word: word.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_entries_expire_after_their_ttl() {
+        let test_cache = TestCache::open("ttl", DEFAULT_CACHE_MAX_ENTRIES);
+        let cache = &test_cache.cache;
+        cache.insert("happy", PROVIDER, &found("happy")).unwrap();
+        cache.insert("qwxz", PROVIDER, &CachedDefinition::NotFound).unwrap();
+
+        test_cache.set_fetched_at("happy", now() - 29 * DAY);
+        test_cache.set_fetched_at("qwxz", now() - DAY + 60);
+        assert!(matches!(cache.get("happy", PROVIDER).unwrap(), Some(CachedDefinition::Found(word)) if word.word == "happy"));
+        assert!(matches!(cache.get("qwxz", PROVIDER).unwrap(), Some(CachedDefinition::NotFound)));
+
+        test_cache.set_fetched_at("happy", now() - 31 * DAY);
+        test_cache.set_fetched_at("qwxz", now() - DAY - 60);
+        assert!(cache.get("happy", PROVIDER).unwrap().is_none());
+        assert!(cache.get("qwxz", PROVIDER).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_oldest_entries_are_evicted_over_the_limit() {
+        let test_cache = TestCache::open("eviction", 2);
+        let cache = &test_cache.cache;
+        cache.insert("one", PROVIDER, &found("one")).unwrap();
+        cache.insert("two", PROVIDER, &found("two")).unwrap();
+        test_cache.set_fetched_at("one", now() - 20);
+        test_cache.set_fetched_at("two", now() - 10);
+
+        cache.insert("three", PROVIDER, &found("three")).unwrap();
+        assert_eq!(cache.len().unwrap(), 2);
+        assert!(cache.get("one", PROVIDER).unwrap().is_none());
+        assert!(cache.get("two", PROVIDER).unwrap().is_some());
+        assert!(cache.get("three", PROVIDER).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_entries_are_keyed_by_normalised_word_and_provider() {
+        let test_cache = TestCache::open("keys", DEFAULT_CACHE_MAX_ENTRIES);
+        let cache = &test_cache.cache;
+        cache.insert(" Happy ", PROVIDER, &found("happy")).unwrap();
+
+        assert!(cache.get("happy", PROVIDER).unwrap().is_some());
+        assert!(cache.get("HAPPY", PROVIDER).unwrap().is_some());
+        assert!(cache.get("happy", "https://other-provider.example").unwrap().is_none());
+
+        cache.insert("happy", "https://other-provider.example", &CachedDefinition::NotFound).unwrap();
+        assert_eq!(cache.len().unwrap(), 2);
+        assert!(matches!(cache.get("happy", PROVIDER).unwrap(), Some(CachedDefinition::Found(_))));
+    }
+}
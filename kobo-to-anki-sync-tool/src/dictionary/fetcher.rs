@@ -0,0 +1,347 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use rand::Rng;
+use reqwest::{header::{HeaderMap, RETRY_AFTER}, StatusCode};
+
+use crate::anki::Word;
+
+use super::{
+    provider_word::{ProviderWord, PROVIDER_RESPONSE_VERSION},
+    CachedDefinition,
+    DefinitionCache,
+};
+
+pub const DEFAULT_CONCURRENCY: usize = 4;
+pub const DEFAULT_MAX_RETRIES: u32 = 6;
+pub const DEFAULT_BASE_BACKOFF: Duration = Duration::from_secs(2);
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(120);
+/// A provider that can't be reached at all is most likely down, so don't keep
+/// the sync waiting on it for the whole retry budget.
+const MAX_CONNECTION_RETRIES: u32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct FetcherConfig {
+    /// Providers are tried in order, the next one is only asked when the
+    /// previous one doesn't know the word or can't be reached
+    pub provider_urls: Vec<String>,
+    pub concurrency: usize,
+    pub max_retries: u32,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl FetcherConfig {
+    pub fn new(provider_urls: &[String], concurrency: usize) -> Self {
+        Self {
+            provider_urls: provider_urls
+                .iter()
+                .map(|provider_url| provider_url.trim().trim_end_matches('/').to_string())
+                .filter(|provider_url| !provider_url.is_empty())
+                .collect(),
+            concurrency,
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_backoff: DEFAULT_BASE_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+/// Fetches definitions from a words provider, reading through the local
+/// [`DefinitionCache`] when there is one.
+///
+/// Requests running in parallel share a single backoff: when the provider
+/// answers 429, every worker waits until the pause is over instead of
+/// hammering the provider with more requests.
+pub struct DefinitionFetcher {
+    http_client: reqwest::Client,
+    pub(super) config: FetcherConfig,
+    definition_cache: Option<DefinitionCache>,
+    paused_until: tokio::sync::Mutex<Option<Instant>>,
+}
+
+/// Where a definition came from, or why there is none.
+#[derive(Debug, Clone)]
+pub enum Lookup {
+    Found {
+        word: Word,
+        provider_url: String,
+    },
+    /// Every provider answered and none of them knows the word
+    NotFound,
+    /// The word is unknown to the providers that answered, the others
+    /// failed with this error
+    Failed(String),
+}
+
+enum FetchAttempt {
+    Done(CachedDefinition),
+    Retry(Option<Duration>),
+    ConnectionFailed(reqwest::Error),
+}
+
+impl DefinitionFetcher {
+    pub fn new(config: FetcherConfig) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            config,
+            definition_cache: None,
+            paused_until: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn with_definition_cache(mut self, definition_cache: DefinitionCache) -> Self {
+        self.definition_cache = Some(definition_cache);
+        self
+    }
+
+    pub fn definition_cache(&self) -> Option<&DefinitionCache> {
+        self.definition_cache.as_ref()
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.config.concurrency.max(1)
+    }
+
+    pub async fn get_word(&self, word: &str, connection_status: Arc<Mutex<bool>>) -> Lookup {
+        self.get_word_from(&self.config.provider_urls, word, connection_status).await
+    }
+
+    /// Like [`DefinitionFetcher::get_word`] for a word that may have come
+    /// through [`DefinitionFetcher::stream_words`], only the providers after
+    /// the first one are asked for words it doesn't know.
+    pub async fn get_streamed_word(
+        &self,
+        word: &str,
+        streamed: Option<&CachedDefinition>,
+        connection_status: Arc<Mutex<bool>>,
+    ) -> Lookup {
+        match (streamed, self.config.provider_urls.split_first()) {
+            (Some(CachedDefinition::Found(found_word)), Some((provider_url, _))) => Lookup::Found {
+                word: found_word.clone(),
+                provider_url: provider_url.clone(),
+            },
+            (Some(CachedDefinition::NotFound), Some((_, other_provider_urls))) => {
+                self.get_word_from(other_provider_urls, word, connection_status).await
+            }
+            _ => self.get_word(word, connection_status).await,
+        }
+    }
+
+    async fn get_word_from(
+        &self,
+        provider_urls: &[String],
+        word: &str,
+        connection_status: Arc<Mutex<bool>>,
+    ) -> Lookup {
+        let mut unreachable_providers = 0;
+        let mut last_error = None;
+        for provider_url in provider_urls {
+            match self.get_word_from_provider(provider_url, word).await {
+                Ok(CachedDefinition::Found(word)) => return Lookup::Found {
+                    word,
+                    provider_url: provider_url.clone(),
+                },
+                Ok(CachedDefinition::NotFound) => continue,
+                Err(e) => {
+                    println!("Could not fetch {} from {}: {:?}", word, provider_url, e);
+                    last_error = Some(format!("{}: {:#}", provider_url, e));
+                    let is_connection_error = e
+                        .downcast_ref::<reqwest::Error>()
+                        .map_or(false, |e| e.is_connect() || e.is_timeout());
+                    if is_connection_error {
+                        unreachable_providers += 1;
+                    }
+                }
+            }
+        }
+        if unreachable_providers > 0 && unreachable_providers == provider_urls.len() {
+            *connection_status.lock().unwrap() = false;
+        }
+        match last_error {
+            Some(error) => Lookup::Failed(error),
+            None => Lookup::NotFound,
+        }
+    }
+
+    async fn get_word_from_provider(&self, provider_url: &str, word: &str) -> Result<CachedDefinition> {
+        if let Some(definition) = self.cached(word, provider_url) {
+            return Ok(definition);
+        }
+        let fetched_word = self.fetch_word_from_provider(provider_url, word).await?;
+        self.cache(word, provider_url, &fetched_word);
+        Ok(fetched_word)
+    }
+
+    fn cached(&self, word: &str, provider_url: &str) -> Option<CachedDefinition> {
+        let definition_cache = self.definition_cache.as_ref()?;
+        match definition_cache.get(word, provider_url) {
+            Ok(definition) => definition,
+            Err(e) => {
+                println!("Could not read {} from the definition cache: {:?}", word, e);
+                None
+            }
+        }
+    }
+
+    pub(super) fn is_cached(&self, word: &str, provider_url: &str) -> bool {
+        self.cached(word, provider_url).is_some()
+    }
+
+    pub(super) fn cache(&self, word: &str, provider_url: &str, definition: &CachedDefinition) {
+        if let Some(definition_cache) = &self.definition_cache {
+            if let Err(e) = definition_cache.insert(word, provider_url, definition) {
+                println!("Could not write {} to the definition cache: {:?}", word, e);
+            }
+        }
+    }
+
+    /// Only definitive answers (a definition or a 404) are returned as `Ok`,
+    /// so that throttled or failed lookups never end up in the cache.
+    async fn fetch_word_from_provider(&self, provider_url: &str, word: &str) -> Result<CachedDefinition> {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_pause().await;
+            let delay = match self.try_fetch_word(provider_url, word).await? {
+                FetchAttempt::Done(definition) => return Ok(definition),
+                FetchAttempt::ConnectionFailed(e) => {
+                    if attempt >= self.config.max_retries.min(MAX_CONNECTION_RETRIES) {
+                        return Err(e.into());
+                    }
+                    self.backoff_delay(attempt)
+                }
+                FetchAttempt::Retry(retry_after) => {
+                    if attempt >= self.config.max_retries {
+                        return Err(anyhow::anyhow!(
+                            "Gave up on {} after {} retries", word, attempt
+                        ));
+                    }
+                    let delay = retry_after.unwrap_or_else(|| self.backoff_delay(attempt));
+                    self.pause_for(delay).await;
+                    delay
+                }
+            };
+            attempt += 1;
+            println!("Retrying {} in {:?} (attempt {}/{})", word, delay, attempt, self.config.max_retries);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn try_fetch_word(&self, provider_url: &str, word: &str) -> Result<FetchAttempt> {
+        let url = format!("{}/get_word/{}?version={}", provider_url, word, PROVIDER_RESPONSE_VERSION);
+        let response = match self.http_client.get(url).send().await {
+            Ok(response) => response,
+            Err(e) => return Ok(FetchAttempt::ConnectionFailed(e)),
+        };
+        match response.status() {
+            StatusCode::NOT_FOUND => {
+                println!("Word {} not found", word);
+                Ok(FetchAttempt::Done(CachedDefinition::NotFound))
+            }
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                let retry_after = parse_retry_after(response.headers());
+                println!("Rate limited while fetching {}, retry after: {:?}", word, retry_after);
+                Ok(FetchAttempt::Retry(retry_after))
+            }
+            status if status.is_server_error() => {
+                println!("Server error while fetching {}: {}", word, status);
+                Ok(FetchAttempt::Retry(None))
+            }
+            status if status.is_success() => {
+                let word = response.json::<ProviderWord>().await?;
+                Ok(FetchAttempt::Done(CachedDefinition::Found(Word::from(word))))
+            }
+            status => Err(anyhow::anyhow!("Unexpected status {} while fetching {}", status, word)),
+        }
+    }
+
+    /// Exponential backoff with jitter, capped at `max_backoff`.
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exponential = self.config.base_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.config.max_backoff);
+        let jitter = rand::thread_rng().gen_range(0..=exponential.as_millis() as u64 / 2);
+        (exponential / 2 + Duration::from_millis(jitter)).min(self.config.max_backoff)
+    }
+
+    pub(super) async fn pause_for(&self, delay: Duration) {
+        let mut paused_until = self.paused_until.lock().await;
+        let until = Instant::now() + delay;
+        if paused_until.map_or(true, |current| current < until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    async fn wait_for_pause(&self) {
+        let paused_until = *self.paused_until.lock().await;
+        if let Some(paused_until) = paused_until {
+            let now = Instant::now();
+            if paused_until > now {
+                tokio::time::sleep(paused_until - now).await;
+            }
+        }
+    }
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    date.duration_since(std::time::SystemTime::now()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay_is_jittered_and_capped() {
+        let fetcher = DefinitionFetcher::new(FetcherConfig::new(&[], 1));
+        for _ in 0..100 {
+            let delay = fetcher.backoff_delay(0);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2), "{:?}", delay);
+            let delay = fetcher.backoff_delay(3);
+            assert!(delay >= Duration::from_secs(8) && delay <= Duration::from_secs(16), "{:?}", delay);
+            let delay = fetcher.backoff_delay(20);
+            assert!(delay >= DEFAULT_MAX_BACKOFF / 2 && delay <= DEFAULT_MAX_BACKOFF, "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(120)));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        headers.insert(RETRY_AFTER, date.parse().unwrap());
+        let delay = parse_retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60), "{:?}", delay);
+
+        // Dates that already passed and garbage are ignored
+        let date = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(60));
+        headers.insert(RETRY_AFTER, date.parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_no_providers_is_not_a_connection_failure() {
+        let fetcher = DefinitionFetcher::new(FetcherConfig::new(&["  ".to_string()], 1));
+        let connection_status = Arc::new(Mutex::new(true));
+        let lookup = fetcher.get_word("happy", connection_status.clone()).await;
+        assert!(matches!(lookup, Lookup::NotFound));
+        assert!(*connection_status.lock().unwrap());
+    }
+}
@@ -0,0 +1,18 @@
+mod cache;
+mod fetcher;
+mod provider_word;
+mod stream;
+
+pub use cache::{
+    CachedDefinition,
+    DefinitionCache,
+};
+
+pub use fetcher::{
+    DefinitionFetcher,
+    FetcherConfig,
+    Lookup,
+    DEFAULT_CONCURRENCY,
+};
+
+pub use stream::ServerQueue;
\ No newline at end of file
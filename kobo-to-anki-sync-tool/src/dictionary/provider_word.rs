@@ -0,0 +1,145 @@
+use serde::Deserialize;
+
+use crate::anki::{MeaningWithExamples, SenseDetails, Word};
+
+/// The version of the provider response that is asked for. Providers that
+/// don't know versions answer with version 1, which has no entries.
+pub const PROVIDER_RESPONSE_VERSION: u32 = 2;
+
+/// A word as the words provider sends it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderWord {
+    word: String,
+    meanings_with_examples: Vec<MeaningWithExamples>,
+    /// Only in version 2 responses
+    #[serde(default)]
+    entries: Vec<ProviderEntry>,
+}
+
+/// The senses of one part of speech.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ProviderEntry {
+    part_of_speech: Option<String>,
+    uk_ipa: Option<String>,
+    us_ipa: Option<String>,
+    senses: Vec<ProviderSense>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ProviderSense {
+    definition: String,
+    examples: Vec<String>,
+    guide_word: Option<String>,
+    cefr_level: Option<String>,
+    grammar: Vec<String>,
+    labels: Vec<String>,
+    register: Option<String>,
+}
+
+impl From<ProviderWord> for Word {
+    /// Senses of every part of speech become one list of meanings, each
+    /// with its part of speech in its details, as cards have no room for
+    /// the grouping.
+    fn from(provider_word: ProviderWord) -> Self {
+        if provider_word.entries.is_empty() {
+            return Word {
+                word: provider_word.word,
+                meanings_with_examples: provider_word.meanings_with_examples,
+                ..Default::default()
+            };
+        }
+        let uk_ipa = provider_word.entries.iter().find_map(|entry| entry.uk_ipa.clone());
+        let us_ipa = provider_word.entries.iter().find_map(|entry| entry.us_ipa.clone());
+        let meanings_with_examples = provider_word.entries
+            .into_iter()
+            .flat_map(|entry| {
+                let part_of_speech = entry.part_of_speech;
+                entry.senses.into_iter().map(move |sense| MeaningWithExamples {
+                    meaning: sense.definition,
+                    examples: sense.examples,
+                    details: SenseDetails {
+                        part_of_speech: part_of_speech.clone(),
+                        guide_word: sense.guide_word,
+                        cefr_level: sense.cefr_level,
+                        grammar: sense.grammar,
+                        labels: sense.labels,
+                        register: sense.register,
+                    },
+                })
+            })
+            .collect();
+        Word {
+            word: provider_word.word,
+            meanings_with_examples,
+            uk_ipa,
+            us_ipa,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_1_word_keeps_its_meanings() {
+        let provider_word = serde_json::from_str::<ProviderWord>(r#"{
+            "word": "happy",
+            "meanings_with_examples": [{"meaning": "feeling pleasure", "examples": ["She looks happy."]}],
+            "has_meaning": true
+        }"#).unwrap();
+        let word = Word::from(provider_word);
+        assert_eq!(word.word, "happy");
+        assert_eq!(word.meanings_with_examples.len(), 1);
+        assert_eq!(word.meanings_with_examples[0].examples, vec!["She looks happy."]);
+        assert_eq!(word.meanings_with_examples[0].details.summary(), "");
+        assert_eq!(word.uk_ipa, None);
+    }
+
+    #[test]
+    fn test_version_2_entries_become_meanings_with_details() {
+        let provider_word = serde_json::from_str::<ProviderWord>(r#"{
+            "version": 2,
+            "word": "run",
+            "meanings_with_examples": [],
+            "has_meaning": true,
+            "entries": [
+                {
+                    "headword": "run",
+                    "part_of_speech": "verb",
+                    "us_ipa": "rʌn",
+                    "senses": [
+                        {"definition": "to go quickly", "examples": ["I ran home."], "guide_word": "GO QUICKLY", "cefr_level": "A1", "grammar": ["I"]},
+                        {"definition": "to be in charge of", "register": "formal"}
+                    ]
+                },
+                {
+                    "headword": "run",
+                    "part_of_speech": "noun",
+                    "uk_ipa": "rʌn",
+                    "senses": [{"definition": "a period of running", "labels": ["UK"]}]
+                }
+            ]
+        }"#).unwrap();
+        let word = Word::from(provider_word);
+        // The first pronunciation of each kind
+        assert_eq!(word.uk_ipa.as_deref(), Some("rʌn"));
+        assert_eq!(word.us_ipa.as_deref(), Some("rʌn"));
+        let meanings = word.meanings_with_examples
+            .iter()
+            .map(|meaning| meaning.meaning.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(meanings, vec!["to go quickly", "to be in charge of", "a period of running"]);
+
+        let details = &word.meanings_with_examples[0].details;
+        assert_eq!(details.part_of_speech.as_deref(), Some("verb"));
+        assert_eq!(details.guide_word.as_deref(), Some("GO QUICKLY"));
+        assert_eq!(details.cefr_level.as_deref(), Some("A1"));
+        assert_eq!(details.grammar, vec!["I"]);
+        assert_eq!(word.meanings_with_examples[1].details.register.as_deref(), Some("formal"));
+        assert_eq!(word.meanings_with_examples[2].details.part_of_speech.as_deref(), Some("noun"));
+        assert_eq!(word.meanings_with_examples[2].details.labels, vec!["UK"]);
+    }
+}
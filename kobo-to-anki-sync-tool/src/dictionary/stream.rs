@@ -0,0 +1,198 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Result;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::anki::Word;
+
+use super::{
+    provider_word::{ProviderWord, PROVIDER_RESPONSE_VERSION},
+    CachedDefinition,
+    DefinitionFetcher,
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The provider sends the queue position every few seconds, a longer
+/// silence means the stream is stuck.
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Where the streamed words are on the provider, from its last update.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ServerQueue {
+    /// Words of other clients ahead of the first one still waiting
+    pub position: usize,
+    /// Streamed words without an answer yet
+    pub waiting: usize,
+    pub resolved: usize,
+    pub total: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamMessage {
+    Result(StreamResult),
+    Queue(ServerQueue),
+    Done,
+    Error {
+        error: String,
+        retry_after: Option<u64>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum StreamResult {
+    Found {
+        word: String,
+        definition: ProviderWord,
+    },
+    NotFound {
+        word: String,
+    },
+    Error {
+        word: String,
+        error: String,
+    },
+}
+
+impl DefinitionFetcher {
+    /// Sends all the words to the first provider over one WebSocket and
+    /// collects its definitive answers as they come, calling
+    /// `on_queue_update` with the position of the words in its fetch queue.
+    ///
+    /// Words already in the local cache are not sent and the answers go into
+    /// it. Words that failed are left out, [`DefinitionFetcher::get_word`]
+    /// retries them. An error means the provider can't stream at all, e.g.
+    /// because it is older than the stream endpoint.
+    pub async fn stream_words(
+        &self,
+        words: &[String],
+        mut on_queue_update: impl FnMut(ServerQueue),
+    ) -> Result<HashMap<String, CachedDefinition>> {
+        let mut definitions = HashMap::new();
+        let Some(provider_url) = self.config.provider_urls.first() else {
+            return Ok(definitions);
+        };
+        let words = words
+            .iter()
+            .filter(|word| !self.is_cached(word, provider_url))
+            .cloned()
+            .collect::<Vec<String>>();
+        if words.is_empty() {
+            return Ok(definitions);
+        }
+
+        let url = format!("{}/v1/words:stream?version={}", websocket_url(provider_url), PROVIDER_RESPONSE_VERSION);
+        let (mut socket, _) = tokio::time::timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(url))
+            .await
+            .map_err(|_| anyhow::anyhow!("Timed out connecting to {}", provider_url))??;
+        let request = serde_json::json!({ "words": words });
+        socket.send(Message::Text(request.to_string())).await?;
+        println!("Streaming {} words from {}", words.len(), provider_url);
+
+        loop {
+            let message = match tokio::time::timeout(MESSAGE_TIMEOUT, socket.next()).await {
+                Ok(Some(message)) => message?,
+                Ok(None) => break,
+                Err(_) => {
+                    println!("The stream from {} went quiet, looking the rest up one by one", provider_url);
+                    break;
+                }
+            };
+            let text = match message {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            match serde_json::from_str::<StreamMessage>(&text)? {
+                StreamMessage::Result(StreamResult::Found { word, definition }) => {
+                    let definition = CachedDefinition::Found(Word::from(definition));
+                    self.cache(&word, provider_url, &definition);
+                    definitions.insert(word, definition);
+                }
+                StreamMessage::Result(StreamResult::NotFound { word }) => {
+                    self.cache(&word, provider_url, &CachedDefinition::NotFound);
+                    definitions.insert(word, CachedDefinition::NotFound);
+                }
+                StreamMessage::Result(StreamResult::Error { word, error }) => {
+                    println!("Could not stream {} from {}: {}", word, provider_url, error);
+                }
+                StreamMessage::Queue(server_queue) => on_queue_update(server_queue),
+                StreamMessage::Done => break,
+                StreamMessage::Error { error, retry_after } => {
+                    // The lookups that follow should not run into the same limit
+                    if let Some(retry_after) = retry_after {
+                        self.pause_for(Duration::from_secs(retry_after)).await;
+                    }
+                    anyhow::bail!("{} refused the stream: {}", provider_url, error);
+                }
+            }
+        }
+        Ok(definitions)
+    }
+}
+
+/// `http://` becomes `ws://` and `https://` becomes `wss://`.
+fn websocket_url(provider_url: &str) -> String {
+    match provider_url.strip_prefix("http") {
+        Some(rest) => format!("ws{}", rest),
+        None => provider_url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_url() {
+        assert_eq!(websocket_url("http://localhost:3000"), "ws://localhost:3000");
+        assert_eq!(websocket_url("https://kobo-to-anki.usiiaa.top"), "wss://kobo-to-anki.usiiaa.top");
+        assert_eq!(websocket_url("ws://localhost:3000"), "ws://localhost:3000");
+    }
+
+    /// The provider flattens the result into the message.
+    #[test]
+    fn test_stream_messages() {
+        let message = r#"{"type":"result","status":"found","word":"happy","definition":{"word":"happy","meanings_with_examples":[{"meaning":"feeling pleasure","examples":[]}]}}"#;
+        match serde_json::from_str::<StreamMessage>(message).unwrap() {
+            StreamMessage::Result(StreamResult::Found { word, definition }) => {
+                assert_eq!(word, "happy");
+                assert_eq!(Word::from(definition).meanings_with_examples[0].meaning, "feeling pleasure");
+            }
+            _ => panic!("not a found result"),
+        }
+
+        let message = r#"{"type":"result","status":"not_found","word":"qwxz"}"#;
+        assert!(matches!(
+            serde_json::from_str::<StreamMessage>(message).unwrap(),
+            StreamMessage::Result(StreamResult::NotFound { word }) if word == "qwxz"
+        ));
+        let message = r#"{"type":"result","status":"error","word":"sad","error":"timed out"}"#;
+        assert!(matches!(
+            serde_json::from_str::<StreamMessage>(message).unwrap(),
+            StreamMessage::Result(StreamResult::Error { word, error }) if word == "sad" && error == "timed out"
+        ));
+
+        let message = r#"{"type":"queue","position":3,"waiting":2,"resolved":5,"total":7}"#;
+        match serde_json::from_str::<StreamMessage>(message).unwrap() {
+            StreamMessage::Queue(server_queue) => {
+                assert_eq!((server_queue.position, server_queue.waiting, server_queue.resolved, server_queue.total), (3, 2, 5, 7));
+            }
+            _ => panic!("not a queue update"),
+        }
+
+        assert!(matches!(serde_json::from_str::<StreamMessage>(r#"{"type":"done"}"#).unwrap(), StreamMessage::Done));
+        let message = r#"{"type":"error","error":"Too many requests","retry_after":30}"#;
+        assert!(matches!(
+            serde_json::from_str::<StreamMessage>(message).unwrap(),
+            StreamMessage::Error { retry_after: Some(30), .. }
+        ));
+        assert!(matches!(
+            serde_json::from_str::<StreamMessage>(r#"{"type":"error","error":"Bad request"}"#).unwrap(),
+            StreamMessage::Error { retry_after: None, .. }
+        ));
+    }
+}
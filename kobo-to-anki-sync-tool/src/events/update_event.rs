@@ -4,13 +4,12 @@ use eframe::egui;
 
 use crate::anki::AnkiClient;
 use crate::anki::AnkiConnectionStatus;
-use crate::cards::extract_words_from_anki_cards;
-use crate::cards::prepare_words;
-use crate::kobo::get_words_from_kobo_db;
+use crate::kobo::load_words_from_kobo_db_non_blocking;
 use crate::AppState;
 use crate::layout;
 
-use crate::kobo::{find_and_validate_kobo_path, validate_kobo_path};
+use crate::kobo::validate_kobo_path;
+use crate::sync_phase::SyncPhase;
 
 pub fn update_ui(
     app_state: &mut AppState,
@@ -26,20 +25,18 @@ pub fn update_ui(
         }
     }
 
-    let fresh_kobo_path = find_and_validate_kobo_path();
-    if app_state.kobo_path.is_none() {
-        app_state.kobo_path = fresh_kobo_path.clone();
-    }
-    if app_state.kobo_path.is_some() && fresh_kobo_path.is_none() {
-        if !app_state.custom_path {
-            app_state.kobo_path = None;
-        }
+    while let Ok(event) = app_state.channels.device_events_rc.try_recv() {
+        app_state.handle_device_event(event);
     }
+    app_state.receive_alternate_lookups();
     egui::TopBottomPanel::top("Kobo To Anki Sync Tool").show(ctx, |ui| {
         layout::draw_header(ui);
+        layout::draw_settings_button(app_state, ui);
     });
+    layout::draw_settings_window(app_state, ctx);
 
     egui::CentralPanel::default().show(ctx, |ui| {
+        layout::display_profile_selection(app_state, ui);
         layout::draw_kobo_connection_information_message(app_state, ui);
 
         if app_state.kobo_path.is_none() {
@@ -52,129 +49,150 @@ pub fn update_ui(
         if app_state.invalid_kobo_path {
             layout::notify_user_about_invalid_kobo_path(ui);
         }
+        if app_state.channels.kobo_words_rc.is_some() {
+            layout::show_loading_message("Reading the Kobo database...", ui);
+        }
+        if app_state.kobo_db_error.is_some() {
+            layout::notify_user_about_kobo_db_error(app_state, ui);
+        }
 
         ui.add_space(10.0);
         layout::draw_horizontal_line(ui);
         ui.add_space(10.0);
 
         layout::display_anki_connection_status_message(app_state, ui);
+        let deck_can_be_changed = !matches!(app_state.phase, SyncPhase::Syncing);
         if app_state.anki_connection_status != crate::anki::AnkiConnectionStatus::Connected {
             layout::draw_anki_connection_guide(ui);
-        } else if app_state.sync_started == false && !(app_state.prepared_words_from_anki.is_some() && app_state.prepared_words_from_kobo.is_some() && app_state.kobo_path.is_some()) && app_state.prepared_words_from_kobo.is_some() {
+        } else if deck_can_be_changed && app_state.prepared_words_from_kobo.is_some() {
             ui.add_space(10.0);
             layout::draw_horizontal_line(ui);
             layout::display_deck_selection_dropdown(app_state, ui);
         }
+        if app_state.channels.anki_cards_rc.is_some() {
+            layout::show_loading_message("Loading cards from the deck...", ui);
+        }
 
         ui.add_space(10.0);
         layout::draw_horizontal_line(ui);
         ui.add_space(10.0);
 
-        if app_state.prepared_words_from_kobo.is_some() && app_state.prepared_words_from_anki.is_some() {
-            let kobo_words = app_state.prepared_words_from_kobo.as_ref().unwrap();
-            let anki_words = app_state.prepared_words_from_anki.as_ref().unwrap();
-            if app_state.words_to_add.is_none() {
-                let mut new_words = vec![];
-                for word in kobo_words {
-                    if !anki_words.contains(word) {
-                        new_words.push(word.clone());
-                    }
-                }
-                app_state.words_to_add = Some(new_words);
+        match app_state.phase.clone() {
+            SyncPhase::Detecting => {
+                layout::display_rescan_button(app_state, ui);
             }
-            let new_words = app_state.words_to_add.as_ref().unwrap();
-
-            //println!("new words count: {:?}", new_words);
-            let new_words_count = new_words.len();
-
-            let already_added_words = kobo_words.len() - new_words_count;
-            let total_words_in_kobo_count = kobo_words.len();
-
-            let words_with_no_definitions = app_state.words_with_no_definitions.lock().unwrap().clone() as usize;
-
-            layout::display_new_words_count(
-                &app_state,
-                ui,
-                new_words_count,
-                already_added_words,
-                total_words_in_kobo_count,
-                words_with_no_definitions,
-            );
-            ui.add_space(10.0);
-            layout::draw_horizontal_line(ui);
-            ui.add_space(10.0);
+            SyncPhase::Ready => {
+                display_word_counts(app_state, ui);
+                layout::display_ignore_list_controls(app_state, ui);
+                layout::display_definition_cache_controls(app_state, ui);
+                layout::display_rescan_button(app_state, ui);
+                ui.add_space(10.0);
+                layout::draw_horizontal_line(ui);
+                ui.add_space(10.0);
 
-            if app_state.sync_started == false {
                 layout::display_start_button(app_state, ui);
             }
+            SyncPhase::Planning => {
+                display_word_counts(app_state, ui);
+                ui.add_space(10.0);
+                layout::draw_horizontal_line(ui);
+                ui.add_space(10.0);
 
-            if app_state.sync_started && app_state.server_connection_status.lock().unwrap().clone() == true {
-                if app_state.sync_progress.lock().unwrap().clone() >= 100.0 {
-                    layout::show_done_message(ui);
+                if app_state.staging_done() {
+                    layout::display_sync_approved_button(app_state, ui);
+                    ui.add_space(10.0);
+                    layout::draw_horizontal_line(ui);
+                    ui.add_space(10.0);
+                    layout::display_needs_attention_list(app_state, ui);
+                    layout::display_staging_table(app_state, ui);
                 } else {
-                    layout::show_sync_started_message(ui);
+                    layout::show_fetching_definitions_message(ui);
+                    // The provider knows better than the elapsed time while it streams
+                    if !layout::show_server_queue_message(&app_state.staging_server_queue, ui) {
+                        layout::show_eta_message(app_state.staging_started_at.unwrap(), &app_state.staging_progress, ui);
+                    }
+                    ui.add_space(10.0);
+                    layout::draw_horizontal_line(ui);
+                    ui.add_space(10.0);
+
+                    layout::show_progress_bar(&app_state.staging_progress, ui);
                 }
-                layout::show_eta_message(app_state, ui);
             }
+            SyncPhase::Syncing => {
+                display_word_counts(app_state, ui);
+                ui.add_space(10.0);
+                layout::draw_horizontal_line(ui);
+                ui.add_space(10.0);
 
-            if app_state.sync_started && app_state.server_connection_status.lock().unwrap().clone() == true {
+                layout::show_sync_started_message(ui);
+                layout::show_eta_message(app_state.sync_started_at.unwrap(), &app_state.sync_progress, ui);
                 ui.add_space(10.0);
                 layout::draw_horizontal_line(ui);
                 ui.add_space(10.0);
 
-                layout::show_progress_bar(app_state, ui);
+                layout::show_progress_bar(&app_state.sync_progress, ui);
             }
+            SyncPhase::Done => {
+                display_word_counts(app_state, ui);
+                ui.add_space(10.0);
+                layout::draw_horizontal_line(ui);
+                ui.add_space(10.0);
 
-            // if app_state.sync_progress.lock().unwrap().clone() >= 100 {
-            //     ui.add_space(10.0);
-            //     layout::draw_horizontal_line(ui);
-            //     ui.add_space(10.0);
-
-            //     layout::show_done_message(&app_state, ui);
-            // }
-        }
-        if app_state.server_connection_status.lock().unwrap().clone() == false {
-            layout::show_server_connection_error_message(ui);
+                layout::show_done_message(ui);
+                layout::show_progress_bar(&app_state.sync_progress, ui);
+                layout::display_new_sync_button(app_state, ui);
+                layout::display_sync_report(app_state, ui);
+            }
+            SyncPhase::Failed(err) => {
+                layout::show_sync_failed_message(&err, ui);
+                layout::display_new_sync_button(app_state, ui);
+                layout::display_sync_report(app_state, ui);
+            }
         }
     });
 
     app_state.file_dialog.update(ctx);
+    app_state.stop_words_file_dialog.update(ctx);
+    app_state.report_file_dialog.update(ctx);
+
+    if let Some(path) = app_state.stop_words_file_dialog.take_selected() {
+        match app_state.ignore_list.import_stop_words(&path) {
+            Ok(added) => println!("Imported {} stop-words from {:?}", added, path),
+            Err(err) => eprintln!("Could not import stop-words from {:?}: {:?}", path, err),
+        }
+        // Recompute the diff so the new stop-words are filtered out
+        if app_state.phase == SyncPhase::Ready {
+            app_state.words_to_add = None;
+        }
+    }
+
+    if let Some(path) = app_state.report_file_dialog.take_selected() {
+        if let Some(format) = app_state.report_export_format.take() {
+            match app_state.sync_report.lock().unwrap().save(&path, format) {
+                Ok(path) => println!("Saved the sync report to {:?}", path),
+                Err(err) => eprintln!("Could not save the sync report to {:?}: {:?}", path, err),
+            }
+        }
+    }
 
     // Check if the user selected a file.
     if let Some(path) = app_state.file_dialog.take_selected() {
         if validate_kobo_path(&path) {
-            app_state.kobo_path = Some(path);
+            app_state.select_kobo_path(Some(path));
             app_state.invalid_kobo_path = false;
             app_state.custom_path = true;
 
         } else {
-            app_state.kobo_path = None;
+            app_state.select_kobo_path(None);
             app_state.invalid_kobo_path = true;
         }
     };
 
-    if app_state.anki_connection_status == AnkiConnectionStatus::Connected && app_state.raw_cards_from_anki.is_none() && app_state.selected_deck_name.is_some() {
-        let async_rt = &app_state.async_rt;
-        async_rt.block_on(async {
-            let cards = app_state.anki_client.get_cards_from_anki_deck(app_state.selected_deck_name.as_ref().unwrap()).await;
-            let formated_cards = extract_words_from_anki_cards(&cards);
-
-
-            //println!("len: {}", formated_cards.len());
-            app_state.prepared_words_from_anki = Some(formated_cards);
-            app_state.raw_cards_from_anki = Some(cards);
-        });
+    if app_state.anki_connection_status == AnkiConnectionStatus::Connected {
+        AnkiClient::load_cards_from_anki_deck_non_blocking(app_state);
     }
 
-    if app_state.kobo_path.is_some() && app_state.invalid_kobo_path == false && app_state.prepared_words_from_kobo.is_none() {
-        let words = get_words_from_kobo_db(
-            &app_state.kobo_path.as_ref().unwrap()
-        ).unwrap();
-        app_state.prepared_words_from_kobo = Some(
-            prepare_words(words)
-        );
-        //println!("{:?}", app_state.prepared_words_from_kobo);
-    }
+    load_words_from_kobo_db_non_blocking(app_state);
 
     // if app_state.prepared_words_from_kobo.is_some() && app_state.prepared_words_from_anki.is_some() {
     //     let kobo_words = app_state.prepared_words_from_kobo.as_ref().unwrap();
@@ -190,5 +208,31 @@ pub fn update_ui(
     //     println!("old words count: {}", kobo_words.len() - new_words.len());
     // }
 
+    app_state.update_phase();
+    app_state.continue_requested_staging();
+
     ctx.request_repaint_after(Duration::from_millis(300));
-}
\ No newline at end of file
+}
+
+fn display_word_counts(app_state: &AppState, ui: &mut egui::Ui) {
+    let kobo_words = app_state.prepared_words_from_kobo.as_ref().unwrap();
+    let new_words_count = app_state.words_to_add.as_ref().unwrap().len();
+    let ignored_words_count = app_state.ignored_words_count;
+    let already_added_words = app_state.known_words
+        .iter()
+        .map(|kobo_word| kobo_word.lookups.len())
+        .sum();
+    let total_words_in_kobo_count = kobo_words.len();
+
+    let words_with_no_definitions = app_state.words_with_no_definitions.lock().unwrap().clone() as usize;
+
+    layout::display_new_words_count(
+        &app_state,
+        ui,
+        new_words_count,
+        already_added_words,
+        total_words_in_kobo_count,
+        words_with_no_definitions,
+        ignored_words_count,
+    );
+}
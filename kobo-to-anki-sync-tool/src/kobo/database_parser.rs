@@ -1,17 +1,128 @@
 use rusqlite;
+use tokio::sync::mpsc::error::TryRecvError;
 
+use crate::cards::prepare_lookups;
+use crate::AppState;
+
+
+/// Narrows the looked up words down to one dictionary and/or book.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WordFilter {
+    /// `DictSuffix` of the dictionary the word was looked up in, e.g. `-de`
+    pub dict_suffix: Option<String>,
+    /// Part of the book title or file path, case-insensitive
+    pub book: Option<String>,
+}
+
+/// A word looked up on the reader and the book it was looked up in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KoboLookup {
+    pub word: String,
+    /// Title of the book, or its file name for books without one
+    pub book: Option<String>,
+}
 
 pub fn get_words_from_kobo_db(
     kobo_path: &std::path::PathBuf,
-) -> Result<Vec<String>, rusqlite::Error> {
+    word_filter: &WordFilter,
+) -> Result<Vec<KoboLookup>, rusqlite::Error> {
     let kobo_reader_sqlite = kobo_path.join(".kobo/KoboReader.sqlite");
     let conn = rusqlite::Connection::open(kobo_reader_sqlite)?;
-    let mut stmt = conn.prepare("SELECT * FROM WordList")?;
+    // Books are the content rows with ContentType 6, their ContentID is the
+    // VolumeId the word was looked up in
+    let mut stmt = conn.prepare(
+        "SELECT WordList.Text, content.Title, WordList.VolumeId FROM WordList
+        LEFT JOIN content ON content.ContentID = WordList.VolumeId AND content.ContentType = 6
+        WHERE (?1 IS NULL OR WordList.DictSuffix = ?1 OR WordList.DictSuffix = '-' || ?1)
+        AND (?2 IS NULL OR WordList.VolumeId LIKE ?2 OR content.Title LIKE ?2)"
+    )?;
+    let book_pattern = word_filter.book
+        .as_ref()
+        .map(|book| format!("%{}%", book));
     let words = stmt
-        .query_map([], |row| {
-            Ok(row.get(0)?)
+        .query_map(rusqlite::params![word_filter.dict_suffix, book_pattern], |row| {
+            let title: Option<String> = row.get(1)?;
+            let volume_id: Option<String> = row.get(2)?;
+            Ok(KoboLookup {
+                word: row.get(0)?,
+                book: title
+                    .or_else(|| volume_id.as_deref().map(book_file_name))
+                    .filter(|book| !book.trim().is_empty()),
+            })
         })?
-        .map(|word| word.unwrap())
-        .collect::<Vec<String>>();
+        .map(|lookup| lookup.unwrap())
+        .collect::<Vec<KoboLookup>>();
     Ok(words)
-}
\ No newline at end of file
+}
+
+/// Sideloaded books have no title in the database, their VolumeId is the
+/// path of the file, e.g. `file:///mnt/onboard/Books/Dracula.epub`.
+fn book_file_name(volume_id: &str) -> String {
+    let file_name = volume_id.rsplit('/').next().unwrap_or(volume_id);
+    match file_name.rsplit_once('.') {
+        Some((name, _extension)) if !name.is_empty() => name.to_string(),
+        _ => file_name.to_string(),
+    }
+}
+
+pub fn load_words_from_kobo_db_non_blocking(app_state: &mut AppState) {
+    let Some(kobo_path) = app_state.kobo_path.clone() else {
+        return;
+    };
+    if app_state.invalid_kobo_path || app_state.prepared_words_from_kobo.is_some() || app_state.kobo_db_error.is_some() {
+        return;
+    }
+    if app_state.channels.kobo_words_rc.is_none() {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        app_state.channels.kobo_words_rc = Some(rx);
+        let word_filter = app_state.word_filter();
+        app_state.async_rt.spawn(async move {
+            let path = kobo_path.clone();
+            let res = tokio::task::spawn_blocking(move || {
+                get_words_from_kobo_db(&path, &word_filter).map(prepare_lookups)
+            }).await;
+            let res = match res {
+                Ok(res) => res.map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            let _ = tx.send((kobo_path, res)).await;
+        });
+    }
+
+    if let Some(rx) = &mut app_state.channels.kobo_words_rc {
+        match rx.try_recv() {
+            Ok((loaded_kobo_path, res)) => {
+                app_state.channels.kobo_words_rc = None;
+                // The device was changed while its database was being read
+                if Some(&loaded_kobo_path) != app_state.kobo_path.as_ref() {
+                    return;
+                }
+                match res {
+                    Ok(words) => app_state.prepared_words_from_kobo = Some(words),
+                    Err(err) => {
+                        eprintln!("Could not read the Kobo database: {}", err);
+                        app_state.kobo_db_error = Some(err);
+                    }
+                }
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                app_state.channels.kobo_words_rc = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_file_name_of_sideloaded_books() {
+        assert_eq!(book_file_name("file:///mnt/onboard/Books/Dracula.epub"), "Dracula");
+        assert_eq!(book_file_name("file:///mnt/onboard/Books/README"), "README");
+        assert_eq!(book_file_name("file:///mnt/onboard/.hidden"), ".hidden");
+        // Store books keep their id
+        assert_eq!(book_file_name("0f1c2d3e-4b5a-6978-8a9b-0c1d2e3f4a5b"), "0f1c2d3e-4b5a-6978-8a9b-0c1d2e3f4a5b");
+    }
+}
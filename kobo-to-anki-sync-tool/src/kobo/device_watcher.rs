@@ -0,0 +1,320 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use eframe::egui;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Attached(PathBuf),
+    Detached(PathBuf),
+}
+
+/// Waits a bit after a mount change before rescanning, the mount point
+/// directory is usually created before the filesystem is mounted on it.
+const SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Starts a background thread that reports Kobo readers being connected and
+/// disconnected. Every reader that is already connected is reported as
+/// attached right away. The thread blocks until the mounts change, so after
+/// the receiver is dropped it only stops at the next mount or unmount.
+///
+/// `ctx` is asked to repaint after every change so the window reacts even
+/// when it is idle, watch mode runs without one.
+pub fn spawn_device_watcher(ctx: Option<egui::Context>) -> mpsc::Receiver<DeviceEvent> {
+    let (tx, rx) = mpsc::channel(16);
+    std::thread::Builder::new()
+        .name("kobo-device-watcher".to_string())
+        .spawn(move || watch_devices(tx, ctx))
+        .expect("Could not start the device watcher thread");
+    rx
+}
+
+/// The readers connected right now, for one-off runs that don't keep
+/// watching.
+pub fn find_kobo_paths() -> anyhow::Result<BTreeSet<PathBuf>> {
+    Ok(platform::MountWatcher::new()?.find_kobo_paths())
+}
+
+fn watch_devices(tx: mpsc::Sender<DeviceEvent>, ctx: Option<egui::Context>) {
+    let mut mount_watcher = match platform::MountWatcher::new() {
+        Ok(mount_watcher) => mount_watcher,
+        Err(err) => {
+            eprintln!("Could not watch for Kobo readers: {:?}", err);
+            return;
+        }
+    };
+    let mut known_devices = BTreeSet::new();
+    loop {
+        let devices = mount_watcher.find_kobo_paths();
+        let events = known_devices
+            .difference(&devices)
+            .map(|path: &PathBuf| DeviceEvent::Detached(path.clone()))
+            .chain(devices
+                .difference(&known_devices)
+                .map(|path| DeviceEvent::Attached(path.clone())))
+            .collect::<Vec<DeviceEvent>>();
+        for event in events {
+            println!("Device event: {:?}", event);
+            if tx.blocking_send(event).is_err() {
+                return;
+            }
+        }
+        if let Some(ctx) = &ctx {
+            ctx.request_repaint();
+        }
+        known_devices = devices;
+
+        if let Err(err) = mount_watcher.wait_for_change() {
+            eprintln!("Stopped watching for Kobo readers: {:?}", err);
+            return;
+        }
+        if tx.is_closed() {
+            return;
+        }
+        std::thread::sleep(SETTLE_DELAY);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::BTreeSet;
+    use std::fs::File;
+    use std::io::{Read, Seek, SeekFrom};
+    use std::os::fd::AsRawFd;
+    use std::path::PathBuf;
+
+    use inotify::{Inotify, WatchMask};
+
+    use crate::kobo::validate_kobo_path;
+
+    /// Filesystems a Kobo reader can show up as, anything else (proc, tmpfs,
+    /// network shares, ...) is never touched while scanning.
+    const KOBO_FILESYSTEMS: [&str; 5] = ["vfat", "msdos", "exfat", "fuseblk", "fuse.exfat"];
+
+    /// Blocks in `poll(2)` on `/proc/self/mountinfo`, which the kernel marks
+    /// with `POLLPRI` whenever the mount table changes, and on an inotify
+    /// instance watching the directories desktop environments mount into.
+    pub struct MountWatcher {
+        mountinfo: File,
+        inotify: Option<Inotify>,
+    }
+
+    impl MountWatcher {
+        pub fn new() -> anyhow::Result<Self> {
+            let mountinfo = File::open("/proc/self/mountinfo")?;
+            let inotify = match watch_mount_directories() {
+                Ok(inotify) => Some(inotify),
+                Err(err) => {
+                    eprintln!("Could not watch the mount directories, relying on the mount table only: {:?}", err);
+                    None
+                }
+            };
+            Ok(Self { mountinfo, inotify })
+        }
+
+        pub fn find_kobo_paths(&mut self) -> BTreeSet<PathBuf> {
+            // Reading the file from the start is also what clears the
+            // pending `POLLPRI` notification
+            let mut content = String::new();
+            let read = self.mountinfo
+                .seek(SeekFrom::Start(0))
+                .and_then(|_| self.mountinfo.read_to_string(&mut content));
+            if let Err(err) = read {
+                eprintln!("Could not read the mount table: {:?}", err);
+                return BTreeSet::new();
+            }
+            kobo_filesystem_mount_points(&content)
+                .into_iter()
+                .filter(validate_kobo_path)
+                .collect()
+        }
+
+        pub fn wait_for_change(&mut self) -> anyhow::Result<()> {
+            let mut poll_fds = vec![libc::pollfd {
+                fd: self.mountinfo.as_raw_fd(),
+                events: libc::POLLPRI,
+                revents: 0,
+            }];
+            if let Some(inotify) = &self.inotify {
+                poll_fds.push(libc::pollfd {
+                    fd: inotify.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                });
+            }
+            loop {
+                let res = unsafe {
+                    libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as libc::nfds_t, -1)
+                };
+                if res >= 0 {
+                    break;
+                }
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(err.into());
+                }
+            }
+            if let Some(inotify) = &mut self.inotify {
+                let mut buffer = [0; 4096];
+                // Only the wake-up matters, drain the queued events
+                while let Ok(events) = inotify.read_events(&mut buffer) {
+                    if events.count() == 0 {
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn watch_mount_directories() -> anyhow::Result<Inotify> {
+        let inotify = Inotify::init()?;
+        let mut mount_directories = vec![
+            PathBuf::from("/media"),
+            PathBuf::from("/mnt"),
+            PathBuf::from("/run/media"),
+        ];
+        if let Ok(user) = std::env::var("USER") {
+            mount_directories.push(PathBuf::from("/media").join(&user));
+            mount_directories.push(PathBuf::from("/run/media").join(&user));
+        }
+        for mount_directory in mount_directories.iter().filter(|dir| dir.is_dir()) {
+            inotify.watches().add(
+                mount_directory,
+                WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_TO | WatchMask::MOVED_FROM,
+            )?;
+        }
+        Ok(inotify)
+    }
+
+    /// Mount points whose filesystem a Kobo reader can show up as.
+    fn kobo_filesystem_mount_points(content: &str) -> Vec<PathBuf> {
+        parse_mountinfo(content)
+            .into_iter()
+            .filter(|(_, fs_type)| KOBO_FILESYSTEMS.contains(&fs_type.as_str()))
+            .map(|(mount_point, _)| mount_point)
+            .collect()
+    }
+
+    /// Returns the mount point and filesystem type of every mount.
+    fn parse_mountinfo(content: &str) -> Vec<(PathBuf, String)> {
+        content
+            .lines()
+            .filter_map(|line| {
+                let fields = line.split(' ').collect::<Vec<&str>>();
+                let mount_point = fields.get(4)?;
+                // Optional fields end with a lone "-", the type follows it
+                let separator = fields.iter().position(|field| *field == "-")?;
+                let fs_type = fields.get(separator + 1)?;
+                Some((PathBuf::from(unescape_mount_point(mount_point)), fs_type.to_string()))
+            })
+            .collect()
+    }
+
+    /// Spaces, tabs, newlines and backslashes are written as octal escapes.
+    fn unescape_mount_point(mount_point: &str) -> String {
+        let bytes = mount_point.as_bytes();
+        let mut unescaped = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let escape = bytes.get(i + 1..i + 4)
+                .filter(|_| bytes[i] == b'\\')
+                .and_then(|digits| std::str::from_utf8(digits).ok())
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+            match escape {
+                Some(byte) => {
+                    unescaped.push(byte);
+                    i += 4;
+                }
+                None => {
+                    unescaped.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&unescaped).to_string()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const MOUNTINFO: &str = r"22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+25 22 0:5 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw
+98 22 0:45 / /run/user/1000 rw,nosuid,nodev,relatime shared:240 - tmpfs tmpfs rw,size=1620396k,mode=700,uid=1000
+412 22 8:17 / /run/media/reader/KOBOeReader rw,nosuid,nodev,relatime shared:229 - vfat /dev/sdb1 rw,uid=1000,gid=1000
+415 22 8:33 / /media/reader/My\040Kobo\134Libra rw,nosuid,nodev,relatime - exfat /dev/sdc1 rw
+418 22 8:49 / /mnt/usb\040stick rw,relatime shared:231 master:7 - ext4 /dev/sdd1 rw
+";
+
+        #[test]
+        fn test_parse_mountinfo() {
+            let mounts = parse_mountinfo(MOUNTINFO);
+            assert_eq!(mounts.len(), 6);
+            assert_eq!(mounts[0], (PathBuf::from("/"), "ext4".to_string()));
+            assert_eq!(mounts[3], (PathBuf::from("/run/media/reader/KOBOeReader"), "vfat".to_string()));
+            // Escaped space and backslash
+            assert_eq!(mounts[4], (PathBuf::from("/media/reader/My Kobo\\Libra"), "exfat".to_string()));
+            // More than one optional field before the separator
+            assert_eq!(mounts[5], (PathBuf::from("/mnt/usb stick"), "ext4".to_string()));
+            assert!(parse_mountinfo("not a mountinfo line\n").is_empty());
+        }
+
+        #[test]
+        fn test_only_kobo_filesystems_are_kept() {
+            assert_eq!(
+                kobo_filesystem_mount_points(MOUNTINFO),
+                vec![
+                    PathBuf::from("/run/media/reader/KOBOeReader"),
+                    PathBuf::from("/media/reader/My Kobo\\Libra"),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_unescape_mount_point() {
+            assert_eq!(unescape_mount_point("/media/KOBOeReader"), "/media/KOBOeReader");
+            assert_eq!(unescape_mount_point("/media/My\\040Kobo"), "/media/My Kobo");
+            assert_eq!(unescape_mount_point("/media/a\\011b\\012c"), "/media/a\tb\nc");
+            // Backslashes without three octal digits are kept as they are
+            assert_eq!(unescape_mount_point("/media/a\\b"), "/media/a\\b");
+            assert_eq!(unescape_mount_point("/media/a\\04"), "/media/a\\04");
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    use crate::kobo::validate_kobo_path;
+
+    /// There is no cheap mount notification to wait on here, so the disks
+    /// are listed again every few seconds on the watcher thread.
+    const RESCAN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+    pub struct MountWatcher;
+
+    impl MountWatcher {
+        pub fn new() -> anyhow::Result<Self> {
+            Ok(Self)
+        }
+
+        pub fn find_kobo_paths(&mut self) -> BTreeSet<PathBuf> {
+            disk_list::get_disk_list()
+                .iter()
+                .filter_map(|disk| disk.get(2))
+                .filter(|mount_point| mount_point.contains("KOBOeReader"))
+                .map(PathBuf::from)
+                .filter(|kobo_path| validate_kobo_path(kobo_path))
+                .collect()
+        }
+
+        pub fn wait_for_change(&mut self) -> anyhow::Result<()> {
+            std::thread::sleep(RESCAN_INTERVAL);
+            Ok(())
+        }
+    }
+}
@@ -1,8 +1,11 @@
 mod find_utils;
 mod database_parser;
+mod device_watcher;
 mod sync;
 
-pub use find_utils::find_and_validate_kobo_path;
 pub use find_utils::validate_kobo_path;
+pub use find_utils::read_kobo_serial;
 
-pub use database_parser::get_words_from_kobo_db;
+pub use device_watcher::{find_kobo_paths, spawn_device_watcher, DeviceEvent};
+
+pub use database_parser::{get_words_from_kobo_db, load_words_from_kobo_db_non_blocking, KoboLookup, WordFilter};
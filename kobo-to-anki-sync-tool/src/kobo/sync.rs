@@ -1,59 +1,397 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use crate::{anki, AppState};
+use futures::StreamExt;
+
+use crate::{anki, cards::{approved_words, normalise_word, ApprovedWord, KoboWord, StagedWord}, dictionary, report::SyncReport, AppState};
+
+use crate::sync_phase::SyncPhase;
 
 
 impl AppState {
-    pub async fn sync_kobo_to_anki(
-        already_processed_words: u32,
+    pub fn start_staging(&mut self) {
+        if self.phase != SyncPhase::Ready || self.words_to_add.is_none() {
+            return;
+        }
+        self.staging_started_at = Some(std::time::Instant::now());
+        self.phase = SyncPhase::Planning;
+        let staging_progress = Arc::clone(&self.staging_progress);
+        let staging_server_queue = Arc::clone(&self.staging_server_queue);
+        let words_to_add = self.words_to_add.as_ref().unwrap().clone();
+        let definition_fetcher = self.definition_fetcher.clone();
+        let staged_words = Arc::clone(&self.staged_words);
+        let connection_status = Arc::clone(&self.server_connection_status);
+        let words_with_no_definitions = Arc::clone(&self.words_with_no_definitions);
+        let settings = self.effective_settings();
+        self.sync_report = Arc::new(Mutex::new(SyncReport::new(
+            self.selected_deck_name.as_deref().unwrap_or_default(),
+            self.selected_profile().map(|profile| profile.name.as_str()),
+            &settings.provider_urls,
+            self.ignored_words_count,
+        )));
+        let sync_report = Arc::clone(&self.sync_report);
+
+        let staging_task = self.async_rt.spawn(async move {
+            AppState::fetch_definitions_for_staging(
+                staging_progress,
+                staging_server_queue,
+                words_to_add,
+                &definition_fetcher,
+                staged_words,
+                connection_status,
+                words_with_no_definitions,
+                sync_report,
+            ).await;
+        });
+        self.staging_task = Some(staging_task);
+    }
+
+    /// Asks the providers for another spelling or the lemma of a word that
+    /// has no definition. The answer is picked up by
+    /// [`AppState::receive_alternate_lookups`].
+    pub fn look_up_alternate_spelling(&mut self, headword: String, spelling: String) {
+        let definition_fetcher = self.definition_fetcher.clone();
+        let alternate_lookups_tx = self.alternate_lookups_tx.clone();
+        self.async_rt.spawn(async move {
+            // A failed lookup is shown next to the word, it must not stop the staging
+            let lookup = definition_fetcher.get_word(&spelling, Arc::new(Mutex::new(true))).await;
+            let _ = alternate_lookups_tx.send((headword, spelling, lookup)).await;
+        });
+    }
+
+    pub fn receive_alternate_lookups(&mut self) {
+        // The spelling comes along, the one in the draft may have been edited since
+        while let Ok((headword, spelling, lookup)) = self.channels.alternate_lookups_rc.try_recv() {
+            let mut staged_words = self.staged_words.lock().unwrap();
+            // The plan may have been thrown away in the meantime
+            let Some(staged_word) = staged_words
+                .iter_mut()
+                .find(|staged_word| staged_word.kobo_word.headword == headword && !staged_word.has_definition()) else {
+                continue;
+            };
+            staged_word.draft.lookup_in_progress = false;
+            match lookup {
+                dictionary::Lookup::Found { word, provider_url } => {
+                    staged_word.set_definition(word, Some(provider_url));
+                    let mut words_with_no_definitions = self.words_with_no_definitions.lock().unwrap();
+                    *words_with_no_definitions = words_with_no_definitions.saturating_sub(1);
+                }
+                dictionary::Lookup::NotFound => {
+                    staged_word.draft.lookup_error = Some(format!("\"{}\" was not found either", spelling));
+                }
+                dictionary::Lookup::Failed(error) => {
+                    staged_word.draft.lookup_error = Some(format!("Lookup failed: {}", error));
+                }
+            }
+        }
+    }
+
+    pub fn staging_done(&self) -> bool {
+        self.phase == SyncPhase::Planning && self.staging_progress.lock().unwrap().clone() >= 100.0
+    }
+
+    pub fn start_sync(&mut self) {
+        if !self.staging_done() || self.selected_deck_name.is_none() {
+            return;
+        }
+        self.sync_started_at = Some(std::time::Instant::now());
+        self.phase = SyncPhase::Syncing;
+        let words_to_sync = approved_words(&self.staged_words.lock().unwrap());
+        self.sync_report.lock().unwrap().record_review(&self.staged_words.lock().unwrap());
+        let sync_progress = Arc::clone(&self.sync_progress);
+        let sync_error = Arc::clone(&self.sync_error);
+        let anki_client = self.anki_client.clone();
+        let deck_name = self.selected_deck_name.as_ref().unwrap().clone();
+        let processed_words = Arc::clone(&self.processed_words);
+        let sync_report = Arc::clone(&self.sync_report);
+        let known_words = self.known_words.clone();
+        let card_template = self.effective_settings().card_template;
+        self.session.last_deck_name = Some(deck_name.clone());
+
+        self.async_rt.spawn(async move {
+            AppState::sync_kobo_to_anki(
+                sync_progress,
+                sync_error,
+                words_to_sync,
+                known_words,
+                &anki_client,
+                &deck_name,
+                &card_template,
+                processed_words,
+                sync_report,
+            ).await;
+        });
+    }
+
+    /// Picks the profile, reader and deck of the previous session again and
+    /// fetches the definitions once everything is read. The sync still waits
+    /// on the review screen, the work is driven by
+    /// [`AppState::continue_requested_staging`] as the data becomes available.
+    pub fn request_staging_with_last_session(&mut self) {
+        if self.phase == SyncPhase::Syncing {
+            return;
+        }
+        // Selecting the profile overwrites the session
+        let session = self.session.clone();
+        let last_profile_name = session.last_profile_name
+            .filter(|profile_name| self.settings.profile(profile_name).is_some());
+        self.select_profile(last_profile_name);
+        if let Some(custom_kobo_path) = session.custom_kobo_path.filter(super::validate_kobo_path) {
+            self.custom_path = true;
+            self.select_kobo_path(Some(custom_kobo_path));
+        }
+        if let Some(last_deck_name) = session.last_deck_name {
+            if self.selected_deck_name.as_ref() != Some(&last_deck_name) {
+                self.change_deck(last_deck_name);
+            }
+        }
+        if matches!(self.phase, SyncPhase::Done | SyncPhase::Failed(_)) {
+            self.new_sync();
+        }
+        self.staging_requested = true;
+    }
+
+    pub fn continue_requested_staging(&mut self) {
+        if !self.staging_requested {
+            return;
+        }
+        match self.phase {
+            SyncPhase::Detecting => {}
+            SyncPhase::Ready => {
+                self.start_staging();
+                self.staging_requested = false;
+            }
+            SyncPhase::Planning | SyncPhase::Syncing | SyncPhase::Done | SyncPhase::Failed(_) => {
+                self.staging_requested = false;
+            }
+        }
+    }
+
+    pub async fn fetch_definitions_for_staging(
         progress: Arc<Mutex<f32>>,
-        words_to_add: Vec<String>,
-        anki_client: &anki::AnkiClient<'static>,
-        deck_name: &str,
+        server_queue: Arc<Mutex<Option<dictionary::ServerQueue>>>,
+        words_to_add: Vec<KoboWord>,
+        definition_fetcher: &dictionary::DefinitionFetcher,
+        staged_words: Arc<Mutex<Vec<StagedWord>>>,
         connection_status: Arc<Mutex<bool>>,
-        processed_words: Arc<Mutex<u32>>,
         words_with_no_definitions: Arc<Mutex<u32>>,
+        report: Arc<Mutex<SyncReport>>,
     ) {
+        let started_at = Instant::now();
         let word_count = words_to_add.len() as u32;
         let mut current_word_num = 0;
-        for word in words_to_add {
-            let current_word = word.clone();
+
+        // The first provider gets every word at once and answers as its
+        // fetches end, so the progress follows the provider's queue
+        let headwords = words_to_add
+            .iter()
+            .map(|word| word.headword.clone())
+            .collect::<Vec<String>>();
+        let streamed = definition_fetcher.stream_words(&headwords, |queue| {
+            // The lookups below still have to run, the staging is only done after them
+            let streamed_progress = 100.0 * queue.resolved as f32 / queue.total.max(1) as f32;
+            *progress.lock().unwrap() = streamed_progress.min(99.0);
+            *server_queue.lock().unwrap() = Some(queue);
+        }).await;
+        *server_queue.lock().unwrap() = None;
+        let streamed = match streamed {
+            Ok(streamed) => streamed,
+            Err(e) => {
+                println!("Could not stream the definitions, looking them up one by one: {:?}", e);
+                HashMap::new()
+            }
+        };
+
+        // `buffered` keeps at most `concurrency` lookups in flight and yields
+        // them in the original order
+        let mut definitions = futures::stream::iter(words_to_add)
+            .map(|word| {
+                let connection_status = connection_status.clone();
+                let streamed = &streamed;
+                async move {
+                    let mut word = word;
+                    let mut definition = definition_fetcher
+                        .get_streamed_word(&word.headword, streamed.get(&word.headword), connection_status.clone())
+                        .await;
+                    // The lemmatiser may have turned the word into something
+                    // no dictionary knows, e.g. "lens" into "len", the forms
+                    // it was looked up in are tried before giving up
+                    if matches!(definition, dictionary::Lookup::NotFound) {
+                        for lookup in word.lookups.clone() {
+                            if lookup == word.headword {
+                                continue;
+                            }
+                            let lookup_definition = definition_fetcher.get_word(&lookup, connection_status.clone()).await;
+                            if matches!(lookup_definition, dictionary::Lookup::NotFound) {
+                                continue;
+                            }
+                            println!("{:?} is not in the dictionary, using its form {:?}", word.headword, lookup);
+                            if matches!(lookup_definition, dictionary::Lookup::Found { .. }) {
+                                word.headword = lookup;
+                            }
+                            definition = lookup_definition;
+                            break;
+                        }
+                    }
+                    (word, definition)
+                }
+            })
+            .buffered(definition_fetcher.concurrency());
+
+        while let Some((word, definition)) = definitions.next().await {
             current_word_num += 1;
-            *processed_words.lock().unwrap() = current_word_num;
-            println!("Current word num: {}, word: {}", current_word_num, current_word);
-            println!("already_processed_words: {}, word_count: {}, current_word_num: {}", already_processed_words, word_count, current_word_num);
+            println!("Fetched definition {}/{}: {}", current_word_num, word_count, word.headword);
 
             if connection_status.lock().unwrap().clone() == false {
+                let mut report = report.lock().unwrap();
+                report.record_error(None, "Could not connect to the words provider".to_string());
+                report.record_fetch_time(started_at.elapsed());
                 return;
             }
-            let connection_status = connection_status.clone();
-            let word = anki_client.get_word_from_api(&word, connection_status.clone()).await;
-            if word.is_none() {
-                println!("No definition found for the word: {:?}", word);
-                let mut progress = progress.lock().unwrap();
-                *progress = {
-                    100.0 * current_word_num as f32 / (word_count) as f32
-                };
-                let mut words_with_no_definitions = words_with_no_definitions.lock().unwrap();
-                if connection_status.lock().unwrap().clone() == false {
+            if !matches!(definition, dictionary::Lookup::Found { .. }) {
+                println!("No definition found for the word: {:?}", word.headword);
+                *words_with_no_definitions.lock().unwrap() += 1;
+            }
+            let staged_word = StagedWord::new(word, definition);
+            let mut staged_words = staged_words.lock().unwrap();
+            let provider_headword = staged_word.provider_headword();
+            let same_headword = staged_words
+                .iter_mut()
+                .find(|other| provider_headword.is_some() && other.provider_headword() == provider_headword);
+            match same_headword {
+                Some(other) => {
+                    println!("{:?} has the same headword as {:?}", staged_word.kobo_word.headword, other.kobo_word.headword);
+                    other.kobo_word.merge(&staged_word.kobo_word);
+                }
+                None => staged_words.push(staged_word),
+            }
+            drop(staged_words);
+
+            // Words streamed before are staged at once, the bar must not go back
+            let mut progress = progress.lock().unwrap();
+            *progress = progress.max(100.0 * current_word_num as f32 / (word_count) as f32);
+        }
+        report.lock().unwrap().record_fetch_time(started_at.elapsed());
+        // An empty word list never enters the loop above
+        *progress.lock().unwrap() = 100.0;
+    }
+
+    /// Adds the approved words to the deck, or their forms to the note that
+    /// is already there. Everything that happens goes into the report.
+    pub async fn sync_kobo_to_anki(
+        progress: Arc<Mutex<f32>>,
+        sync_error: Arc<Mutex<Option<String>>>,
+        words_to_add: Vec<ApprovedWord>,
+        known_words: Vec<KoboWord>,
+        anki_client: &anki::AnkiClient,
+        deck_name: &str,
+        card_template: &anki::CardTemplate,
+        processed_words: Arc<Mutex<u32>>,
+        report: Arc<Mutex<SyncReport>>,
+    ) {
+        let started_at = Instant::now();
+        let fail = |word: Option<&str>, message: String| {
+            eprintln!("{}", message);
+            let mut report = report.lock().unwrap();
+            report.record_error(word, message.clone());
+            report.finish(started_at.elapsed());
+            *sync_error.lock().unwrap() = Some(message);
+        };
+
+        let mut deck_notes = match anki_client.get_deck_notes(deck_name, card_template).await {
+            Ok(deck_notes) => deck_notes,
+            Err(err) => {
+                fail(None, format!("Could not read the notes of \"{}\": {}", deck_name, err));
+                return;
+            }
+        };
+
+        // Words that already have a note may have been looked up in new forms
+        for kobo_word in &known_words {
+            let mut words = vec![kobo_word.headword.clone()];
+            words.extend(kobo_word.lookups.iter().cloned());
+            match record_lookups(anki_client, &mut deck_notes, card_template, &words, kobo_word).await {
+                Ok(RecordedLookups::Updated(note_word)) => report.lock().unwrap().record_updated(&note_word, kobo_word),
+                Ok(_) => report.lock().unwrap().record_duplicate(&kobo_word.headword),
+                Err(err) => {
+                    fail(Some(&kobo_word.headword), format!("Could not update the note of \"{}\": {}", kobo_word.headword, err));
                     return;
                 }
-                *words_with_no_definitions += 1;
-                continue;
             }
+        }
+
+        let word_count = words_to_add.len() as u32;
+        let mut current_word_num = 0;
+        for ApprovedWord { word, kobo_word, provider_url } in words_to_add {
+            current_word_num += 1;
+            println!("Current word num: {}, word: {}", current_word_num, word.word);
+
+            // The provider may return the headword of a word already in the deck
+            let words = vec![normalise_word(&word.word), kobo_word.headword.clone()];
+            let res = match record_lookups(anki_client, &mut deck_notes, card_template, &words, &kobo_word).await {
+                Ok(RecordedLookups::Updated(note_word)) => {
+                    report.lock().unwrap().record_updated(&note_word, &kobo_word);
+                    Ok(())
+                }
+                Ok(RecordedLookups::Unchanged) => {
+                    report.lock().unwrap().record_duplicate(&word.word);
+                    Ok(())
+                }
+                Ok(RecordedLookups::NoNote) => anki_client.add_card_with_fromating(
+                    &word,
+                    deck_name,
+                    card_template,
+                    &kobo_word.lookup_history(),
+                ).await.map(|added| {
+                    let mut report = report.lock().unwrap();
+                    if added {
+                        report.record_added(&word.word, &kobo_word, provider_url.as_deref());
+                    } else {
+                        report.record_duplicate(&word.word);
+                    }
+                }),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = res {
+                fail(Some(&word.word), format!("Could not add \"{}\" to Anki: {}", word.word, err));
+                return;
+            }
+            *processed_words.lock().unwrap() = current_word_num;
 
-            anki_client.add_card_with_fromating(
-                &word.unwrap().word,
-                deck_name,
-                connection_status
-            ).await;
-            //println!("{:?}", word);
             let mut progress = progress.lock().unwrap();
-            
             *progress = {
                 100.0 * current_word_num as f32 / (word_count) as f32
             };
         }
+        report.lock().unwrap().finish(started_at.elapsed());
+        *progress.lock().unwrap() = 100.0;
+    }
+}
+
+enum RecordedLookups {
+    /// The deck has no such note, or doesn't record lookups at all
+    NoNote,
+    Unchanged,
+    /// The note with this front got new forms or books
+    Updated(String),
+}
 
+/// Adds the forms and books of `kobo_word` to the note of one of `words`.
+async fn record_lookups(
+    anki_client: &anki::AnkiClient,
+    deck_notes: &mut anki::DeckNotes,
+    card_template: &anki::CardTemplate,
+    words: &[String],
+    kobo_word: &KoboWord,
+) -> anyhow::Result<RecordedLookups> {
+    let Some(note) = deck_notes.find_mut(words) else {
+        return Ok(RecordedLookups::NoNote);
+    };
+    if !note.lookup_history.merge(&kobo_word.lookup_history()) {
+        return Ok(RecordedLookups::Unchanged);
     }
+    println!("Adding the forms {:?} to the note of {:?}", kobo_word.lookups, note.word);
+    anki_client.update_lookup_history(note, card_template).await?;
+    Ok(RecordedLookups::Updated(note.word.clone()))
 }
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use eframe::egui::{
     self,
@@ -8,6 +8,9 @@ use eframe::egui::{
     TextFormat
 };
 
+use crate::anki::AnkiClient;
+use crate::dictionary::ServerQueue;
+use crate::sync_phase::SyncPhase;
 use crate::AppState;
 
 pub fn draw_header(
@@ -23,6 +26,26 @@ pub fn draw_header(
 }
 
 
+pub fn display_profile_selection(app_state: &mut AppState, ui: &mut egui::Ui) {
+    if app_state.settings.profiles.is_empty() {
+        return;
+    }
+    let mut selected = app_state.selected_profile_name.clone();
+    ui.vertical_centered(|ui| {
+        ui.add_enabled_ui(app_state.phase != SyncPhase::Syncing, |ui| {
+            egui::ComboBox::from_label("Sync profile")
+                .selected_text(selected.clone().unwrap_or("No profile".to_string()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "No profile");
+                    for profile in &app_state.settings.profiles {
+                        ui.selectable_value(&mut selected, Some(profile.name.clone()), &profile.name);
+                    }
+                });
+        });
+    });
+    app_state.select_profile(selected);
+}
+
 pub fn draw_kobo_connection_information_message(app_state: &AppState, ui: &mut egui::Ui) {
 
     ui.vertical_centered(|ui| {
@@ -72,10 +95,31 @@ pub fn draw_kobo_connection_status_message_when_device_is_detected(app_state: &m
     ui.vertical_centered(|ui| {
         ui.label(job);
 
+        if app_state.kobo_devices.len() > 1 {
+            draw_kobo_device_selection(app_state, ui);
+        }
         draw_file_picker_button(app_state, ui);
     });
 }
 
+fn draw_kobo_device_selection(app_state: &mut AppState, ui: &mut egui::Ui) {
+    let mut selected = app_state.kobo_path.clone();
+    ui.label(RichText::new(format!("{} Kobo readers connected", app_state.kobo_devices.len()))
+        .color(Color32::GOLD)
+        .font(FontId::new(16.0, FontFamily::Proportional)));
+    egui::ComboBox::from_id_salt("kobo_device_selection")
+        .selected_text(selected.as_ref().map(|path| path.display().to_string()).unwrap_or_default())
+        .show_ui(ui, |ui| {
+            for device in &app_state.kobo_devices {
+                ui.selectable_value(&mut selected, Some(device.clone()), device.display().to_string());
+            }
+        });
+    if selected != app_state.kobo_path {
+        app_state.custom_path = false;
+        app_state.select_kobo_path(selected);
+    }
+}
+
 fn draw_file_picker_button(app_state: &mut AppState, ui: &mut egui::Ui) {
     if ui.button(
         RichText::new("Select/Change Path")
@@ -97,6 +141,35 @@ pub fn notify_user_about_invalid_kobo_path(ui: &mut egui::Ui) {
     });
 }
 
+pub fn notify_user_about_kobo_db_error(app_state: &mut AppState, ui: &mut egui::Ui) {
+    ui.vertical_centered(|ui| {
+        ui.label(RichText::new(format!(
+            "Could not read the Kobo database: {}",
+            app_state.kobo_db_error.as_ref().unwrap()
+        ))
+            .color(Color32::LIGHT_RED)
+            .font(FontId::new(20.0, FontFamily::Proportional)));
+        if ui.button(
+            RichText::new("Try again")
+                .color(Color32::LIGHT_BLUE)
+                .font(FontId::new(20.0, FontFamily::Proportional))
+        ).clicked() {
+            app_state.kobo_db_error = None;
+        }
+    });
+}
+
+pub fn show_loading_message(text: &str, ui: &mut egui::Ui) {
+    ui.vertical_centered(|ui| {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(RichText::new(text)
+                .color(Color32::GOLD)
+                .font(FontId::new(20.0, FontFamily::Proportional)));
+        });
+    });
+}
+
 pub fn display_anki_connection_status_message(app_state: &AppState, ui: &mut egui::Ui) {
     ui.vertical_centered(|ui| {
         let text: String;
@@ -167,15 +240,11 @@ pub fn display_deck_selection_dropdown(app_state: &mut AppState, ui: &mut egui::
     // if app_state.selected_deck_name.is_some() {
     //     return;
     // }
-    if app_state.deck_names.is_none() {
-        let anki_client = app_state.anki_client.clone();
-        let decks = app_state.async_rt.block_on(async move {
-            return anki_client.get_decks().await;
-            //app_state.deck_names = Some(decks);
-        });
-        app_state.deck_names = Some(decks);
-    }
-    let deck_names = app_state.deck_names.as_ref().unwrap();
+    AnkiClient::load_deck_names_non_blocking(app_state);
+    let Some(deck_names) = app_state.deck_names.as_ref() else {
+        show_loading_message("Loading decks from Anki...", ui);
+        return;
+    };
     let mut selected = String::new();
     ui.vertical_centered(|ui| {
         ui.label(RichText::new("Select a deck")
@@ -193,8 +262,8 @@ pub fn display_deck_selection_dropdown(app_state: &mut AppState, ui: &mut egui::
                 });
             });
     });
-    if selected != "" {
-        app_state.selected_deck_name = Some(selected);
+    if selected != "" && app_state.selected_deck_name.as_ref() != Some(&selected) {
+        app_state.change_deck(selected);
     }
     //println!("{:?}", selected);
 }
@@ -206,7 +275,7 @@ pub fn display_new_words_count(
     already_added_words_count: usize,
     all_words_in_kobo_count: usize,
     words_with_no_definitions: usize,
-
+    ignored_words_count: usize,
 ) {
     let already_added_words_count = already_added_words_count + app_state.processed_words.lock().unwrap().clone() as usize;
     let new_words_count = new_words_count - app_state.processed_words.lock().unwrap().clone() as usize;
@@ -215,54 +284,89 @@ pub fn display_new_words_count(
     let already_added_words_count_layout = generate_layout_for_display_new_words_count("Words already added: ", already_added_words_count.to_string().as_str(), Color32::LIGHT_BLUE);
     let all_words_in_kobo_count_layout = generate_layout_for_display_new_words_count("All words in Kobo: ", all_words_in_kobo_count.to_string().as_str(), Color32::RED);
     let words_with_no_definitions_layout = generate_layout_for_display_new_words_count("Words with no definitions: ", words_with_no_definitions.to_string().as_str(), Color32::LIGHT_RED);
+    let ignored_words_count_layout = generate_layout_for_display_new_words_count("Ignored words: ", ignored_words_count.to_string().as_str(), Color32::GRAY);
 
     ui.vertical_centered(|ui| {
         ui.label(new_words_count_layout);
         ui.label(already_added_words_count_layout);
         ui.label(all_words_in_kobo_count_layout);
         ui.label(words_with_no_definitions_layout);
+        ui.label(ignored_words_count_layout);
+    });
+}
+
+pub fn display_ignore_list_controls(app_state: &mut AppState, ui: &mut egui::Ui) {
+    let mut ignore_list_changed = false;
+    ui.vertical_centered(|ui| {
+        ui.horizontal_wrapped(|ui| {
+            if ui.button(
+                RichText::new("Import stop-word list")
+                    .color(Color32::LIGHT_BLUE)
+            ).clicked() {
+                app_state.stop_words_file_dialog.select_file();
+            }
+            let stop_words_count = app_state.ignore_list.stop_words_count();
+            if ui.add_enabled(
+                stop_words_count > 0,
+                egui::Button::new(format!("Clear stop-words ({})", stop_words_count))
+            ).clicked() {
+                if let Err(err) = app_state.ignore_list.clear_stop_words() {
+                    eprintln!("Could not save the ignore list: {:?}", err);
+                }
+                ignore_list_changed = true;
+            }
+            let ignored_words_count = app_state.ignore_list.ignored_words_count();
+            if ui.add_enabled(
+                ignored_words_count > 0,
+                egui::Button::new(format!("Clear ignored words ({})", ignored_words_count))
+            ).clicked() {
+                if let Err(err) = app_state.ignore_list.clear_ignored_words() {
+                    eprintln!("Could not save the ignore list: {:?}", err);
+                }
+                ignore_list_changed = true;
+            }
+        });
+    });
+    if ignore_list_changed {
+        app_state.words_to_add = None;
+    }
+}
+
+pub fn display_definition_cache_controls(app_state: &mut AppState, ui: &mut egui::Ui) {
+    let Some(definition_cache) = app_state.definition_fetcher.definition_cache() else {
+        return;
+    };
+    let cached_definitions_count = definition_cache.len().unwrap_or(0);
+    ui.vertical_centered(|ui| {
+        if ui.add_enabled(
+            cached_definitions_count > 0,
+            egui::Button::new(format!("Clear definition cache ({} cached)", cached_definitions_count))
+        ).on_hover_text("Definitions are kept locally so re-syncs don't fetch them again").clicked() {
+            if let Err(err) = definition_cache.clear() {
+                eprintln!("Could not clear the definition cache: {:?}", err);
+            }
+        }
     });
 }
 
 pub fn display_start_button(app_state: &mut AppState, ui: &mut egui::Ui) {
     ui.vertical_centered(|ui| {
         if ui.button(
-            RichText::new("Start Sync")
+            RichText::new("Fetch Definitions")
                 .color(Color32::LIGHT_BLUE)
                 .font(FontId::new(20.0, FontFamily::Proportional))
         ).clicked() {
-            if app_state.sync_started {
-                return;
-            }
-            app_state.sync_started_at = Some(std::time::Instant::now());
-            app_state.sync_started = true;
-            let sync_progress = Arc::clone(&app_state.sync_progress);
-            let words_to_add = app_state.words_to_add.as_ref().unwrap().clone();
-            let anki_client = app_state.anki_client.clone();
-            let connection_status = Arc::clone(&app_state.server_connection_status);
-            let deck_name = app_state.selected_deck_name.as_ref().unwrap().clone();
-            let processed_words = Arc::clone(&app_state.processed_words);
-            let kobo_words = app_state.prepared_words_from_kobo.as_ref().unwrap();
-
-            let new_words = app_state.words_to_add.as_ref().unwrap();
-            let new_words_count = new_words.len();
-            let already_added_words_count = kobo_words.len() - new_words_count;
-
-            let words_with_no_definitions = Arc::clone(&app_state.words_with_no_definitions);
-
-            app_state.async_rt.spawn(async move {
-                AppState::sync_kobo_to_anki(
-                    already_added_words_count as u32,
-                    sync_progress,
-                    words_to_add,
-                    &anki_client,
-                    &deck_name,
-                    connection_status,
-                    processed_words,
-                    words_with_no_definitions,
-                ).await;
-            });
+            app_state.start_staging();
+        }
 
+        if let Some(last_deck_name) = app_state.session.last_deck_name.clone() {
+            if ui.button(
+                RichText::new("Sync again with the same settings")
+                    .color(Color32::LIGHT_GREEN)
+                    .font(FontId::new(16.0, FontFamily::Proportional))
+            ).on_hover_text(format!("Picks \"{}\" and the reader and profile of the last sync again and fetches the definitions for review", last_deck_name)).clicked() {
+                app_state.request_staging_with_last_session();
+            }
         }
     });
 }
@@ -275,26 +379,49 @@ pub fn show_sync_started_message(ui: &mut egui::Ui) {
     });
 }
 
-pub fn show_server_connection_error_message(ui: &mut egui::Ui) {
+pub fn show_sync_failed_message(err: &str, ui: &mut egui::Ui) {
     ui.vertical_centered(|ui| {
-        ui.label(RichText::new("Could not connect to the server!")
+        ui.label(RichText::new(err)
             .color(Color32::LIGHT_RED)
             .font(FontId::new(20.0, FontFamily::Proportional)));
     });
 }
 
-pub fn show_eta_message(app_state: &AppState, ui: &mut egui::Ui) {
-    let sync_started_at = app_state.sync_started_at.unwrap();
-    let elapsed = sync_started_at.elapsed();
+pub fn display_rescan_button(app_state: &mut AppState, ui: &mut egui::Ui) {
+    ui.vertical_centered(|ui| {
+        if ui.button(
+            RichText::new("Rescan")
+                .color(Color32::LIGHT_BLUE)
+                .font(FontId::new(16.0, FontFamily::Proportional))
+        ).on_hover_text("Read the decks, the selected deck and the Kobo database again").clicked() {
+            app_state.rescan();
+        }
+    });
+}
+
+pub fn display_new_sync_button(app_state: &mut AppState, ui: &mut egui::Ui) {
+    ui.vertical_centered(|ui| {
+        if ui.button(
+            RichText::new("New sync")
+                .color(Color32::LIGHT_BLUE)
+                .font(FontId::new(20.0, FontFamily::Proportional))
+        ).clicked() {
+            app_state.new_sync();
+        }
+    });
+}
+
+pub fn show_eta_message(started_at: std::time::Instant, progress: &Arc<Mutex<f32>>, ui: &mut egui::Ui) {
+    let elapsed = started_at.elapsed();
     let eta = {
         let elapsed = elapsed.as_secs();
 
-        let avg_time_per_unit = app_state.sync_progress.lock().unwrap().clone() as f64 / elapsed as f64;
-        if app_state.sync_progress.lock().unwrap().clone() == 0.0 || app_state.sync_progress.lock().unwrap().clone() >= 100.0 {
+        let progress = progress.lock().unwrap().clone();
+        let avg_time_per_unit = progress as f64 / elapsed as f64;
+        if progress == 0.0 || progress >= 100.0 {
             return;
         }
-        //println!("{}", app_state.sync_progress.lock().unwrap().clone());
-        let remaining = 100.0 - app_state.sync_progress.lock().unwrap().clone();
+        let remaining = 100.0 - progress;
         let eta = remaining as f64 / avg_time_per_unit;
         let hours = eta / 3600.0;
         let minutes = (eta % 3600.0) / 60.0;
@@ -315,11 +442,40 @@ pub fn show_eta_message(app_state: &AppState, ui: &mut egui::Ui) {
     });
 }
 
-pub fn show_progress_bar(app_state: &AppState, ui: &mut egui::Ui) {
-    let progress = app_state.sync_progress.lock().unwrap();
+/// Shows where the words are in the provider's fetch queue while they
+/// stream, returns `false` when the provider sent nothing to show.
+pub fn show_server_queue_message(server_queue: &Arc<Mutex<Option<ServerQueue>>>, ui: &mut egui::Ui) -> bool {
+    let Some(server_queue) = *server_queue.lock().unwrap() else {
+        return false;
+    };
+    if server_queue.waiting == 0 {
+        return false;
+    }
+    let message = if server_queue.position == 0 {
+        format!("{} words left, being fetched now", server_queue.waiting)
+    } else {
+        format!("{} words left, {} ahead of them", server_queue.waiting, server_queue.position)
+    };
+    let queue_layout = generate_labelled_layout("Provider queue: ", &message);
+    ui.vertical_centered(|ui| {
+        ui.label(queue_layout);
+    });
+    true
+}
+
+pub fn show_progress_bar(progress: &Arc<Mutex<f32>>, ui: &mut egui::Ui) {
+    let progress = progress.lock().unwrap();
     ui.add(egui::ProgressBar::new(*progress as f32 / 100.0).animate(true));
 }
 
+pub fn show_fetching_definitions_message(ui: &mut egui::Ui) {
+    ui.vertical_centered(|ui| {
+        ui.label(RichText::new("Fetching definitions...")
+            .color(Color32::GOLD)
+            .font(FontId::new(20.0, FontFamily::Proportional)));
+    });
+}
+
 pub fn show_done_message(ui: &mut egui::Ui) {
     ui.vertical_centered(|ui| {
         ui.label(RichText::new("Sync completed!")
@@ -330,10 +486,17 @@ pub fn show_done_message(ui: &mut egui::Ui) {
 
 fn generate_layout_for_eta_message(
     eta: &str,
+) -> LayoutJob {
+    generate_labelled_layout("ETA: ", eta)
+}
+
+fn generate_labelled_layout(
+    label: &str,
+    value: &str,
 ) -> LayoutJob {
     let mut job = LayoutJob::default();
     job.append(
-        "ETA: ",
+        label,
         0.0,
         TextFormat {
             font_id: FontId::new(20.0, FontFamily::Proportional),
@@ -342,7 +505,7 @@ fn generate_layout_for_eta_message(
         },
     );
     job.append(
-        eta,
+        value,
         0.0,
         TextFormat {
             font_id: FontId::new(20.0, FontFamily::Proportional),
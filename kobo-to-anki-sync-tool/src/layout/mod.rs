@@ -1,6 +1,12 @@
 mod app_layout;
 mod fonts_setup;
+mod report_layout;
+mod staging_layout;
+mod settings_layout;
 
 pub(super) use app_layout::*;
+pub(super) use report_layout::*;
+pub(super) use staging_layout::*;
+pub(super) use settings_layout::*;
 
 pub(super) use fonts_setup::setup_custom_fonts;
\ No newline at end of file
@@ -0,0 +1,56 @@
+use eframe::egui::{
+    self,
+    Color32, RichText,
+};
+
+use crate::report::ReportFormat;
+use crate::AppState;
+
+/// The report of the sync that just finished, with buttons to save it.
+pub fn display_sync_report(app_state: &mut AppState, ui: &mut egui::Ui) {
+    let sync_report = app_state.sync_report.lock().unwrap().clone();
+    if sync_report.is_empty() {
+        return;
+    }
+    ui.add_space(10.0);
+    egui::CollapsingHeader::new("Sync report")
+        .default_open(true)
+        .show(ui, |ui| {
+            for line in sync_report.summary() {
+                ui.label(line);
+            }
+            ui.horizontal(|ui| {
+                for format in [ReportFormat::Json, ReportFormat::Html] {
+                    if ui.button(format!("Save as {}", format.extension().to_uppercase())).clicked() {
+                        app_state.report_export_format = Some(format);
+                        app_state.report_file_dialog.save_file();
+                    }
+                }
+            });
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    for error in &sync_report.errors {
+                        let text = match &error.word {
+                            Some(word) => format!("{}: {}", word, error.message),
+                            None => error.message.clone(),
+                        };
+                        ui.label(RichText::new(text).color(Color32::LIGHT_RED));
+                    }
+                    for missing in &sync_report.without_definition {
+                        ui.label(RichText::new(format!("{}: {}", missing.word, missing.reason))
+                            .color(Color32::LIGHT_RED));
+                    }
+                    for updated in &sync_report.updated {
+                        ui.label(RichText::new(format!("{}: new forms {}", updated.word, updated.forms.join(", ")))
+                            .color(Color32::LIGHT_BLUE));
+                    }
+                    for added in &sync_report.added {
+                        ui.label(RichText::new(format!("{}: added", added.word))
+                            .color(Color32::GREEN));
+                    }
+                });
+        });
+}
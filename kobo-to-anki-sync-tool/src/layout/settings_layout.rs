@@ -0,0 +1,345 @@
+use eframe::egui::{
+    self,
+    Color32, FontFamily,
+    FontId, RichText,
+};
+
+use crate::kobo::read_kobo_serial;
+use crate::anki::CardTemplate;
+use crate::cards::Language;
+use crate::settings::{Settings, SyncProfile};
+use crate::AppState;
+
+pub fn draw_settings_button(app_state: &mut AppState, ui: &mut egui::Ui) {
+    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+        if ui.button(
+            RichText::new("Settings")
+                .color(Color32::LIGHT_BLUE)
+        ).clicked() {
+            app_state.settings_draft = app_state.settings.clone();
+            app_state.settings_error = None;
+            app_state.settings_window_open = true;
+        }
+    });
+}
+
+pub fn draw_settings_window(app_state: &mut AppState, ctx: &egui::Context) {
+    if !app_state.settings_window_open {
+        return;
+    }
+    let mut window_open = true;
+    let mut save_clicked = false;
+    let mut cancel_clicked = false;
+
+    egui::Window::new("Settings")
+        .open(&mut window_open)
+        .resizable(true)
+        .default_width(600.0)
+        .show(ctx, |ui| {
+            if app_state.cli_args.has_overrides() {
+                ui.label(RichText::new("Some settings are overridden from the command line or the environment for this run.")
+                    .color(Color32::GOLD));
+                ui.add_space(5.0);
+            }
+            let connected_serials = app_state.kobo_devices
+                .iter()
+                .filter_map(read_kobo_serial)
+                .collect::<Vec<String>>();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                draw_settings_form(&mut app_state.settings_draft, &connected_serials, ui);
+            });
+            ui.separator();
+            if let Some(settings_error) = &app_state.settings_error {
+                ui.label(RichText::new(settings_error).color(Color32::LIGHT_RED));
+            }
+            ui.horizontal(|ui| {
+                if ui.button(RichText::new("Save").color(Color32::GREEN)).clicked() {
+                    save_clicked = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    cancel_clicked = true;
+                }
+                if ui.button("Reset to defaults").clicked() {
+                    app_state.settings_draft = Settings::default();
+                }
+            });
+            ui.label(RichText::new(format!("Settings file: {}", app_state.settings_path.display()))
+                .color(Color32::GRAY)
+                .small());
+        });
+
+    if save_clicked {
+        let mut settings = app_state.settings_draft.clone();
+        settings.provider_urls = settings.provider_urls
+            .iter()
+            .map(|provider_url| provider_url.trim().to_string())
+            .filter(|provider_url| !provider_url.is_empty())
+            .collect();
+        for profile in settings.profiles.iter_mut() {
+            profile.name = profile.name.trim().to_string();
+        }
+        match app_state.apply_settings(settings) {
+            Ok(_) => {
+                app_state.settings_error = None;
+                window_open = false;
+            }
+            Err(err) => {
+                eprintln!("Could not save the settings: {:?}", err);
+                app_state.settings_error = Some(format!("Could not save the settings: {:#}", err));
+            }
+        }
+    }
+    if cancel_clicked {
+        window_open = false;
+    }
+    app_state.settings_window_open = window_open;
+}
+
+fn draw_settings_form(settings: &mut Settings, connected_serials: &[String], ui: &mut egui::Ui) {
+    section_label(ui, "Words providers (one URL per line, tried in order)");
+    let mut provider_urls = settings.provider_urls.join("\n");
+    if ui.add(
+        egui::TextEdit::multiline(&mut provider_urls)
+            .desired_rows(2)
+            .desired_width(f32::INFINITY)
+    ).changed() {
+        settings.provider_urls = provider_urls
+            .split('\n')
+            .map(|provider_url| provider_url.to_string())
+            .collect();
+    }
+
+    section_label(ui, "AnkiConnect");
+    egui::Grid::new("anki_connect_settings")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("URL:");
+            ui.add(egui::TextEdit::singleline(&mut settings.anki_connect_url).desired_width(f32::INFINITY));
+            ui.end_row();
+
+            ui.label("API key:");
+            let mut anki_connect_key = settings.anki_connect_key.clone().unwrap_or_default();
+            if ui.add(
+                egui::TextEdit::singleline(&mut anki_connect_key)
+                    .password(true)
+                    .desired_width(f32::INFINITY)
+            ).changed() {
+                settings.anki_connect_key = Some(anki_connect_key).filter(|key| !key.is_empty());
+            }
+            ui.end_row();
+
+            ui.label("Default deck:");
+            let mut default_deck = settings.default_deck.clone().unwrap_or_default();
+            if ui.add(egui::TextEdit::singleline(&mut default_deck).desired_width(f32::INFINITY)).changed() {
+                settings.default_deck = Some(default_deck).filter(|deck| !deck.is_empty());
+            }
+            ui.end_row();
+
+            ui.label("Lemmatisation:");
+            draw_lemmatisation_selection(&mut settings.lemmatisation, "lemmatisation", "Off", ui);
+            ui.end_row();
+
+            ui.label("Concurrent lookups:");
+            ui.add(egui::DragValue::new(&mut settings.concurrency).range(1..=16));
+            ui.end_row();
+        });
+
+    section_label(ui, "Card template");
+    draw_card_template_form(&mut settings.card_template, "card_template", ui);
+
+    draw_watch_settings(settings, connected_serials, ui);
+    draw_profiles_settings(settings, connected_serials, ui);
+}
+
+/// Picks the language used to turn looked up forms such as "running" into
+/// their headword "run".
+fn draw_lemmatisation_selection(language: &mut Option<Language>, id_salt: &str, none_label: &str, ui: &mut egui::Ui) {
+    egui::ComboBox::from_id_salt(id_salt)
+        .selected_text(language.map(|language| language.name()).unwrap_or(none_label))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(language, None, none_label);
+            for option in Language::ALL {
+                ui.selectable_value(language, Some(option), option.name());
+            }
+        });
+}
+
+fn draw_card_template_form(card_template: &mut CardTemplate, id_salt: &str, ui: &mut egui::Ui) {
+    egui::Grid::new(format!("{}_note_type", id_salt))
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (label, value) in [
+                ("Note type:", &mut card_template.note_type),
+                ("Front field:", &mut card_template.front_field),
+                ("Back field:", &mut card_template.back_field),
+            ] {
+                ui.label(label);
+                ui.add(egui::TextEdit::singleline(value).desired_width(f32::INFINITY));
+                ui.end_row();
+            }
+            for (label, value) in [
+                ("Forms field:", &mut card_template.forms_field),
+                ("Books field:", &mut card_template.books_field),
+            ] {
+                ui.label(label);
+                let mut field_name = value.clone().unwrap_or_default();
+                if ui.add(
+                    egui::TextEdit::singleline(&mut field_name)
+                        .hint_text("not recorded")
+                        .desired_width(f32::INFINITY)
+                ).changed() {
+                    *value = Some(field_name.trim().to_string()).filter(|field_name| !field_name.is_empty());
+                }
+                ui.end_row();
+            }
+        });
+    ui.label(RichText::new("Front uses {word}, back uses {meanings}, a meaning uses {meaning} and {examples}, an example uses {example}.")
+        .color(Color32::GRAY)
+        .small());
+    ui.label(RichText::new("Front and back can use {ipa_uk} and {ipa_us}, a meaning can use {details} or {part_of_speech}, {guide_word}, {cefr_level}, {grammar}, {labels} and {register}.")
+        .color(Color32::GRAY)
+        .small());
+    for (label, template) in [
+        ("Front", &mut card_template.front),
+        ("Back", &mut card_template.back),
+        ("Meaning", &mut card_template.meaning),
+        ("Example", &mut card_template.example),
+    ] {
+        ui.label(label);
+        ui.add(
+            egui::TextEdit::multiline(template)
+                .code_editor()
+                .desired_rows(2)
+                .desired_width(f32::INFINITY)
+        );
+    }
+}
+
+fn draw_profiles_settings(settings: &mut Settings, connected_serials: &[String], ui: &mut egui::Ui) {
+    section_label(ui, "Sync profiles");
+    ui.label(RichText::new("Empty fields fall back to the settings above.")
+        .color(Color32::GRAY)
+        .small());
+    let mut removed_profile = None;
+    for (index, profile) in settings.profiles.iter_mut().enumerate() {
+        egui::CollapsingHeader::new(format!("Profile: {}", profile.name))
+            .id_salt(("sync_profile", index))
+            .show(ui, |ui| {
+                egui::Grid::new(("sync_profile_grid", index))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.add(egui::TextEdit::singleline(&mut profile.name).desired_width(f32::INFINITY));
+                        ui.end_row();
+
+                        for (label, value, hint) in [
+                            ("Reader serial:", &mut profile.serial, "any reader"),
+                            ("Dictionary (DictSuffix):", &mut profile.dict_suffix, "e.g. -de, any dictionary when empty"),
+                            ("Book:", &mut profile.book_filter, "part of the title or file name"),
+                            ("Deck:", &mut profile.deck, "default deck"),
+                        ] {
+                            ui.label(label);
+                            let mut text = value.clone().unwrap_or_default();
+                            if ui.add(
+                                egui::TextEdit::singleline(&mut text)
+                                    .hint_text(hint)
+                                    .desired_width(f32::INFINITY)
+                            ).changed() {
+                                *value = Some(text.trim().to_string()).filter(|text| !text.is_empty());
+                            }
+                            ui.end_row();
+                        }
+
+                        ui.label("Lemmatisation:");
+                        draw_lemmatisation_selection(&mut profile.lemmatisation, &format!("sync_profile_lemmatisation_{}", index), "same as above", ui);
+                        ui.end_row();
+
+                        ui.label("Words providers:");
+                        let mut provider_urls = profile.provider_urls.join("\n");
+                        if ui.add(
+                            egui::TextEdit::multiline(&mut provider_urls)
+                                .hint_text("same as above")
+                                .desired_rows(1)
+                                .desired_width(f32::INFINITY)
+                        ).changed() {
+                            profile.provider_urls = provider_urls
+                                .split('\n')
+                                .map(|provider_url| provider_url.trim().to_string())
+                                .filter(|provider_url| !provider_url.is_empty())
+                                .collect();
+                        }
+                        ui.end_row();
+                    });
+
+                if profile.serial.is_none() {
+                    for serial in connected_serials {
+                        if ui.button(format!("Use connected reader {}", serial)).clicked() {
+                            profile.serial = Some(serial.clone());
+                        }
+                    }
+                }
+
+                let mut own_card_template = profile.card_template.is_some();
+                if ui.checkbox(&mut own_card_template, "Own card template and note type").changed() {
+                    profile.card_template = own_card_template.then(CardTemplate::default);
+                }
+                if let Some(card_template) = profile.card_template.as_mut() {
+                    draw_card_template_form(card_template, &format!("sync_profile_{}", index), ui);
+                }
+
+                if ui.button(RichText::new("Remove profile").color(Color32::LIGHT_RED)).clicked() {
+                    removed_profile = Some(index);
+                }
+            });
+    }
+    if let Some(index) = removed_profile {
+        settings.profiles.remove(index);
+    }
+    if ui.button("Add profile").clicked() {
+        let name = format!("Profile {}", settings.profiles.len() + 1);
+        settings.profiles.push(SyncProfile::new(&name));
+    }
+}
+
+fn draw_watch_settings(settings: &mut Settings, connected_serials: &[String], ui: &mut egui::Ui) {
+    section_label(ui, "Watch mode (--watch)");
+    ui.label(RichText::new("Readers synced automatically to the default deck when they are connected, one serial number per line.")
+        .color(Color32::GRAY)
+        .small());
+    let watch = &mut settings.watch;
+    let mut known_serials = watch.known_serials.join("\n");
+    if ui.add(
+        egui::TextEdit::multiline(&mut known_serials)
+            .desired_rows(2)
+            .desired_width(f32::INFINITY)
+    ).changed() {
+        watch.known_serials = known_serials
+            .split('\n')
+            .map(|serial| serial.trim().to_string())
+            .filter(|serial| !serial.is_empty())
+            .collect();
+    }
+    for serial in connected_serials {
+        if watch.known_serials.contains(serial) {
+            continue;
+        }
+        if ui.button(format!("Add connected reader {}", serial)).clicked() {
+            watch.known_serials.push(serial.clone());
+        }
+    }
+
+    ui.label("Hook command:");
+    let mut hook_command = watch.hook_command.clone().unwrap_or_default();
+    if ui.add(egui::TextEdit::singleline(&mut hook_command).desired_width(f32::INFINITY))
+        .on_hover_text("Run after every automatic sync with KOBO_TO_ANKI_STATUS, KOBO_TO_ANKI_SERIAL, KOBO_TO_ANKI_DECK, KOBO_TO_ANKI_ADDED_WORDS and KOBO_TO_ANKI_ERROR set")
+        .changed() {
+        watch.hook_command = Some(hook_command).filter(|command| !command.trim().is_empty());
+    }
+}
+
+fn section_label(ui: &mut egui::Ui, text: &str) {
+    ui.add_space(8.0);
+    ui.label(RichText::new(text)
+        .color(Color32::WHITE)
+        .font(FontId::new(18.0, FontFamily::Proportional)));
+}
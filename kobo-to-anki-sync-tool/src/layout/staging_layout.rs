@@ -0,0 +1,365 @@
+use eframe::egui::{
+    self,
+    Color32, FontFamily,
+    FontId, RichText,
+};
+
+use crate::anki::Word;
+use crate::cards::{approved_words, KoboWord, StagedWord};
+use crate::AppState;
+
+pub fn display_staging_table(app_state: &mut AppState, ui: &mut egui::Ui) {
+    let mut staged_words = app_state.staged_words.lock().unwrap();
+    let mut word_to_ignore = None;
+    ui.vertical_centered(|ui| {
+        ui.label(RichText::new("Review the words before they are sent to Anki")
+            .color(Color32::WHITE)
+            .font(FontId::new(20.0, FontFamily::Proportional)));
+        ui.horizontal(|ui| {
+            if ui.button("Select all").clicked() {
+                staged_words
+                    .iter_mut()
+                    .filter(|staged_word| staged_word.has_definition())
+                    .for_each(|staged_word| staged_word.include = true);
+            }
+            if ui.button("Deselect all").clicked() {
+                staged_words
+                    .iter_mut()
+                    .for_each(|staged_word| staged_word.include = false);
+            }
+            ui.checkbox(&mut app_state.session.hide_words_without_definitions, "Hide words without definitions");
+        });
+    });
+    let hide_words_without_definitions = app_state.session.hide_words_without_definitions;
+
+    egui::ScrollArea::vertical()
+        .max_height(ui.available_height() - 60.0)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            for (index, staged_word) in staged_words.iter_mut().enumerate() {
+                if hide_words_without_definitions && !staged_word.has_definition() {
+                    continue;
+                }
+                ui.push_id(index, |ui| {
+                    if draw_staged_word_row(staged_word, ui) {
+                        word_to_ignore = Some(index);
+                    }
+                });
+                ui.separator();
+            }
+        });
+
+    if let Some(index) = word_to_ignore {
+        let staged_word = staged_words.remove(index);
+        let KoboWord { headword, lookups, .. } = &staged_word.kobo_word;
+        // Forms merged in by the provider's headword have their own headword
+        for word in std::iter::once(headword).chain(lookups) {
+            if let Err(err) = app_state.ignore_list.ignore(word) {
+                eprintln!("Could not save the ignore list: {:?}", err);
+            }
+        }
+        if let Some(words_to_add) = app_state.words_to_add.as_mut() {
+            words_to_add.retain(|word| !word.lookups.iter().any(|lookup| lookups.contains(lookup)));
+        }
+        if !staged_word.has_definition() {
+            let mut words_with_no_definitions = app_state.words_with_no_definitions.lock().unwrap();
+            *words_with_no_definitions = words_with_no_definitions.saturating_sub(1);
+        }
+        app_state.ignored_words_count += staged_word.kobo_word.lookups.len();
+    }
+}
+
+/// Words the providers couldn't define, where the user can type a definition
+/// or try another spelling. Resolved words join the review table.
+pub fn display_needs_attention_list(app_state: &mut AppState, ui: &mut egui::Ui) {
+    let mut staged_words = app_state.staged_words.lock().unwrap();
+    let needs_attention_count = staged_words
+        .iter()
+        .filter(|staged_word| !staged_word.has_definition())
+        .count();
+    if needs_attention_count == 0 {
+        return;
+    }
+    let mut alternate_lookups = vec![];
+    let mut resolved_count = 0;
+    egui::CollapsingHeader::new(RichText::new(format!("Needs attention ({})", needs_attention_count))
+            .color(Color32::LIGHT_RED))
+        .id_salt("needs_attention")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt("needs_attention_scroll")
+                .max_height(250.0)
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    for (index, staged_word) in staged_words.iter_mut().enumerate() {
+                        if staged_word.has_definition() {
+                            continue;
+                        }
+                        ui.push_id(index, |ui| {
+                            match draw_needs_attention_row(staged_word, ui) {
+                                Some(AttentionAction::LookUp(spelling)) => {
+                                    staged_word.draft.lookup_in_progress = true;
+                                    staged_word.draft.lookup_error = None;
+                                    alternate_lookups.push((staged_word.kobo_word.headword.clone(), spelling));
+                                }
+                                Some(AttentionAction::UseDraft) => {
+                                    if let Some(word) = staged_word.draft.to_word(&staged_word.kobo_word.headword) {
+                                        staged_word.set_definition(word, None);
+                                        resolved_count += 1;
+                                    }
+                                }
+                                None => {}
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+        });
+    drop(staged_words);
+
+    if resolved_count > 0 {
+        let mut words_with_no_definitions = app_state.words_with_no_definitions.lock().unwrap();
+        *words_with_no_definitions = words_with_no_definitions.saturating_sub(resolved_count);
+    }
+    for (headword, spelling) in alternate_lookups {
+        app_state.look_up_alternate_spelling(headword, spelling);
+    }
+}
+
+enum AttentionAction {
+    LookUp(String),
+    UseDraft,
+}
+
+fn draw_needs_attention_row(staged_word: &mut StagedWord, ui: &mut egui::Ui) -> Option<AttentionAction> {
+    let mut action = None;
+    let KoboWord { headword, lookups, .. } = &staged_word.kobo_word;
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(headword)
+            .color(Color32::LIGHT_BLUE)
+            .font(FontId::new(18.0, FontFamily::Proportional)));
+        if lookups.len() > 1 || lookups.first() != Some(headword) {
+            ui.label(RichText::new(format!("looked up as {}", lookups.join(", ")))
+                .color(Color32::GRAY)
+                .italics());
+        }
+    });
+    if let Some(missing_reason) = &staged_word.missing_reason {
+        ui.label(RichText::new(missing_reason).color(Color32::GRAY).small());
+    }
+
+    let draft = &mut staged_word.draft;
+    ui.horizontal(|ui| {
+        ui.label("Try another spelling:");
+        ui.add(
+            egui::TextEdit::singleline(&mut draft.alternate_spelling)
+                .hint_text("e.g. the lemma or the British spelling")
+                .desired_width(200.0)
+        );
+        let spelling = draft.alternate_spelling.trim().to_string();
+        if ui.add_enabled(
+            !spelling.is_empty() && !draft.lookup_in_progress,
+            egui::Button::new("Look up")
+        ).clicked() {
+            action = Some(AttentionAction::LookUp(spelling));
+        }
+        if draft.lookup_in_progress {
+            ui.spinner();
+        }
+    });
+    if let Some(lookup_error) = &draft.lookup_error {
+        ui.label(RichText::new(lookup_error).color(Color32::LIGHT_RED));
+    }
+
+    egui::Grid::new("manual_definition")
+        .num_columns(3)
+        .show(ui, |ui| {
+            for (label, text, hint) in [
+                ("Definition:", &mut draft.meaning, "what the word means"),
+                ("Examples:", &mut draft.examples, "one example per line"),
+            ] {
+                ui.label(label);
+                ui.add(
+                    egui::TextEdit::multiline(text)
+                        .hint_text(hint)
+                        .desired_rows(1)
+                        .desired_width(f32::INFINITY)
+                );
+                if ui.small_button("Paste").on_hover_text("Paste from the clipboard").clicked() {
+                    if let Some(clipboard_text) = clipboard_text() {
+                        text.push_str(&clipboard_text);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    if ui.add_enabled(
+        !draft.meaning.trim().is_empty(),
+        egui::Button::new("Use this definition")
+    ).clicked() {
+        action = Some(AttentionAction::UseDraft);
+    }
+    action
+}
+
+fn clipboard_text() -> Option<String> {
+    let clipboard_text = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text());
+    match clipboard_text {
+        Ok(clipboard_text) => Some(clipboard_text),
+        Err(err) => {
+            eprintln!("Could not read the clipboard: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Returns `true` when the user asked to ignore the word from now on.
+fn draw_staged_word_row(staged_word: &mut StagedWord, ui: &mut egui::Ui) -> bool {
+    let mut ignore_clicked = false;
+    ui.horizontal(|ui| {
+        ui.add_enabled(
+            staged_word.has_definition(),
+            egui::Checkbox::without_text(&mut staged_word.include)
+        );
+        let KoboWord { headword, lookups, .. } = &staged_word.kobo_word;
+        ui.label(RichText::new(headword)
+            .color(Color32::LIGHT_BLUE)
+            .font(FontId::new(18.0, FontFamily::Proportional)));
+        if lookups.len() > 1 || lookups.first() != Some(headword) {
+            ui.label(RichText::new(format!("looked up as {}", lookups.join(", ")))
+                .color(Color32::GRAY)
+                .italics());
+        }
+        if !staged_word.has_definition() {
+            ui.label(RichText::new("no definition found")
+                .color(Color32::LIGHT_RED)
+                .italics())
+                .on_hover_text(staged_word.missing_reason.as_deref().unwrap_or_default());
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.small_button("Ignore").on_hover_text("Never suggest this word again").clicked() {
+                ignore_clicked = true;
+            }
+        });
+    });
+
+    let StagedWord { word, selected_meanings, include, .. } = staged_word;
+    let Some(word) = word else {
+        return ignore_clicked;
+    };
+
+    ui.add_enabled_ui(*include, |ui| {
+        egui::CollapsingHeader::new("Edit")
+            .id_salt("edit")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Front:");
+                    ui.text_edit_singleline(&mut word.word);
+                });
+                for (meaning_index, meaning_with_examples) in word.meanings_with_examples.iter_mut().enumerate() {
+                    ui.push_id(meaning_index, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut selected_meanings[meaning_index], "");
+                            let details = meaning_with_examples.details.summary();
+                            if !details.is_empty() {
+                                ui.label(RichText::new(details).color(Color32::GRAY).small());
+                            }
+                            ui.add(
+                                egui::TextEdit::multiline(&mut meaning_with_examples.meaning)
+                                    .desired_rows(1)
+                                    .desired_width(f32::INFINITY)
+                            );
+                        });
+                        for example in meaning_with_examples.examples.iter_mut() {
+                            ui.horizontal(|ui| {
+                                ui.add_space(30.0);
+                                ui.add(
+                                    egui::TextEdit::singleline(example)
+                                        .desired_width(f32::INFINITY)
+                                );
+                            });
+                        }
+                    });
+                }
+            });
+
+        egui::CollapsingHeader::new("Preview")
+            .id_salt("preview")
+            .show(ui, |ui| {
+                if let Some(approved_word) = staged_word_preview(word, selected_meanings) {
+                    draw_card_preview(&approved_word, ui);
+                } else {
+                    ui.label(RichText::new("Nothing selected, this word will be skipped")
+                        .color(Color32::LIGHT_RED));
+                }
+            });
+    });
+    ignore_clicked
+}
+
+fn staged_word_preview(word: &Word, selected_meanings: &[bool]) -> Option<Word> {
+    let staged_word = StagedWord {
+        kobo_word: KoboWord::new(word.word.clone()),
+        include: true,
+        word: Some(word.clone()),
+        selected_meanings: selected_meanings.to_vec(),
+        provider_url: None,
+        missing_reason: None,
+        draft: Default::default(),
+    };
+    staged_word.approved_word()
+}
+
+/// Renders a card the way the default Anki template displays it.
+pub fn draw_card_preview(word: &Word, ui: &mut egui::Ui) {
+    egui::Frame::group(ui.style())
+        .fill(Color32::from_rgb(30, 30, 46))
+        .show(ui, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(RichText::new(&word.word)
+                    .color(Color32::RED)
+                    .font(FontId::new(36.0, FontFamily::Proportional)));
+                let pronunciation = [("UK", &word.uk_ipa), ("US", &word.us_ipa)]
+                    .iter()
+                    .filter_map(|(region, ipa)| Some(format!("{} /{}/", region, ipa.as_ref()?)))
+                    .collect::<Vec<String>>();
+                if !pronunciation.is_empty() {
+                    ui.label(RichText::new(pronunciation.join("  ")).color(Color32::GRAY));
+                }
+                ui.separator();
+                for meaning_with_examples in &word.meanings_with_examples {
+                    let details = meaning_with_examples.details.summary();
+                    if !details.is_empty() {
+                        ui.label(RichText::new(details)
+                            .color(Color32::GRAY)
+                            .font(FontId::new(10.0, FontFamily::Proportional)));
+                    }
+                    ui.label(RichText::new(&meaning_with_examples.meaning)
+                        .color(Color32::from_rgb(0, 255, 255))
+                        .font(FontId::new(18.0, FontFamily::Proportional)));
+                    for example in &meaning_with_examples.examples {
+                        ui.label(RichText::new(example)
+                            .color(Color32::WHITE)
+                            .font(FontId::new(10.0, FontFamily::Proportional)));
+                    }
+                }
+            });
+        });
+}
+
+pub fn display_sync_approved_button(app_state: &mut AppState, ui: &mut egui::Ui) {
+    let approved_words_count = approved_words(&app_state.staged_words.lock().unwrap()).len();
+    ui.vertical_centered(|ui| {
+        let button = ui.add_enabled(
+            approved_words_count > 0,
+            egui::Button::new(
+                RichText::new(format!("Sync {} approved words", approved_words_count))
+                    .color(Color32::LIGHT_BLUE)
+                    .font(FontId::new(20.0, FontFamily::Proportional))
+            )
+        );
+        if button.clicked() {
+            app_state.start_sync();
+        }
+    });
+}
@@ -3,6 +3,7 @@
 use std::{path::PathBuf, sync::{Arc, Mutex}};
 
 use anki_bridge::prelude::CardsInfoResponse;
+use clap::Parser;
 use eframe::egui::{self, IconData};
 use egui_file_dialog::FileDialog;
 use tokio::runtime;
@@ -13,78 +14,322 @@ mod kobo;
 mod anki;
 mod cards;
 mod dictionary;
+mod storage;
+mod settings;
+mod daemon;
+mod report;
+mod sync_phase;
+
+use sync_phase::SyncPhase;
 
 struct Channels {
     anki_connection_status_rc: Option<tokio::sync::mpsc::Receiver<anki::AnkiConnectionStatus>>,
+    deck_names_rc: Option<tokio::sync::mpsc::Receiver<anyhow::Result<Vec<String>>>>,
+    anki_cards_rc: Option<tokio::sync::mpsc::Receiver<(String, anyhow::Result<(Vec<CardsInfoResponse>, Vec<String>)>)>>,
+    kobo_words_rc: Option<tokio::sync::mpsc::Receiver<(PathBuf, Result<Vec<kobo::KoboLookup>, String>)>>,
+    device_events_rc: tokio::sync::mpsc::Receiver<kobo::DeviceEvent>,
+    /// Lookups of alternate spellings, by the headword they were made for
+    alternate_lookups_rc: tokio::sync::mpsc::Receiver<(String, String, dictionary::Lookup)>,
 }
 
 struct AppState {
     file_dialog: FileDialog,
+    stop_words_file_dialog: FileDialog,
     async_rt: runtime::Runtime,
     kobo_path: Option<PathBuf>,
+    /// Readers reported by the device watcher, in the order they were attached
+    kobo_devices: Vec<PathBuf>,
     invalid_kobo_path: bool,
-    anki_client: Arc<anki::AnkiClient<'static>>,
+    kobo_db_error: Option<String>,
+    anki_client: Arc<anki::AnkiClient>,
+    definition_fetcher: Arc<dictionary::DefinitionFetcher>,
+    alternate_lookups_tx: tokio::sync::mpsc::Sender<(String, String, dictionary::Lookup)>,
     anki_connection_status: anki::AnkiConnectionStatus,
     channels: Channels,
     custom_path: bool,
     first_attempt_at_connecting_to_anki: bool,
     last_connection_attempt_time: std::time::Instant,
-    prepared_words_from_kobo: Option<Vec<String>>,
+    prepared_words_from_kobo: Option<Vec<kobo::KoboLookup>>,
     prepared_words_from_anki: Option<Vec<String>>,
     raw_cards_from_anki: Option<Vec<CardsInfoResponse>>,
     selected_deck_name: Option<String>,
     deck_names: Option<Vec<String>>,
-    sync_started: bool,
+    phase: SyncPhase,
     sync_progress: Arc<Mutex<f32>>,
     sync_started_at: Option<std::time::Instant>,
-    words_to_add: Option<Vec<String>>,
+    sync_error: Arc<Mutex<Option<String>>>,
+    words_to_add: Option<Vec<cards::KoboWord>>,
+    ignore_list: storage::IgnoreList,
+    ignored_words_count: usize,
+    known_words: Vec<cards::KoboWord>,
+    staging_task: Option<tokio::task::JoinHandle<()>>,
+    staging_progress: Arc<Mutex<f32>>,
+    /// The place of the words in the provider's fetch queue while they stream
+    staging_server_queue: Arc<Mutex<Option<dictionary::ServerQueue>>>,
+    staging_started_at: Option<std::time::Instant>,
+    staged_words: Arc<Mutex<Vec<cards::StagedWord>>>,
     server_connection_status: Arc<Mutex<bool>>,
     processed_words: Arc<Mutex<u32>>,
+    sync_report: Arc<Mutex<report::SyncReport>>,
+    report_file_dialog: FileDialog,
+    report_export_format: Option<report::ReportFormat>,
     words_with_no_definitions: Arc<Mutex<u32>>,
+    settings: settings::Settings,
+    settings_draft: settings::Settings,
+    settings_path: PathBuf,
+    settings_window_open: bool,
+    settings_error: Option<String>,
+    cli_args: settings::CliArgs,
+    session: storage::SessionState,
+    staging_requested: bool,
+    selected_profile_name: Option<String>,
 }
 
 impl AppState {
-    pub fn new(cc: &eframe::CreationContext) -> Self {
+    pub fn new(cc: &eframe::CreationContext, cli_args: settings::CliArgs) -> Self {
         layout::setup_custom_fonts(&cc.egui_ctx);
+        let (settings_path, settings) = load_settings(&cli_args);
+        // Settings an older build saved are kept, the panel shows what to fix
+        let settings_error = settings.validate()
+            .err()
+            .map(|err| format!("Please fix the settings: {:#}", err));
+        let session = storage::SessionState::load(cc.storage);
+        if let Some(profile_name) = &cli_args.profile {
+            if settings.profile(profile_name).is_none() {
+                eprintln!("There is no sync profile named {:?}", profile_name);
+            }
+        }
+        let selected_profile = cli_args.profile.clone()
+            .or(session.last_profile_name.clone())
+            .and_then(|profile_name| settings.profile(&profile_name).cloned());
+        let effective_settings = effective_settings(&settings, selected_profile.as_ref(), &cli_args);
+        // A deck given on the command line wins over the one of the profile,
+        // then the one used last time and finally the default deck from the
+        // settings file
+        let selected_deck_name = cli_args.deck.clone()
+            .or(selected_profile.as_ref().and_then(|profile| profile.deck.clone()))
+            .or(session.last_deck_name.clone())
+            .or(effective_settings.default_deck.clone());
+        let (alternate_lookups_tx, alternate_lookups_rc) = tokio::sync::mpsc::channel(16);
+        let custom_kobo_path = session.custom_kobo_path
+            .clone()
+            .filter(|path| kobo::validate_kobo_path(path));
         Self {
             file_dialog: FileDialog::new(),
+            stop_words_file_dialog: FileDialog::new(),
             async_rt: runtime::Builder::new_multi_thread()
                 .enable_all()
                 .build()
                 .unwrap(),
-            kobo_path: None,
+            kobo_path: custom_kobo_path.clone(),
+            kobo_devices: vec![],
             invalid_kobo_path: false,
-            anki_client: Arc::new(anki::AnkiClient::new()),
+            kobo_db_error: None,
+            anki_client: Arc::new(create_anki_client(&effective_settings)),
+            definition_fetcher: Arc::new(create_definition_fetcher(&effective_settings)),
+            alternate_lookups_tx,
             anki_connection_status: anki::AnkiConnectionStatus::Disconnected,
             channels: Channels {
                 anki_connection_status_rc: None,
+                deck_names_rc: None,
+                anki_cards_rc: None,
+                kobo_words_rc: None,
+                device_events_rc: kobo::spawn_device_watcher(Some(cc.egui_ctx.clone())),
+                alternate_lookups_rc,
             },
-            custom_path: false,
+            custom_path: custom_kobo_path.is_some(),
             first_attempt_at_connecting_to_anki: true,
             last_connection_attempt_time: std::time::Instant::now() - std::time::Duration::from_secs(5),
             prepared_words_from_kobo: None,
             prepared_words_from_anki: None,
-            selected_deck_name: None,
+            selected_deck_name,
             deck_names: None,
             raw_cards_from_anki: None,
-            sync_started: false,
+            phase: SyncPhase::Detecting,
             sync_progress: Arc::new(Mutex::new(0.0)),
             sync_started_at: None,
+            sync_error: Arc::new(Mutex::new(None)),
             words_to_add: None,
+            ignore_list: storage::IgnoreList::load().unwrap_or_else(|err| {
+                eprintln!("Could not load the ignore list: {:?}", err);
+                storage::IgnoreList::default()
+            }),
+            ignored_words_count: 0,
+            known_words: vec![],
+            staging_task: None,
+            staging_progress: Arc::new(Mutex::new(0.0)),
+            staging_server_queue: Arc::new(Mutex::new(None)),
+            staging_started_at: None,
+            staged_words: Arc::new(Mutex::new(vec![])),
             server_connection_status: Arc::new(Mutex::new(true)),
             processed_words: Arc::new(Mutex::new(0)),
+            sync_report: Arc::new(Mutex::new(report::SyncReport::default())),
+            report_file_dialog: FileDialog::new(),
+            report_export_format: None,
             words_with_no_definitions: Arc::new(Mutex::new(0)),
+            settings_draft: settings.clone(),
+            settings,
+            settings_path,
+            settings_window_open: settings_error.is_some(),
+            settings_error,
+            cli_args,
+            session,
+            staging_requested: false,
+            selected_profile_name: selected_profile.map(|profile| profile.name),
+        }
+    }
+
+    /// Settings from the settings file with the selected profile and then
+    /// the command line and environment overrides applied on top.
+    pub fn effective_settings(&self) -> settings::Settings {
+        effective_settings(&self.settings, self.selected_profile(), &self.cli_args)
+    }
+
+    pub fn selected_profile(&self) -> Option<&settings::SyncProfile> {
+        self.selected_profile_name
+            .as_ref()
+            .and_then(|profile_name| self.settings.profile(profile_name))
+    }
+
+    pub fn word_filter(&self) -> kobo::WordFilter {
+        self.selected_profile()
+            .map(|profile| profile.word_filter())
+            .unwrap_or_default()
+    }
+
+    /// Switches to another profile (or back to the plain settings) and
+    /// reads everything again with its deck, reader, filters and providers.
+    pub fn select_profile(&mut self, profile_name: Option<String>) {
+        if self.phase == SyncPhase::Syncing || self.selected_profile_name == profile_name {
+            return;
+        }
+        self.selected_profile_name = profile_name;
+        self.session.last_profile_name = self.selected_profile_name.clone();
+        let effective_settings = self.effective_settings();
+        self.definition_fetcher = Arc::new(create_definition_fetcher(&effective_settings));
+        self.rescan();
+        if let Some(deck) = self.selected_profile().and_then(|profile| profile.deck.clone()) {
+            self.selected_deck_name = Some(deck);
+        }
+        let profile_device = self.kobo_devices
+            .iter()
+            .find(|device| self.is_profile_device(device))
+            .cloned();
+        if profile_device.is_some() {
+            self.custom_path = false;
+            self.select_kobo_path(profile_device);
+        }
+    }
+
+    /// Whether the reader has the serial number of the selected profile.
+    fn is_profile_device(&self, kobo_path: &PathBuf) -> bool {
+        match self.selected_profile().and_then(|profile| profile.serial.as_ref()) {
+            Some(serial) => kobo::read_kobo_serial(kobo_path).as_ref() == Some(serial),
+            None => false,
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: kobo::DeviceEvent) {
+        match event {
+            kobo::DeviceEvent::Attached(kobo_path) => {
+                if !self.kobo_devices.contains(&kobo_path) {
+                    self.kobo_devices.push(kobo_path.clone());
+                }
+                let current_is_profile_device = self.kobo_path
+                    .as_ref()
+                    .is_some_and(|current| self.is_profile_device(current));
+                if self.kobo_path.is_none() || (!current_is_profile_device && self.is_profile_device(&kobo_path)) {
+                    self.custom_path = false;
+                    self.select_kobo_path(Some(kobo_path));
+                }
+            }
+            kobo::DeviceEvent::Detached(kobo_path) => {
+                self.kobo_devices.retain(|device| device != &kobo_path);
+                if self.kobo_path.as_ref() == Some(&kobo_path) && !self.custom_path {
+                    self.select_kobo_path(self.kobo_devices.first().cloned());
+                }
+            }
+        }
+    }
 
+    pub fn apply_settings(&mut self, settings: settings::Settings) -> anyhow::Result<()> {
+        settings.save(&self.settings_path)?;
+        self.settings = settings;
+        // The profile may have been renamed or removed
+        if self.selected_profile().is_none() {
+            self.selected_profile_name = None;
         }
+        let effective_settings = self.effective_settings();
+        self.anki_client = Arc::new(create_anki_client(&effective_settings));
+        self.definition_fetcher = Arc::new(create_definition_fetcher(&effective_settings));
+        // Reconnect to the (maybe different) AnkiConnect and provider
+        self.anki_connection_status = anki::AnkiConnectionStatus::Disconnected;
+        self.channels.anki_connection_status_rc = None;
+        self.rescan();
+        Ok(())
+    }
+}
+
+
+fn load_settings(cli_args: &settings::CliArgs) -> (PathBuf, settings::Settings) {
+    let settings_path = cli_args.settings_path().unwrap_or_else(|err| {
+        eprintln!("Could not determine the settings path: {:?}", err);
+        PathBuf::from("settings.toml")
+    });
+    let settings = settings::Settings::load(&settings_path).unwrap_or_else(|err| {
+        eprintln!("Could not load the settings from {:?}, using the defaults: {:?}", settings_path, err);
+        settings::Settings::default()
+    });
+    (settings_path, settings)
+}
+
+fn effective_settings(
+    settings: &settings::Settings,
+    profile: Option<&settings::SyncProfile>,
+    cli_args: &settings::CliArgs,
+) -> settings::Settings {
+    match profile {
+        Some(profile) => cli_args.apply(&profile.apply(settings)),
+        None => cli_args.apply(settings),
     }
 }
 
+fn create_anki_client(settings: &settings::Settings) -> anki::AnkiClient {
+    anki::AnkiClient::new(
+        &settings.anki_connect_url,
+        settings.anki_connect_key.clone()
+    )
+}
+
+fn create_definition_fetcher(settings: &settings::Settings) -> dictionary::DefinitionFetcher {
+    let definition_fetcher = dictionary::DefinitionFetcher::new(
+        dictionary::FetcherConfig::new(&settings.provider_urls, settings.concurrency)
+    );
+    match dictionary::DefinitionCache::open_default() {
+        Ok(definition_cache) => definition_fetcher.with_definition_cache(definition_cache),
+        Err(err) => {
+            eprintln!("Could not open the definition cache, definitions will not be cached: {:?}", err);
+            definition_fetcher
+        }
+    }
+}
 
 impl eframe::App for AppState {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         catppuccin_egui::set_theme(ctx, catppuccin_egui::MOCHA);
         events::update_ui(self, ctx);
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if self.selected_deck_name.is_some() {
+            self.session.last_deck_name = self.selected_deck_name.clone();
+        }
+        if self.custom_path {
+            self.session.custom_kobo_path = self.kobo_path.clone();
+        }
+        self.session.last_profile_name = self.selected_profile_name.clone();
+        self.session.save(storage);
+    }
 }
 
 
@@ -109,6 +354,24 @@ fn load_icon() -> IconData {
 }
 fn main() -> eframe::Result<()> {
     env_logger::init();
+    let cli_args = settings::CliArgs::parse();
+    if cli_args.watch || cli_args.once {
+        let (_, settings) = load_settings(&cli_args);
+        let async_rt = runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let res = if cli_args.watch {
+            async_rt.block_on(daemon::run_watch_mode(settings, &cli_args))
+        } else {
+            async_rt.block_on(daemon::run_once(settings, &cli_args))
+        };
+        if let Err(err) = res {
+            daemon::log_error(&format!("{:#}", err));
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     let icon = Arc::new(load_icon());
     let mut native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder {
@@ -122,7 +385,7 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Kobo To Anki Sync Tool",
         native_options,
-        Box::new(|ctx| Ok(Box::new(AppState::new(ctx)))),
+        Box::new(|ctx| Ok(Box::new(AppState::new(ctx, cli_args)))),
     )
 }
 
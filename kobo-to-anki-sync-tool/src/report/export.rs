@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use crate::storage::app_data_dir;
+
+use super::SyncReport;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Json,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+impl SyncReport {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// A standalone page with a table per kind of outcome.
+    pub fn to_html(&self) -> String {
+        let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>Sync report: {}</title>\n", escape(&self.deck)));
+        html.push_str("<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse;margin-bottom:2em}td,th{border:1px solid #ccc;padding:4px 8px;text-align:left}</style>\n");
+        html.push_str("</head>\n<body>\n");
+        html.push_str(&format!("<h1>Sync report: {}</h1>\n<ul>\n", escape(&self.deck)));
+        if let Some(profile) = &self.profile {
+            html.push_str(&format!("<li>Profile: {}</li>\n", escape(profile)));
+        }
+        html.push_str(&format!("<li>Providers: {}</li>\n", escape(&self.provider_urls.join(", "))));
+        for (label, value) in [("Started", &self.started_at), ("Finished", &self.finished_at)] {
+            if let Some(value) = value {
+                html.push_str(&format!("<li>{}: {}</li>\n", label, escape(value)));
+            }
+        }
+        for line in self.summary() {
+            html.push_str(&format!("<li>{}</li>\n", escape(&line)));
+        }
+        html.push_str("</ul>\n");
+
+        push_table(&mut html, "Errors", &["Word", "Message"], self.errors
+            .iter()
+            .map(|error| vec![error.word.clone().unwrap_or_default(), error.message.clone()]));
+        push_table(&mut html, "Without a definition", &["Word", "Looked up as", "Reason"], self.without_definition
+            .iter()
+            .map(|missing| vec![missing.word.clone(), missing.forms.join(", "), missing.reason.clone()]));
+        push_table(&mut html, "Added", &["Word", "Looked up as", "Books", "Provider"], self.added
+            .iter()
+            .map(|added| vec![added.word.clone(), added.forms.join(", "), added.books.join("; "), added.provider_url.clone().unwrap_or_default()]));
+        push_table(&mut html, "Notes with new forms", &["Word", "Looked up as", "Books"], self.updated
+            .iter()
+            .map(|updated| vec![updated.word.clone(), updated.forms.join(", "), updated.books.join("; ")]));
+        push_table(&mut html, "Unchecked on the review screen", &["Word"], self.skipped
+            .iter()
+            .map(|word| vec![word.clone()]));
+        push_table(&mut html, "Already in the deck", &["Word"], self.duplicates
+            .iter()
+            .map(|word| vec![word.clone()]));
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Writes the report, adding the extension of the format when the path
+    /// has none. Returns the path that was written.
+    pub fn save(&self, path: &Path, format: ReportFormat) -> Result<PathBuf> {
+        let path = match path.extension() {
+            Some(_) => path.to_path_buf(),
+            None => path.with_extension(format.extension()),
+        };
+        let content = match format {
+            ReportFormat::Json => self.to_json()?,
+            ReportFormat::Html => self.to_html(),
+        };
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    /// Saves the report in both formats to the `reports` directory of the
+    /// app data directory, used by the headless modes. Returns the path of
+    /// the JSON report.
+    pub fn save_to_reports_dir(&self, name: &str) -> Result<PathBuf> {
+        let reports_dir = app_data_dir()?.join("reports");
+        std::fs::create_dir_all(&reports_dir)?;
+        let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+        let file_name = name
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+            .collect::<String>();
+        let path = reports_dir.join(format!("sync-{}-{}", timestamp, file_name));
+        self.save(&path, ReportFormat::Html)?;
+        self.save(&path, ReportFormat::Json)
+    }
+}
+
+fn push_table(html: &mut String, title: &str, headers: &[&str], rows: impl Iterator<Item = Vec<String>>) {
+    let rows = rows.collect::<Vec<Vec<String>>>();
+    if rows.is_empty() {
+        return;
+    }
+    html.push_str(&format!("<h2>{} ({})</h2>\n<table>\n<tr>", escape(title), rows.len()));
+    for header in headers {
+        html.push_str(&format!("<th>{}</th>", escape(header)));
+    }
+    html.push_str("</tr>\n");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<td>{}</td>", escape(&cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cards::KoboWord;
+
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape(r#"<b>"Tom" & Jerry</b>"#), "&lt;b&gt;&quot;Tom&quot; &amp; Jerry&lt;/b&gt;");
+        // Escaped once only
+        assert_eq!(escape("&amp;"), "&amp;amp;");
+    }
+
+    #[test]
+    fn test_save_adds_the_extension_of_the_format() {
+        let dir = std::env::temp_dir().join(format!("kobo-to-anki-report-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let report = SyncReport::new("Deck", None, &[], 0);
+
+        let path = report.save(&dir.join("report"), ReportFormat::Json).unwrap();
+        assert_eq!(path, dir.join("report.json"));
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with('{'));
+        let path = report.save(&dir.join("report"), ReportFormat::Html).unwrap();
+        assert_eq!(path, dir.join("report.html"));
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("<!DOCTYPE html>"));
+        // A chosen extension is kept
+        let path = report.save(&dir.join("report.txt"), ReportFormat::Html).unwrap();
+        assert_eq!(path, dir.join("report.txt"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_to_html() {
+        let mut report = SyncReport::new("Words <en>", Some("English"), &["https://provider.example".to_string()], 0);
+        let mut kobo_word = KoboWord::new("run".to_string());
+        kobo_word.lookups.push("running".to_string());
+        kobo_word.books = vec!["Book & Co".to_string()];
+        report.record_added("run", &kobo_word, Some("https://provider.example"));
+        report.record_error(Some("walk"), "Anki said <no>".to_string());
+
+        let html = report.to_html();
+        assert!(html.contains("<title>Sync report: Words &lt;en&gt;</title>"));
+        assert!(html.contains("<li>Profile: English</li>"));
+        assert!(html.contains("<h2>Added (1)</h2>"));
+        assert!(html.contains("<td>run</td><td>run, running</td><td>Book &amp; Co</td><td>https://provider.example</td>"));
+        assert!(html.contains("<td>walk</td><td>Anki said &lt;no&gt;</td>"));
+        // Empty tables are left out
+        assert!(!html.contains("Already in the deck ("));
+    }
+}
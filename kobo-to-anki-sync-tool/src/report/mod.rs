@@ -0,0 +1,6 @@
+mod export;
+mod sync_report;
+
+pub use export::ReportFormat;
+
+pub use sync_report::SyncReport;
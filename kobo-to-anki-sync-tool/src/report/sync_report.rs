@@ -0,0 +1,207 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cards::{KoboWord, StagedWord};
+
+/// What happened during one sync, shown when the sync is over and saved as
+/// JSON or HTML so failed words can be retried or filled in by hand.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SyncReport {
+    pub deck: String,
+    pub profile: Option<String>,
+    /// Providers in the order they were asked
+    pub provider_urls: Vec<String>,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// Time spent fetching definitions, in seconds
+    pub fetch_seconds: Option<f64>,
+    /// Time spent adding the cards to Anki, in seconds
+    pub sync_seconds: Option<f64>,
+    pub added: Vec<ReportedWord>,
+    /// Notes that were already in the deck and got new forms or books
+    pub updated: Vec<ReportedWord>,
+    /// Words skipped because the deck already has them
+    pub duplicates: Vec<String>,
+    /// Words with a definition that were unchecked on the review screen
+    pub skipped: Vec<String>,
+    pub without_definition: Vec<MissingDefinition>,
+    pub ignored_count: usize,
+    pub errors: Vec<ReportedError>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReportedWord {
+    pub word: String,
+    pub forms: Vec<String>,
+    pub books: Vec<String>,
+    pub provider_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MissingDefinition {
+    pub word: String,
+    pub forms: Vec<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReportedError {
+    /// The word that was being synced, `None` for errors of the whole sync
+    pub word: Option<String>,
+    pub message: String,
+}
+
+impl SyncReport {
+    pub fn new(deck: &str, profile: Option<&str>, provider_urls: &[String], ignored_count: usize) -> Self {
+        Self {
+            deck: deck.to_string(),
+            profile: profile.map(|profile| profile.to_string()),
+            provider_urls: provider_urls.to_vec(),
+            started_at: Some(httpdate::fmt_http_date(SystemTime::now())),
+            ignored_count,
+            ..Default::default()
+        }
+    }
+
+    /// Notes the words that won't be synced after the review: the ones
+    /// without a definition and the ones the user unchecked.
+    pub fn record_review(&mut self, staged_words: &[StagedWord]) {
+        for staged_word in staged_words {
+            if !staged_word.has_definition() {
+                self.without_definition.push(MissingDefinition {
+                    word: staged_word.kobo_word.headword.clone(),
+                    forms: staged_word.kobo_word.lookups.clone(),
+                    reason: staged_word.missing_reason.clone().unwrap_or_default(),
+                });
+            } else if staged_word.approved_word().is_none() {
+                self.skipped.push(staged_word.kobo_word.headword.clone());
+            }
+        }
+    }
+
+    pub fn record_added(&mut self, word: &str, kobo_word: &KoboWord, provider_url: Option<&str>) {
+        self.added.push(ReportedWord {
+            word: word.to_string(),
+            forms: kobo_word.lookups.clone(),
+            books: kobo_word.books.clone(),
+            provider_url: provider_url.map(|provider_url| provider_url.to_string()),
+        });
+    }
+
+    pub fn record_updated(&mut self, word: &str, kobo_word: &KoboWord) {
+        self.updated.push(ReportedWord {
+            word: word.to_string(),
+            forms: kobo_word.lookups.clone(),
+            books: kobo_word.books.clone(),
+            provider_url: None,
+        });
+    }
+
+    pub fn record_duplicate(&mut self, word: &str) {
+        if !self.duplicates.iter().any(|duplicate| duplicate == word) {
+            self.duplicates.push(word.to_string());
+        }
+    }
+
+    pub fn record_error(&mut self, word: Option<&str>, message: String) {
+        self.errors.push(ReportedError {
+            word: word.map(|word| word.to_string()),
+            message,
+        });
+    }
+
+    pub fn record_fetch_time(&mut self, fetch_time: Duration) {
+        self.fetch_seconds = Some(fetch_time.as_secs_f64());
+    }
+
+    pub fn finish(&mut self, sync_time: Duration) {
+        self.sync_seconds = Some(sync_time.as_secs_f64());
+        self.finished_at = Some(httpdate::fmt_http_date(SystemTime::now()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.started_at.is_none()
+    }
+
+    /// One line per kind of outcome, for the window and the logs.
+    pub fn summary(&self) -> Vec<String> {
+        let mut summary = vec![
+            format!("Cards added: {}", self.added.len()),
+            format!("Notes with new forms: {}", self.updated.len()),
+            format!("Already in the deck: {}", self.duplicates.len()),
+            format!("Without a definition: {}", self.without_definition.len()),
+            format!("Unchecked on the review screen: {}", self.skipped.len()),
+            format!("Ignored: {}", self.ignored_count),
+            format!("Errors: {}", self.errors.len()),
+        ];
+        if let Some(fetch_seconds) = self.fetch_seconds {
+            summary.push(format!("Fetching definitions took {:.1}s", fetch_seconds));
+        }
+        if let Some(sync_seconds) = self.sync_seconds {
+            summary.push(format!("Adding the cards took {:.1}s", sync_seconds));
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anki::{MeaningWithExamples, Word};
+    use crate::dictionary::Lookup;
+
+    use super::*;
+
+    fn staged_word(headword: &str, lookup: Lookup) -> StagedWord {
+        StagedWord::new(KoboWord::new(headword.to_string()), lookup)
+    }
+
+    fn found(word: &str) -> Lookup {
+        Lookup::Found {
+            word: Word {
+                word: word.to_string(),
+                meanings_with_examples: vec![MeaningWithExamples {
+                    meaning: "a meaning".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            provider_url: "https://provider.example".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_record_review_counts_missing_and_unchecked_words() {
+        let mut unchecked = staged_word("sad", found("sad"));
+        unchecked.include = false;
+        let staged_words = vec![
+            staged_word("happy", found("happy")),
+            unchecked,
+            staged_word("qwxz", Lookup::NotFound),
+            staged_word("zzyq", Lookup::Failed("timed out".to_string())),
+        ];
+        let mut report = SyncReport::new("Deck", None, &[], 0);
+        report.record_review(&staged_words);
+
+        assert_eq!(report.skipped, vec!["sad".to_string()]);
+        assert_eq!(report.without_definition.len(), 2);
+        assert_eq!(report.without_definition[0].word, "qwxz");
+        assert_eq!(report.without_definition[0].reason, "Not found by any words provider");
+        assert_eq!(report.without_definition[1].reason, "Lookup failed: timed out");
+    }
+
+    #[test]
+    fn test_record_duplicate_counts_every_word_once() {
+        let mut report = SyncReport::new("Deck", None, &[], 0);
+        report.record_duplicate("happy");
+        report.record_duplicate("sad");
+        report.record_duplicate("happy");
+
+        assert_eq!(report.duplicates, vec!["happy".to_string(), "sad".to_string()]);
+        assert!(report.summary().contains(&"Already in the deck: 2".to_string()));
+    }
+}
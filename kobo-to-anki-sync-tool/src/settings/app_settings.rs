@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::anki::{CardTemplate, DEFAULT_ANKI_CONNECT_URL};
+use crate::cards::Language;
+use crate::dictionary::DEFAULT_CONCURRENCY;
+
+use super::SyncProfile;
+
+pub const DEFAULT_PROVIDER_URL: &str = "https://kobo-to-anki.usiiaa.top";
+
+/// Everything the user can change from the settings panel, persisted as TOML.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Providers are tried in order until one of them knows the word
+    pub provider_urls: Vec<String>,
+    pub anki_connect_url: String,
+    pub anki_connect_key: Option<String>,
+    pub default_deck: Option<String>,
+    pub card_template: CardTemplate,
+    /// Language whose rules turn looked up forms into their headword, lookups
+    /// are only normalised when `None`
+    pub lemmatisation: Option<Language>,
+    pub concurrency: usize,
+    pub watch: WatchSettings,
+    pub profiles: Vec<SyncProfile>,
+}
+
+/// Used by `--watch`, which syncs on its own whenever a known reader is
+/// connected.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WatchSettings {
+    /// Serial numbers of the readers that are synced automatically
+    pub known_serials: Vec<String>,
+    /// Run through the shell after every automatic sync
+    pub hook_command: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            provider_urls: vec![DEFAULT_PROVIDER_URL.to_string()],
+            anki_connect_url: DEFAULT_ANKI_CONNECT_URL.to_string(),
+            anki_connect_key: None,
+            default_deck: None,
+            card_template: CardTemplate::default(),
+            lemmatisation: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            watch: WatchSettings::default(),
+            profiles: vec![],
+        }
+    }
+}
+
+impl Settings {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the config directory"))?
+            .join("kobo-to-anki-sync-tool");
+        Ok(config_dir.join("settings.toml"))
+    }
+
+    /// Missing files are not an error, the defaults are used instead. The
+    /// settings aren't validated here, so that a file an older build wrote
+    /// is never replaced by the defaults, see [`Settings::validate`].
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Settings::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        let settings = toml::from_str::<Settings>(&content)?;
+        Ok(settings)
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&SyncProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Words can't be looked up without a provider, and profiles are picked
+    /// by name, so every profile needs its own.
+    pub fn validate(&self) -> Result<()> {
+        if self.provider_urls.iter().all(|provider_url| provider_url.trim().is_empty()) {
+            anyhow::bail!("At least one words provider URL is needed");
+        }
+        let mut names = HashSet::new();
+        for profile in &self.profiles {
+            let name = profile.name.trim();
+            if name.is_empty() {
+                anyhow::bail!("Every sync profile needs a name");
+            }
+            if !names.insert(name) {
+                anyhow::bail!("There is more than one sync profile named \"{}\"", name);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string_pretty(&self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_need_a_unique_name() {
+        let mut settings = Settings {
+            profiles: vec![SyncProfile::new("German"), SyncProfile::new("French")],
+            ..Default::default()
+        };
+        assert!(settings.validate().is_ok());
+
+        settings.profiles.push(SyncProfile::new(" German "));
+        assert!(settings.validate().is_err());
+        settings.profiles[2].name = " ".to_string();
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_provider_urls_cant_be_empty() {
+        let mut settings = Settings::default();
+        assert!(settings.validate().is_ok());
+
+        settings.provider_urls = vec![];
+        assert!(settings.validate().is_err());
+        settings.provider_urls = vec![" ".to_string()];
+        assert!(settings.validate().is_err());
+
+        let path = std::env::temp_dir().join(format!("kobo-to-anki-settings-{}.toml", std::process::id()));
+        assert!(settings.save(&path).is_err());
+        // Loaded as they are, to be fixed in the settings panel
+        std::fs::write(&path, "provider_urls = []\n").unwrap();
+        let loaded = Settings::load(&path).unwrap();
+        assert!(loaded.provider_urls.is_empty());
+        assert!(loaded.validate().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,9 @@
+mod app_settings;
+mod overrides;
+mod profiles;
+
+pub use app_settings::Settings;
+
+pub use overrides::CliArgs;
+
+pub use profiles::SyncProfile;
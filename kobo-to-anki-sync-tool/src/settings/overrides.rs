@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use super::Settings;
+
+/// Command line and environment overrides. They are applied on top of the
+/// settings file for this run only and are never written back to it.
+#[derive(Debug, Clone, Default, Parser)]
+#[command(version, about = "Create Anki cards from the words you looked up on your Kobo")]
+pub struct CliArgs {
+    /// Settings file to use instead of the default one
+    #[arg(long, env = "KOBO_TO_ANKI_SETTINGS")]
+    pub settings: Option<PathBuf>,
+
+    /// Words provider URL, can be given multiple times to build a fallback chain
+    #[arg(long = "provider-url", env = "KOBO_TO_ANKI_PROVIDER_URLS", value_delimiter = ',')]
+    pub provider_urls: Vec<String>,
+
+    #[arg(long, env = "KOBO_TO_ANKI_ANKI_CONNECT_URL")]
+    pub anki_connect_url: Option<String>,
+
+    #[arg(long, env = "KOBO_TO_ANKI_ANKI_CONNECT_KEY", hide_env_values = true)]
+    pub anki_connect_key: Option<String>,
+
+    /// Deck to select on startup
+    #[arg(long, env = "KOBO_TO_ANKI_DECK")]
+    pub deck: Option<String>,
+
+    /// How many definitions are fetched at the same time
+    #[arg(long, env = "KOBO_TO_ANKI_CONCURRENCY")]
+    pub concurrency: Option<usize>,
+
+    /// Sync profile to use, from the `[[profiles]]` in the settings file
+    #[arg(long, env = "KOBO_TO_ANKI_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Run without a window and sync whenever a known reader is connected
+    #[arg(long, conflicts_with = "once")]
+    pub watch: bool,
+
+    /// Run without a window, sync the connected reader once and exit
+    #[arg(long)]
+    pub once: bool,
+
+    /// Command run after every sync in watch mode
+    #[arg(long, env = "KOBO_TO_ANKI_HOOK_COMMAND")]
+    pub hook_command: Option<String>,
+}
+
+impl CliArgs {
+    pub fn settings_path(&self) -> anyhow::Result<PathBuf> {
+        match &self.settings {
+            Some(path) => Ok(path.clone()),
+            None => Settings::default_path(),
+        }
+    }
+
+    pub fn has_overrides(&self) -> bool {
+        !self.provider_urls.is_empty()
+            || self.anki_connect_url.is_some()
+            || self.anki_connect_key.is_some()
+            || self.deck.is_some()
+            || self.concurrency.is_some()
+            || self.hook_command.is_some()
+    }
+
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        let mut settings = settings.clone();
+        if !self.provider_urls.is_empty() {
+            settings.provider_urls = self.provider_urls.clone();
+        }
+        if let Some(anki_connect_url) = &self.anki_connect_url {
+            settings.anki_connect_url = anki_connect_url.clone();
+        }
+        if let Some(anki_connect_key) = &self.anki_connect_key {
+            settings.anki_connect_key = Some(anki_connect_key.clone());
+        }
+        if let Some(deck) = &self.deck {
+            settings.default_deck = Some(deck.clone());
+        }
+        if let Some(concurrency) = self.concurrency {
+            settings.concurrency = concurrency;
+        }
+        if let Some(hook_command) = &self.hook_command {
+            settings.watch.hook_command = Some(hook_command.clone());
+        }
+        settings
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use crate::anki::CardTemplate;
+use crate::cards::Language;
+use crate::kobo::WordFilter;
+
+use super::Settings;
+
+/// A named set of choices for one kind of sync, e.g. German lookups from
+/// one reader into a German deck. Anything left empty falls back to the
+/// regular settings.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SyncProfile {
+    pub name: String,
+    /// Serial number of the reader this profile syncs, any reader when empty
+    pub serial: Option<String>,
+    /// Only words looked up in this dictionary (`DictSuffix`, e.g. `-de`)
+    pub dict_suffix: Option<String>,
+    /// Only words looked up in books whose title or path contains this
+    pub book_filter: Option<String>,
+    pub deck: Option<String>,
+    pub card_template: Option<CardTemplate>,
+    pub lemmatisation: Option<Language>,
+    pub provider_urls: Vec<String>,
+}
+
+impl SyncProfile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The settings with this profile's deck, template, lemmatisation and
+    /// providers on top.
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        let mut settings = settings.clone();
+        if let Some(deck) = &self.deck {
+            settings.default_deck = Some(deck.clone());
+        }
+        if let Some(card_template) = &self.card_template {
+            settings.card_template = card_template.clone();
+        }
+        if let Some(language) = self.lemmatisation {
+            settings.lemmatisation = Some(language);
+        }
+        if !self.provider_urls.is_empty() {
+            settings.provider_urls = self.provider_urls.clone();
+        }
+        settings
+    }
+
+    pub fn word_filter(&self) -> WordFilter {
+        WordFilter {
+            dict_suffix: self.dict_suffix.clone(),
+            book: self.book_filter.clone(),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+/// Directory where the app keeps everything that should survive a restart,
+/// e.g. `~/.local/share/kobo-to-anki-sync-tool` on Linux.
+pub fn app_data_dir() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the data directory"))?
+        .join("kobo-to-anki-sync-tool");
+    std::fs::create_dir_all(&data_dir)?;
+    Ok(data_dir)
+}
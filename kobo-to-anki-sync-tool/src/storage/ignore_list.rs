@@ -0,0 +1,94 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::cards::prepare_words;
+
+use super::app_data_dir;
+
+/// Words that should never become cards.
+///
+/// Words ignored one by one from the staging screen and words coming from
+/// imported stop-word lists are kept apart, so an imported list can be
+/// dropped without losing the hand-picked ones.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct IgnoreList {
+    ignored_words: BTreeSet<String>,
+    stop_words: BTreeSet<String>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl IgnoreList {
+    pub fn load() -> Result<Self> {
+        let path = app_data_dir()?.join("ignore_list.json");
+        let mut ignore_list = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str::<IgnoreList>(&content)?
+        } else {
+            IgnoreList::default()
+        };
+        ignore_list.path = Some(path);
+        Ok(ignore_list)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string_pretty(&self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.ignored_words.contains(word) || self.stop_words.contains(word)
+    }
+
+    pub fn ignore(&mut self, word: &str) -> Result<()> {
+        self.ignored_words.insert(word.to_string());
+        self.save()
+    }
+
+    pub fn clear_ignored_words(&mut self) -> Result<()> {
+        self.ignored_words.clear();
+        self.save()
+    }
+
+    /// Imports a stop-word list with one word per line. Empty lines and lines
+    /// starting with `#` are skipped. Returns how many new words were added.
+    pub fn import_stop_words(&mut self, path: &Path) -> Result<usize> {
+        let content = std::fs::read_to_string(path)?;
+        let words = content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string())
+            .collect::<Vec<String>>();
+        let before = self.stop_words.len();
+        self.stop_words.extend(
+            prepare_words(words)
+                .into_iter()
+                .filter(|word| !word.is_empty())
+        );
+        self.save()?;
+        Ok(self.stop_words.len() - before)
+    }
+
+    pub fn clear_stop_words(&mut self) -> Result<()> {
+        self.stop_words.clear();
+        self.save()
+    }
+
+    pub fn ignored_words_count(&self) -> usize {
+        self.ignored_words.len()
+    }
+
+    pub fn stop_words_count(&self) -> usize {
+        self.stop_words.len()
+    }
+}
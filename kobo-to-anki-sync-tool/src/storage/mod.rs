@@ -0,0 +1,9 @@
+mod data_dir;
+mod ignore_list;
+mod session;
+
+pub use data_dir::app_data_dir;
+
+pub use ignore_list::IgnoreList;
+
+pub use session::SessionState;
\ No newline at end of file
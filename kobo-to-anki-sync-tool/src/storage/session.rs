@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+const SESSION_KEY: &str = "last_session";
+
+/// What the user picked during the last run, kept in eframe's storage next to
+/// the window layout.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub last_deck_name: Option<String>,
+    pub custom_kobo_path: Option<PathBuf>,
+    pub hide_words_without_definitions: bool,
+    pub last_profile_name: Option<String>,
+}
+
+impl SessionState {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value::<SessionState>(storage, SESSION_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SESSION_KEY, self);
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::{Arc, Mutex};
+
+use crate::cards::{find_new_words, Lemmatiser};
+use crate::report::SyncReport;
+use crate::AppState;
+
+/// Where the app is in a sync. The forward transitions happen in
+/// [`AppState::update_phase`], going back is done through
+/// [`AppState::change_deck`], [`AppState::rescan`] and [`AppState::new_sync`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncPhase {
+    /// Waiting for a reader, AnkiConnect, a deck or the words from either side
+    Detecting,
+    /// Both word lists are loaded and the new words are known
+    Ready,
+    /// Definitions are being fetched or reviewed in the staging table
+    Planning,
+    /// Approved cards are being added to Anki
+    Syncing,
+    Done,
+    Failed(String),
+}
+
+impl AppState {
+    pub fn update_phase(&mut self) {
+        match &self.phase {
+            SyncPhase::Detecting => {
+                if self.prepared_words_from_kobo.is_some() && self.prepared_words_from_anki.is_some() {
+                    self.plan_new_words();
+                    self.phase = SyncPhase::Ready;
+                }
+            }
+            SyncPhase::Ready => {
+                if self.prepared_words_from_kobo.is_none() || self.prepared_words_from_anki.is_none() {
+                    self.reset_plan();
+                } else if self.words_to_add.is_none() {
+                    // The ignore list changed
+                    self.plan_new_words();
+                }
+            }
+            SyncPhase::Planning => {
+                if self.server_connection_status.lock().unwrap().clone() == false {
+                    self.phase = SyncPhase::Failed("Could not connect to the server!".to_string());
+                }
+            }
+            SyncPhase::Syncing => {
+                if let Some(err) = self.sync_error.lock().unwrap().take() {
+                    self.phase = SyncPhase::Failed(err);
+                } else if self.sync_progress.lock().unwrap().clone() >= 100.0 {
+                    self.phase = SyncPhase::Done;
+                }
+            }
+            SyncPhase::Done | SyncPhase::Failed(_) => {}
+        }
+    }
+
+    /// Picks another deck, the cards of the new deck are loaded again and
+    /// the current plan is thrown away.
+    pub fn change_deck(&mut self, deck_name: String) {
+        if self.phase == SyncPhase::Syncing {
+            return;
+        }
+        self.selected_deck_name = Some(deck_name);
+        self.forget_anki_cards();
+        self.reset_plan();
+    }
+
+    /// Reads the deck list, the selected deck and the Kobo database again.
+    pub fn rescan(&mut self) {
+        if self.phase == SyncPhase::Syncing {
+            return;
+        }
+        self.deck_names = None;
+        self.channels.deck_names_rc = None;
+        self.forget_anki_cards();
+        self.forget_kobo_words();
+        self.reset_plan();
+    }
+
+    /// Starts over with the same deck and reader after a finished or failed
+    /// sync. The cards added by the previous sync are read back from Anki so
+    /// they are not offered again.
+    pub fn new_sync(&mut self) {
+        if !matches!(self.phase, SyncPhase::Done | SyncPhase::Failed(_)) {
+            return;
+        }
+        self.forget_anki_cards();
+        self.forget_kobo_words();
+        self.reset_plan();
+    }
+
+    /// Switches to another reader. Words read from the previous one are
+    /// dropped unless definitions are already being fetched for them.
+    pub fn select_kobo_path(&mut self, kobo_path: Option<std::path::PathBuf>) {
+        if self.kobo_path == kobo_path {
+            return;
+        }
+        self.kobo_path = kobo_path;
+        if matches!(self.phase, SyncPhase::Planning | SyncPhase::Syncing) {
+            return;
+        }
+        self.forget_kobo_words();
+        self.reset_plan();
+    }
+
+    fn forget_anki_cards(&mut self) {
+        self.raw_cards_from_anki = None;
+        self.prepared_words_from_anki = None;
+        self.channels.anki_cards_rc = None;
+    }
+
+    fn forget_kobo_words(&mut self) {
+        self.prepared_words_from_kobo = None;
+        self.kobo_db_error = None;
+        self.channels.kobo_words_rc = None;
+    }
+
+    fn plan_new_words(&mut self) {
+        let lemmatiser = self.effective_settings().lemmatisation.map(Lemmatiser::new);
+        let diff = find_new_words(
+            self.prepared_words_from_kobo.as_ref().unwrap(),
+            self.prepared_words_from_anki.as_ref().unwrap(),
+            &self.ignore_list,
+            lemmatiser.as_ref(),
+        );
+        self.words_to_add = Some(diff.new_words);
+        self.ignored_words_count = diff.ignored_count;
+        self.known_words = diff.known_words;
+    }
+
+    fn reset_plan(&mut self) {
+        if let Some(staging_task) = self.staging_task.take() {
+            staging_task.abort();
+        }
+        self.words_to_add = None;
+        self.ignored_words_count = 0;
+        self.known_words = vec![];
+        // New shared state instead of clearing the old one, so a task that is
+        // still winding down cannot write into the next plan
+        self.staged_words = Arc::new(Mutex::new(vec![]));
+        self.staging_progress = Arc::new(Mutex::new(0.0));
+        self.staging_server_queue = Arc::new(Mutex::new(None));
+        self.staging_started_at = None;
+        self.sync_progress = Arc::new(Mutex::new(0.0));
+        self.sync_started_at = None;
+        self.sync_error = Arc::new(Mutex::new(None));
+        self.processed_words = Arc::new(Mutex::new(0));
+        self.sync_report = Arc::new(Mutex::new(SyncReport::default()));
+        self.words_with_no_definitions = Arc::new(Mutex::new(0));
+        self.server_connection_status = Arc::new(Mutex::new(true));
+        self.staging_requested = false;
+        self.phase = SyncPhase::Detecting;
+    }
+}
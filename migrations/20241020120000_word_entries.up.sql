@@ -0,0 +1,3 @@
+-- Add up migration script here
+
+ALTER TABLE words ADD COLUMN IF NOT EXISTS entries TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,8 @@
+-- Add up migration script here
+
+-- Databases created by the old boot script already have the column, the
+-- ones created by the init migration don't. Words without meanings are
+-- stored with an empty list.
+ALTER TABLE words ADD COLUMN IF NOT EXISTS has_meaning BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE words SET has_meaning = TRUE WHERE meanings_with_examples <> '[]';
+ALTER TABLE words ALTER COLUMN has_meaning DROP DEFAULT;
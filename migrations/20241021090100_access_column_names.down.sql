@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+ALTER TABLE words RENAME COLUMN access_count TO acsess_count;
+ALTER TABLE words RENAME COLUMN last_access_at TO last_acsess_at;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+ALTER TABLE words RENAME COLUMN acsess_count TO access_count;
+ALTER TABLE words RENAME COLUMN last_acsess_at TO last_access_at;
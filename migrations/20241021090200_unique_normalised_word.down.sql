@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+-- The duplicates and the original spelling of the words are gone for good
+DROP INDEX IF EXISTS words_normalised_word_key;
@@ -0,0 +1,32 @@
+-- Add up migration script here
+
+-- Words are looked up trimmed and in lower case
+UPDATE words SET word = lower(btrim(word)) WHERE word <> lower(btrim(word));
+
+-- Concurrent lookups stored some words more than once. The row that is
+-- kept gets the access counts of all of them.
+UPDATE words SET
+    access_count = duplicates.access_count,
+    last_access_at = duplicates.last_access_at
+FROM (
+    SELECT word, SUM(access_count)::INT AS access_count, MAX(last_access_at) AS last_access_at
+    FROM words
+    GROUP BY word
+    HAVING COUNT(*) > 1
+) AS duplicates
+WHERE words.word = duplicates.word;
+
+-- Rows with a meaning and with entries are kept over the others, then the
+-- newest one
+DELETE FROM words WHERE id IN (
+    SELECT id FROM (
+        SELECT id, ROW_NUMBER() OVER (
+            PARTITION BY word
+            ORDER BY has_meaning DESC, (entries <> '[]') DESC, created_at DESC, id DESC
+        ) AS rank
+        FROM words
+    ) AS ranked
+    WHERE rank > 1
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS words_normalised_word_key ON words (lower(word));
@@ -0,0 +1,6 @@
+-- Add down migration script here
+
+ALTER TABLE words ALTER COLUMN meanings_with_examples TYPE TEXT USING meanings_with_examples::text;
+ALTER TABLE words ALTER COLUMN entries DROP DEFAULT;
+ALTER TABLE words ALTER COLUMN entries TYPE TEXT USING entries::text;
+ALTER TABLE words ALTER COLUMN entries SET DEFAULT '[]';
@@ -0,0 +1,19 @@
+-- Add up migration script here
+
+-- Rows that aren't valid JSON could never be read, they are dropped so the
+-- next lookup of the word fetches it again
+CREATE FUNCTION pg_temp.is_json(value TEXT) RETURNS BOOLEAN AS $$
+BEGIN
+    PERFORM value::jsonb;
+    RETURN TRUE;
+EXCEPTION WHEN others THEN
+    RETURN FALSE;
+END;
+$$ LANGUAGE plpgsql;
+
+DELETE FROM words WHERE NOT pg_temp.is_json(meanings_with_examples) OR NOT pg_temp.is_json(entries);
+
+ALTER TABLE words ALTER COLUMN meanings_with_examples TYPE JSONB USING meanings_with_examples::jsonb;
+ALTER TABLE words ALTER COLUMN entries DROP DEFAULT;
+ALTER TABLE words ALTER COLUMN entries TYPE JSONB USING entries::jsonb;
+ALTER TABLE words ALTER COLUMN entries SET DEFAULT '[]'::jsonb;
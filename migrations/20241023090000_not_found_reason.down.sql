@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+ALTER TABLE words DROP COLUMN IF EXISTS fetched_at;
+ALTER TABLE words DROP COLUMN IF EXISTS not_found_reason;
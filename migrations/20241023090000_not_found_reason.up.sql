@@ -0,0 +1,12 @@
+-- Add up migration script here
+
+-- Words without a meaning are kept for a while only, the reason decides how
+-- long. The ones stored so far came from pages Cambridge doesn't have, parse
+-- failures weren't stored.
+ALTER TABLE words ADD COLUMN not_found_reason TEXT CHECK (not_found_reason IN ('not_found', 'parse_failure'));
+UPDATE words SET not_found_reason = 'not_found' WHERE NOT has_meaning;
+
+-- When the definitions were last fetched from the Cambridge dictionary
+ALTER TABLE words ADD COLUMN fetched_at BIGINT;
+UPDATE words SET fetched_at = created_at;
+ALTER TABLE words ALTER COLUMN fetched_at SET NOT NULL;